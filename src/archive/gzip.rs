@@ -0,0 +1,100 @@
+use super::inflate::inflate;
+use super::{read_u16_le, read_u32_le, truncated};
+use errors::{Error, ErrorKind, Result};
+use hash::crc32;
+
+pub const MAGIC: &[u8] = &[0x1f, 0x8b];
+
+const HEADER_SIZE: usize = 10;
+const METHOD: usize = 2;
+const FLAGS: usize = 3;
+const METHOD_DEFLATE: u8 = 8;
+
+// Optional fields following the header
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// Decompress a gzip file (RFC 1952), checking the CRC-32 and size in its
+/// trailer
+pub fn extract(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < HEADER_SIZE {
+        return Err(truncated());
+    }
+    if data[METHOD] != METHOD_DEFLATE {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unsupported gzip compression method {}", data[METHOD]),
+        ));
+    }
+
+    let flags = data[FLAGS];
+    let mut offset = HEADER_SIZE;
+    if flags & FLAG_EXTRA != 0 {
+        offset += 2 + read_u16_le(data, offset)? as usize;
+    }
+    if flags & FLAG_NAME != 0 {
+        offset = skip_string(data, offset)?;
+    }
+    if flags & FLAG_COMMENT != 0 {
+        offset = skip_string(data, offset)?;
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        offset += 2;
+    }
+
+    let (output, size) = inflate(data.get(offset..).ok_or_else(truncated)?)?;
+    let trailer = offset + size;
+    let crc = read_u32_le(data, trailer)?;
+    let length = read_u32_le(data, trailer + 4)?;
+
+    if crc != crc32(&output) || length != output.len() as u32 {
+        return Err(Error::new(
+            ErrorKind::Validation,
+            "Checksum of the gzip contents doesn't match",
+        ));
+    }
+
+    Ok(output)
+}
+
+/// Offset right after a zero terminated string
+fn skip_string(data: &[u8], offset: usize) -> Result<usize> {
+    data.get(offset..)
+        .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+        .map(|end| offset + end + 1)
+        .ok_or_else(truncated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // "rustyboy rom " repeated 3 times, stored as game.gb
+    const GZIP: [u8; 44] = [
+        0x1f, 0x8b, 0x08, 0x08, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0x67, 0x61, 0x6d, 0x65, 0x2e,
+        0x67, 0x62, 0x00, 0x2b, 0x2a, 0x2d, 0x2e, 0xa9, 0x4c, 0xca, 0xaf, 0x54, 0x28, 0xca, 0xcf,
+        0x55, 0x28, 0xc2, 0xc9, 0x01, 0x00, 0x8d, 0xed, 0x2f, 0x33, 0x27, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(extract(&GZIP).unwrap(), b"rustyboy rom ".repeat(3));
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let mut data = GZIP.to_vec();
+        let crc = data.len() - 8;
+        data[crc] ^= 0xff;
+        let error = extract(&data).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::Validation));
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(extract(&GZIP[..GZIP.len() - 4]).is_err());
+        assert!(extract(&GZIP[..12]).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Decoder for the DEFLATE format (RFC 1951) used by zip and gzip
+
+use errors::{Error, ErrorKind, Result};
+
+const MAX_BITS: usize = 15;
+const END_OF_BLOCK: u16 = 256;
+
+// Block types
+const STORED: u32 = 0;
+const FIXED: u32 = 1;
+const DYNAMIC: u32 = 2;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Order in which dynamic blocks store the lengths of the code length code
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress a raw DEFLATE stream, returning the data and the number of
+/// bytes of `data` it took up.
+pub fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            STORED => stored_block(&mut reader, &mut output)?,
+            FIXED => {
+                let (literals, distances) = fixed_codes();
+                compressed_block(&mut reader, &mut output, &literals, &distances)?
+            }
+            DYNAMIC => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                compressed_block(&mut reader, &mut output, &literals, &distances)?
+            }
+            _ => return Err(malformed()),
+        }
+
+        if last {
+            return Ok((output, reader.consumed()));
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<()> {
+    reader.align();
+    let length = reader.bits(16)?;
+    let complement = reader.bits(16)?;
+    if length != !complement & 0xffff {
+        return Err(malformed());
+    }
+    output.extend_from_slice(reader.bytes(length as usize)?);
+    Ok(())
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<()> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        if symbol < END_OF_BLOCK {
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+
+        let index = (symbol - END_OF_BLOCK - 1) as usize;
+        if index >= LENGTH_BASE.len() {
+            return Err(malformed());
+        }
+        let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index])? as usize;
+
+        let index = distances.decode(reader)? as usize;
+        if index >= DISTANCE_BASE.len() {
+            return Err(malformed());
+        }
+        let distance = DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index])? as usize;
+        if distance > output.len() {
+            return Err(malformed());
+        }
+
+        // The copy may overlap the bytes it writes, repeating them
+        let start = output.len() - distance;
+        for i in 0..length {
+            let byte = output[start + i];
+            output.push(byte);
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    // Literal and distance code lengths are stored together, and repeats
+    // can cross from one to the other
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or_else(malformed)?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(malformed()),
+        };
+        for _ in 0..repeat {
+            lengths.push(length);
+        }
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(malformed());
+    }
+
+    let (literals, distances) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literals), Huffman::new(distances)))
+}
+
+/// Canonical Huffman code, stored as the number of codes of each length and
+/// the symbols sorted by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..=MAX_BITS {
+            for (symbol, &l) in lengths.iter().enumerate() {
+                if l as usize == length {
+                    symbols.push(symbol as u16);
+                }
+            }
+        }
+
+        Huffman { counts, symbols }
+    }
+
+    /// Read one code a bit at a time. Codes of each length are consecutive,
+    /// so the code is complete once it falls in the range of its length.
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(malformed())
+    }
+}
+
+/// Reads bits starting at the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            position: 0,
+            bit: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u8) -> Result<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.position).ok_or_else(malformed)?;
+            value |= u32::from((byte >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.position += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+    }
+
+    /// Whole bytes, after aligning to a byte boundary
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + n)
+            .ok_or_else(malformed)?;
+        self.position += n;
+        Ok(bytes)
+    }
+
+    /// Number of bytes read so far, counting a partially read one
+    fn consumed(&self) -> usize {
+        self.position + if self.bit != 0 { 1 } else { 0 }
+    }
+}
+
+fn malformed() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Malformed compressed data")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stored() {
+        let data = [0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'];
+        assert_eq!(inflate(&data).unwrap(), (b"hello".to_vec(), data.len()));
+    }
+
+    #[test]
+    fn test_fixed() {
+        // "hello hello hello", with the repeats as a back reference
+        let data = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(
+            inflate(&data).unwrap(),
+            (b"hello hello hello".to_vec(), data.len())
+        );
+    }
+
+    #[test]
+    fn test_dynamic() {
+        let expected: String = (0..60)
+            .map(|i| format!("{}:{};", i, "ab".repeat(i % 7)))
+            .collect();
+        let data = [
+            0x5d, 0xd1, 0xc9, 0x01, 0xc3, 0x20, 0x0c, 0x04, 0xc0, 0x96, 0xac, 0x0b, 0x30, 0x5b,
+            0x4d, 0xd2, 0x7f, 0x11, 0x11, 0xb7, 0xc8, 0xc7, 0x48, 0x0f, 0x8d, 0x58, 0xf3, 0x54,
+            0x50, 0xfd, 0x7c, 0xc1, 0xfe, 0xf1, 0x43, 0xfa, 0xe1, 0x85, 0xce, 0xc2, 0x4b, 0xdb,
+            0xa5, 0x37, 0x29, 0x34, 0xde, 0xe6, 0x8a, 0xd2, 0xc6, 0xdf, 0x31, 0x4e, 0xcf, 0x9a,
+            0x27, 0x3a, 0x00, 0x71, 0x14, 0x48, 0x6e, 0x82, 0xd4, 0xaf, 0xd0, 0x76, 0x80, 0xd2,
+            0x54, 0xf2, 0x56, 0x4a, 0x50, 0xde, 0xa8, 0xf0, 0x73, 0x2b, 0x4c, 0x15, 0xcc, 0x3d,
+            0xc9, 0xf0, 0xc1, 0x2b, 0x02, 0xf8, 0x24, 0x00, 0xa7, 0x4b, 0xc9, 0x7f, 0x4a, 0x71,
+            0xa5, 0xed, 0x81, 0x3c, 0xf3, 0x87, 0xac, 0x1c, 0x90, 0x93, 0x02, 0x12, 0x33, 0x40,
+            0xf4, 0x56, 0xc4, 0x2a, 0x24, 0x75, 0x25, 0x4f, 0xa5, 0x6c, 0xe5, 0xa4, 0x80, 0xc6,
+            0x0c, 0x50, 0xba, 0x15, 0xe5, 0x0a, 0x6d, 0x7b, 0xa0, 0xc3, 0x87, 0xda, 0x7e, 0x9d,
+            0x14, 0x94, 0x7c, 0x29, 0xe5, 0x4f, 0x79, 0x2b, 0xac, 0xed, 0x81, 0x0d, 0x1f, 0xb6,
+            0x72, 0xc0, 0x24, 0x3c, 0x72, 0xcc, 0x00, 0xb3, 0x5b, 0xb1, 0xe4, 0x4a, 0xee, 0x4a,
+            0x99, 0xca, 0xca, 0x81, 0x1f,
+        ];
+        assert_eq!(inflate(&data).unwrap(), (expected.into_bytes(), data.len()));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = [0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e'];
+        assert!(inflate(&data).is_err());
+        assert!(inflate(&[]).is_err());
+        // Reserved block type
+        assert!(inflate(&[0x07]).is_err());
+    }
+}
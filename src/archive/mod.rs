@@ -0,0 +1,91 @@
+//! Roms stored in zip and gzip archives
+
+mod gzip;
+pub mod inflate;
+mod zip;
+
+use errors::{Error, ErrorKind, Result};
+
+/// Extensions of the entries picked from zip archives
+const ROM_EXTENSIONS: [&str; 2] = [".gb", ".gbc"];
+
+/// Extract the rom if `data` is an archive, detecting the format from its
+/// magic bytes. Zip archives use the entry called `entry` or else the first
+/// Game Boy rom in it. Anything else is returned unchanged, and can't be
+/// given an entry.
+pub fn extract(data: Vec<u8>, entry: Option<&str>) -> Result<Vec<u8>> {
+    if data.starts_with(zip::MAGIC) {
+        return zip::extract(&data, |name| match entry {
+            Some(entry) => name == entry,
+            None => is_rom(name),
+        });
+    }
+
+    if let Some(entry) = entry {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Can't pick entry {}, the rom isn't a zip archive", entry),
+        ));
+    }
+
+    if data.starts_with(gzip::MAGIC) {
+        gzip::extract(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+fn is_rom(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ROM_EXTENSIONS
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(read_le(data, offset, 2)? as u16)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32> {
+    read_le(data, offset, 4)
+}
+
+fn read_le(data: &[u8], offset: usize, size: usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + size).ok_or_else(truncated)?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |n, &byte| (n << 8) | u32::from(byte)))
+}
+
+fn truncated() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Truncated archive")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_rom() {
+        assert!(is_rom("tetris.gb"));
+        assert!(is_rom("roms/ZELDA.GBC"));
+        assert!(!is_rom("readme.txt"));
+        assert!(!is_rom("gb"));
+    }
+
+    #[test]
+    fn test_extract_raw() {
+        let data = vec![0x00, 0xc3, 0x50, 0x01];
+        assert_eq!(extract(data.clone(), None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_entry_without_zip() {
+        let error = extract(vec![0x00, 0xc3, 0x50, 0x01], Some("tetris.gb")).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::InvalidInput));
+
+        let error = extract(gzip::MAGIC.to_vec(), Some("tetris.gb")).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::InvalidInput));
+    }
+}
@@ -0,0 +1,192 @@
+use super::inflate::inflate;
+use super::{read_u16_le, read_u32_le, truncated};
+use errors::{Error, ErrorKind, Result};
+use hash::crc32;
+
+pub const MAGIC: &[u8] = b"PK\x03\x04";
+
+const END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const CENTRAL_DIRECTORY_ENTRY: &[u8] = b"PK\x01\x02";
+const CENTRAL_DIRECTORY_ENTRY_SIZE: usize = 46;
+const LOCAL_HEADER_SIZE: usize = 30;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// Entry listed in the central directory, at the end of the archive
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    size: usize,
+    local_header: usize,
+}
+
+/// Decompress the first entry of a zip archive whose name is accepted by
+/// `select`
+pub fn extract<F>(data: &[u8], select: F) -> Result<Vec<u8>>
+where
+    F: Fn(&str) -> bool,
+{
+    let entry = entries(data)?
+        .into_iter()
+        .find(|entry| select(&entry.name))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "No matching rom in the zip archive",
+            )
+        })?;
+
+    // The local header repeats the name, and may have a different extra
+    // field than the central directory
+    let name_size = read_u16_le(data, entry.local_header + 26)? as usize;
+    let extra_size = read_u16_le(data, entry.local_header + 28)? as usize;
+    let begin = entry.local_header + LOCAL_HEADER_SIZE + name_size + extra_size;
+    let compressed = data
+        .get(begin..begin + entry.compressed_size)
+        .ok_or_else(truncated)?;
+
+    let output = match entry.method {
+        METHOD_STORED => compressed.to_vec(),
+        METHOD_DEFLATE => inflate(compressed)?.0,
+        method => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unsupported zip compression method {} for {}",
+                    method, entry.name
+                ),
+            ))
+        }
+    };
+
+    if output.len() != entry.size || crc32(&output) != entry.crc {
+        return Err(Error::new(
+            ErrorKind::Validation,
+            format!(
+                "Checksum of {} in the zip archive doesn't match",
+                entry.name
+            ),
+        ));
+    }
+
+    Ok(output)
+}
+
+/// Read the central directory, which, unlike the local headers, always has
+/// the sizes of each entry
+fn entries(data: &[u8]) -> Result<Vec<Entry>> {
+    // The end of central directory record is followed by a comment of up to
+    // 64 KiB, so it has to be searched for backwards
+    let end = (0..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+        .rev()
+        .find(|&i| data[i..].starts_with(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(truncated)?;
+
+    let count = read_u16_le(data, end + 10)? as usize;
+    let mut offset = read_u32_le(data, end + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if !data
+            .get(offset..)
+            .is_some_and(|d| d.starts_with(CENTRAL_DIRECTORY_ENTRY))
+        {
+            return Err(truncated());
+        }
+
+        let name_size = read_u16_le(data, offset + 28)? as usize;
+        let extra_size = read_u16_le(data, offset + 30)? as usize;
+        let comment_size = read_u16_le(data, offset + 32)? as usize;
+        let name_begin = offset + CENTRAL_DIRECTORY_ENTRY_SIZE;
+        let name = data
+            .get(name_begin..name_begin + name_size)
+            .ok_or_else(truncated)?;
+
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: read_u16_le(data, offset + 10)?,
+            crc: read_u32_le(data, offset + 16)?,
+            compressed_size: read_u32_le(data, offset + 20)? as usize,
+            size: read_u32_le(data, offset + 24)? as usize,
+            local_header: read_u32_le(data, offset + 42)? as usize,
+        });
+
+        offset = name_begin + name_size + extra_size + comment_size;
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // readme.txt (stored), game.gb (deflated) and other.gbc (stored)
+    const ZIP: [u8; 338] = [
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xf1,
+        0x2a, 0x9b, 0xe6, 0x09, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,
+        0x72, 0x65, 0x61, 0x64, 0x6d, 0x65, 0x2e, 0x74, 0x78, 0x74, 0x6e, 0x6f, 0x74, 0x20, 0x61,
+        0x20, 0x72, 0x6f, 0x6d, 0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+        0x00, 0x21, 0x00, 0x8d, 0xed, 0x2f, 0x33, 0x12, 0x00, 0x00, 0x00, 0x27, 0x00, 0x00, 0x00,
+        0x07, 0x00, 0x00, 0x00, 0x67, 0x61, 0x6d, 0x65, 0x2e, 0x67, 0x62, 0x2b, 0x2a, 0x2d, 0x2e,
+        0xa9, 0x4c, 0xca, 0xaf, 0x54, 0x28, 0xca, 0xcf, 0x55, 0x28, 0xc2, 0xc9, 0x01, 0x00, 0x50,
+        0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xf8, 0x93,
+        0x9a, 0x08, 0x09, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x6f,
+        0x74, 0x68, 0x65, 0x72, 0x2e, 0x67, 0x62, 0x63, 0x6f, 0x74, 0x68, 0x65, 0x72, 0x20, 0x72,
+        0x6f, 0x6d, 0x50, 0x4b, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x21, 0x00, 0xf1, 0x2a, 0x9b, 0xe6, 0x09, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x72, 0x65, 0x61, 0x64, 0x6d, 0x65, 0x2e, 0x74, 0x78, 0x74, 0x50, 0x4b,
+        0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00, 0x8d,
+        0xed, 0x2f, 0x33, 0x12, 0x00, 0x00, 0x00, 0x27, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x31, 0x00, 0x00, 0x00, 0x67,
+        0x61, 0x6d, 0x65, 0x2e, 0x67, 0x62, 0x50, 0x4b, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0xf8, 0x93, 0x9a, 0x08, 0x09, 0x00, 0x00, 0x00,
+        0x09, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x80, 0x01, 0x68, 0x00, 0x00, 0x00, 0x6f, 0x74, 0x68, 0x65, 0x72, 0x2e, 0x67, 0x62,
+        0x63, 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x03, 0x00, 0xa4, 0x00,
+        0x00, 0x00, 0x98, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_entries() {
+        let names: Vec<String> = entries(&ZIP)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, vec!["readme.txt", "game.gb", "other.gbc"]);
+    }
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            extract(&ZIP, |name| name.ends_with(".gb")).unwrap(),
+            b"rustyboy rom ".repeat(3)
+        );
+        assert_eq!(
+            extract(&ZIP, |name| name == "other.gbc").unwrap(),
+            b"other rom"
+        );
+        assert!(extract(&ZIP, |name| name == "missing.gb").is_err());
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let mut data = ZIP.to_vec();
+        // Last byte of the stored readme.txt
+        data[0x30] ^= 0xff;
+        let error = extract(&data, |name| name == "readme.txt").unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::Validation));
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(extract(&ZIP[..ZIP.len() - 1], |_| true).is_err());
+        assert!(extract(&ZIP[..4], |_| true).is_err());
+    }
+}
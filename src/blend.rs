@@ -0,0 +1,152 @@
+//! Frame blending, mimicking the slow response of the Game Boy LCD. Games
+//! that flicker objects on and off every frame rely on it to look
+//! transparent.
+
+use std::str::FromStr;
+
+use errors::{Error, ErrorKind, Result};
+
+// Share of the previous output kept by the exponential model, out of 256
+const PERSISTENCE: u32 = 154;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameBlending {
+    /// Show every frame as is
+    Off,
+    /// Show the average of every frame and the one before it
+    Mix,
+    /// Let every pixel fade towards its new colour over several frames, the
+    /// way the liquid crystals of the LCD do
+    Exponential,
+}
+
+impl FromStr for FrameBlending {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<FrameBlending> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(FrameBlending::Off),
+            "mix" => Ok(FrameBlending::Mix),
+            "exponential" => Ok(FrameBlending::Exponential),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown frame blending {}, expected one of: off, mix, exponential",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+/// Blends successive RGBA frames. It only sees the frames after they are
+/// drawn, so it can't affect the emulation.
+pub struct Blender {
+    mode: FrameBlending,
+    /// The previous frame for `Mix`, the previous output for `Exponential`
+    previous: Option<Vec<u8>>,
+}
+
+impl Blender {
+    pub fn new(mode: FrameBlending) -> Blender {
+        Blender {
+            mode,
+            previous: None,
+        }
+    }
+
+    pub fn mode(&self) -> FrameBlending {
+        self.mode
+    }
+
+    /// Blend a new frame with the ones before it, returning what the LCD
+    /// shows
+    pub fn blend(&mut self, frame: &[u8]) -> Vec<u8> {
+        let output: Vec<u8> = match self.previous {
+            Some(ref previous) if previous.len() == frame.len() => {
+                let persistence = match self.mode {
+                    FrameBlending::Off => 0,
+                    FrameBlending::Mix => 128,
+                    FrameBlending::Exponential => PERSISTENCE,
+                };
+                frame
+                    .iter()
+                    .zip(previous)
+                    .map(|(&new, &old)| mix(new, old, persistence))
+                    .collect()
+            }
+            _ => frame.to_vec(),
+        };
+
+        self.previous = Some(match self.mode {
+            FrameBlending::Exponential => output.clone(),
+            _ => frame.to_vec(),
+        });
+        output
+    }
+}
+
+/// `new` with `persistence` / 256 of `old` mixed in, rounded to the nearest
+fn mix(new: u8, old: u8, persistence: u32) -> u8 {
+    let mixed = (u32::from(new) * (256 - persistence) + u32::from(old) * persistence + 128) / 256;
+    let mixed = mixed as u8;
+
+    // Rounding alone would leave pixels one step short of their new colour
+    if mixed == old && new > old {
+        old + 1
+    } else if mixed == old && new < old {
+        old - 1
+    } else {
+        mixed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_off() {
+        let mut blender = Blender::new(FrameBlending::Off);
+        assert_eq!(blender.blend(&[0xff, 0x00]), vec![0xff, 0x00]);
+        assert_eq!(blender.blend(&[0x00, 0xff]), vec![0x00, 0xff]);
+    }
+
+    #[test]
+    fn test_mix() {
+        let mut blender = Blender::new(FrameBlending::Mix);
+        // Nothing to mix the first frame with
+        assert_eq!(blender.blend(&[0xff, 0x00]), vec![0xff, 0x00]);
+        assert_eq!(blender.blend(&[0x00, 0x00]), vec![0x80, 0x00]);
+        // Only the last frame counts
+        assert_eq!(blender.blend(&[0x00, 0x00]), vec![0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_exponential() {
+        let mut blender = Blender::new(FrameBlending::Exponential);
+        blender.blend(&[0xff]);
+
+        // Fades out over several frames
+        let first = blender.blend(&[0x00])[0];
+        let second = blender.blend(&[0x00])[0];
+        assert_eq!(first, 0x99);
+        assert!(second < first && second > 0);
+        for _ in 0..20 {
+            blender.blend(&[0x00]);
+        }
+        assert_eq!(blender.blend(&[0x00]), vec![0x00]);
+
+        // And back in
+        for _ in 0..20 {
+            blender.blend(&[0xff]);
+        }
+        assert_eq!(blender.blend(&[0xff]), vec![0xff]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("mix".parse::<FrameBlending>().unwrap(), FrameBlending::Mix);
+        assert!("motion".parse::<FrameBlending>().is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use errors::{Error, ErrorKind, Result};
+
+// DMG, MGB and SGB boot roms are 256 bytes long and are mapped at 0x0000.
+// The CGB boot rom is 2304 bytes long and is split in two areas, leaving
+// the cartridge header (0x0100-0x01ff) visible to the boot code.
+const SIZE_DMG: usize = 0x100;
+const SIZE_CGB: usize = 0x900;
+
+pub const LOW_BEGIN: usize = 0x0000;
+pub const LOW_END: usize = 0x00ff;
+pub const HIGH_BEGIN: usize = 0x0200;
+pub const HIGH_END: usize = 0x08ff;
+
+pub struct BootRom {
+    raw_data: Vec<u8>,
+}
+
+impl BootRom {
+    pub fn new(raw_data: Vec<u8>) -> Result<BootRom> {
+        match raw_data.len() {
+            SIZE_DMG | SIZE_CGB => Ok(BootRom { raw_data }),
+            n => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Invalid boot rom size: expected {} or {} bytes, got {}",
+                    SIZE_DMG, SIZE_CGB, n
+                ),
+            )),
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        self.raw_data.len() == SIZE_CGB
+    }
+
+    /// Area mapped at 0x0000-0x00ff
+    pub fn low(&self) -> &[u8] {
+        &self.raw_data[LOW_BEGIN..=LOW_END]
+    }
+
+    /// Area mapped at 0x0200-0x08ff, only present in CGB boot roms
+    pub fn high(&self) -> Option<&[u8]> {
+        if self.is_cgb() {
+            Some(&self.raw_data[HIGH_BEGIN..=HIGH_END])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_boot_rom_size() {
+        assert!(BootRom::new(vec![0; 0x100]).is_ok());
+        assert!(BootRom::new(vec![0; 0x900]).is_ok());
+        assert!(BootRom::new(vec![0; 0x200]).is_err());
+        assert!(BootRom::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_boot_rom_areas() {
+        let dmg = BootRom::new(vec![0x31; 0x100]).unwrap();
+        assert!(!dmg.is_cgb());
+        assert_eq!(dmg.low().len(), 0x100);
+        assert!(dmg.high().is_none());
+
+        let mut data = vec![0x00; 0x900];
+        data[0x200] = 0xaa;
+        let cgb = BootRom::new(data).unwrap();
+        assert!(cgb.is_cgb());
+        assert_eq!(cgb.high().unwrap().len(), 0x700);
+        assert_eq!(cgb.high().unwrap()[0], 0xaa);
+    }
+}
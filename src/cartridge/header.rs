@@ -0,0 +1,325 @@
+use std::fmt;
+
+use super::licensee::{Licensee, USE_NEW_LICENSEE_CODE};
+use super::CartridgeType;
+use u8_to_u16;
+
+//
+// Header layout
+//
+
+const ENTRY_POINT_BEGIN: usize = 0x100;
+const ENTRY_POINT_END: usize = 0x103;
+const LOGO_BEGIN: usize = 0x104;
+const LOGO_END: usize = 0x133;
+const TITLE_BEGIN: usize = 0x134;
+const TITLE_END: usize = 0x143;
+const MANUFACTURER_CODE_BEGIN: usize = 0x13f;
+const MANUFACTURER_CODE_END: usize = 0x142;
+const CGB_FLAG: usize = 0x143;
+const NEW_LICENSEE_CODE_BEGIN: usize = 0x144;
+const NEW_LICENSEE_CODE_END: usize = 0x145;
+const SGB_FLAG: usize = 0x146;
+const CARTRIDGE_TYPE: usize = 0x147;
+const ROM_SIZE: usize = 0x148;
+const RAM_SIZE: usize = 0x149;
+const DESTINATION_CODE: usize = 0x14a;
+const OLD_LICENSEE_CODE: usize = 0x14b;
+const MASK_ROM_VERSION: usize = 0x14c;
+const HEADER_CHECKSUM: usize = 0x14d;
+const GLOBAL_CHECKSUM_BEGIN: usize = 0x14e;
+const GLOBAL_CHECKSUM_END: usize = 0x14f;
+
+/// Size of a rom that holds the whole header
+pub const HEADER_SIZE: usize = GLOBAL_CHECKSUM_END + 1;
+
+/// Whether the cartridge makes use of the CGB hardware
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CgbSupport {
+    /// Made for the original Game Boy, runs in compatibility mode on a CGB
+    DmgOnly,
+    /// Uses CGB features when available, but also runs on older models
+    Enhanced,
+    /// Only runs on a CGB
+    CgbOnly,
+}
+
+/// Whether the cartridge makes use of the SGB functions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SgbSupport {
+    Unsupported,
+    Supported,
+}
+
+/// Region the cartridge was sold in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+impl fmt::Display for CgbSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            CgbSupport::DmgOnly => "DMG only",
+            CgbSupport::Enhanced => "CGB enhanced",
+            CgbSupport::CgbOnly => "CGB only",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for SgbSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            SgbSupport::Unsupported => "Unsupported",
+            SgbSupport::Supported => "Supported",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Destination::Japan => "Japan",
+            Destination::Overseas => "Overseas",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Cartridge information stored at 0x100-0x14f
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub entry_point: [u8; 4],
+    pub logo: [u8; 48],
+    pub title: String,
+    /// Only present in cartridges released after the CGB
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub new_licensee_code: [u8; 2],
+    pub sgb_flag: u8,
+    pub cartridge_type_code: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination_code: u8,
+    pub old_licensee_code: u8,
+    pub mask_rom_version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    /// Parse the header of a rom that is at least `HEADER_SIZE` bytes long
+    pub fn parse(data: &[u8]) -> CartridgeHeader {
+        let mut entry_point = [0; 4];
+        entry_point.copy_from_slice(&data[ENTRY_POINT_BEGIN..=ENTRY_POINT_END]);
+        let mut logo = [0; 48];
+        logo.copy_from_slice(&data[LOGO_BEGIN..=LOGO_END]);
+        let mut new_licensee_code = [0; 2];
+        new_licensee_code.copy_from_slice(&data[NEW_LICENSEE_CODE_BEGIN..=NEW_LICENSEE_CODE_END]);
+
+        let cgb_flag = data[CGB_FLAG];
+
+        // Newer cartridges use the last bytes of the title area for the
+        // manufacturer code and CGB flag. There is no flag telling whether a
+        // manufacturer code is present, but it is always 4 uppercase
+        // characters.
+        let manufacturer_code = &data[MANUFACTURER_CODE_BEGIN..=MANUFACTURER_CODE_END];
+        let has_manufacturer_code = cgb_flag & 0x80 == 0x80
+            && manufacturer_code
+                .iter()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        let title_end = if has_manufacturer_code {
+            MANUFACTURER_CODE_BEGIN - 1
+        } else if cgb_flag & 0x80 == 0x80 {
+            CGB_FLAG - 1
+        } else {
+            TITLE_END
+        };
+
+        CartridgeHeader {
+            entry_point,
+            logo,
+            title: parse_string(&data[TITLE_BEGIN..=title_end]),
+            manufacturer_code: if has_manufacturer_code {
+                Some(parse_string(manufacturer_code))
+            } else {
+                None
+            },
+            cgb_flag,
+            new_licensee_code,
+            sgb_flag: data[SGB_FLAG],
+            cartridge_type_code: data[CARTRIDGE_TYPE],
+            rom_size_code: data[ROM_SIZE],
+            ram_size_code: data[RAM_SIZE],
+            destination_code: data[DESTINATION_CODE],
+            old_licensee_code: data[OLD_LICENSEE_CODE],
+            mask_rom_version: data[MASK_ROM_VERSION],
+            header_checksum: data[HEADER_CHECKSUM],
+            global_checksum: u8_to_u16(data[GLOBAL_CHECKSUM_BEGIN], data[GLOBAL_CHECKSUM_END]),
+        }
+    }
+
+    pub fn cgb(&self) -> CgbSupport {
+        match self.cgb_flag & 0xc0 {
+            0xc0 => CgbSupport::CgbOnly,
+            0x80 => CgbSupport::Enhanced,
+            _ => CgbSupport::DmgOnly,
+        }
+    }
+
+    /// The SGB only enables its functions when the cartridge also uses the
+    /// new licensee code
+    pub fn sgb(&self) -> SgbSupport {
+        if self.old_licensee_code == USE_NEW_LICENSEE_CODE && self.sgb_flag == 0x03 {
+            SgbSupport::Supported
+        } else {
+            SgbSupport::Unsupported
+        }
+    }
+
+    pub fn licensee(&self) -> Licensee {
+        Licensee::new(self.old_licensee_code, self.new_licensee_code)
+    }
+
+    pub fn destination(&self) -> Option<Destination> {
+        match self.destination_code {
+            0x00 => Some(Destination::Japan),
+            0x01 => Some(Destination::Overseas),
+            _ => None,
+        }
+    }
+
+    pub fn cartridge_type(&self) -> Option<CartridgeType> {
+        CartridgeType::new(self.cartridge_type_code)
+    }
+
+    pub fn rom_size(&self) -> Option<usize> {
+        match self.rom_size_code {
+            0x00..=0x08 => Some(KB!(32) << self.rom_size_code),
+            0x52 => Some(KB!(1152)),
+            0x53 => Some(KB!(1280)),
+            0x54 => Some(KB!(1536)),
+            _ => None,
+        }
+    }
+
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code {
+            0x01 => Some(KB!(2)),
+            0x02 => Some(KB!(8)),
+            0x03 => Some(KB!(32)),
+            0x04 => Some(KB!(128)),
+            0x05 => Some(KB!(64)),
+            _ => None,
+        }
+    }
+}
+
+/// Header strings are upper case ASCII, padded with zeros
+fn parse_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches(&['\0', ' '][..])
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rom(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0; HEADER_SIZE];
+        rom[TITLE_BEGIN..TITLE_BEGIN + title.len()].copy_from_slice(title);
+        rom[CGB_FLAG] = cgb_flag;
+        rom
+    }
+
+    #[test]
+    fn test_title() {
+        let header = CartridgeHeader::parse(&rom(b"TETRIS", 0x00));
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.manufacturer_code, None);
+
+        let header = CartridgeHeader::parse(&rom(b"SIXTEEN CHARS 16", 0x00));
+        assert_eq!(header.title, "SIXTEEN CHARS 1");
+        assert_eq!(header.manufacturer_code, None);
+    }
+
+    #[test]
+    fn test_cgb_title() {
+        let header = CartridgeHeader::parse(&rom(b"POKEMON YELLOW", 0x80));
+        assert_eq!(header.title, "POKEMON YELLOW");
+        assert_eq!(header.manufacturer_code, None);
+
+        let header = CartridgeHeader::parse(&rom(b"ZELDA DX\0\0\0AZ7E", 0x80));
+        assert_eq!(header.title, "ZELDA DX");
+        assert_eq!(header.manufacturer_code, Some("AZ7E".to_string()));
+        assert_eq!(header.cgb(), CgbSupport::Enhanced);
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut data = rom(b"TEST", 0x00);
+        data[SGB_FLAG] = 0x03;
+        data[CARTRIDGE_TYPE] = 0x13;
+        data[ROM_SIZE] = 0x05;
+        data[RAM_SIZE] = 0x03;
+        data[DESTINATION_CODE] = 0x01;
+        data[OLD_LICENSEE_CODE] = 0x33;
+        data[NEW_LICENSEE_CODE_BEGIN] = b'0';
+        data[NEW_LICENSEE_CODE_END] = b'1';
+        data[MASK_ROM_VERSION] = 0x02;
+        data[HEADER_CHECKSUM] = 0xa5;
+        data[GLOBAL_CHECKSUM_BEGIN] = 0x12;
+        data[GLOBAL_CHECKSUM_END] = 0x34;
+
+        let header = CartridgeHeader::parse(&data);
+        assert_eq!(header.sgb(), SgbSupport::Supported);
+        assert_eq!(header.cgb(), CgbSupport::DmgOnly);
+        assert_eq!(header.licensee(), Licensee::New(*b"01"));
+        assert_eq!(header.destination(), Some(Destination::Overseas));
+        match header.cartridge_type() {
+            Some(CartridgeType::Mbc3RamBattery) => {}
+            t => panic!("Unexpected cartridge type {:?}", t),
+        }
+        assert_eq!(header.rom_size(), Some(KB!(1024)));
+        assert_eq!(header.ram_size(), Some(KB!(32)));
+        assert_eq!(header.destination_code, 0x01);
+        assert_eq!(&header.new_licensee_code, b"01");
+        assert_eq!(header.mask_rom_version, 0x02);
+        assert_eq!(header.header_checksum, 0xa5);
+        assert_eq!(header.global_checksum, 0x1234);
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(
+            CartridgeHeader::parse(&rom(b"TEST", 0xc0)).cgb(),
+            CgbSupport::CgbOnly
+        );
+
+        // The SGB flag is ignored unless the old licensee code is 0x33
+        let mut data = rom(b"TEST", 0x00);
+        data[SGB_FLAG] = 0x03;
+        data[OLD_LICENSEE_CODE] = 0x01;
+        let header = CartridgeHeader::parse(&data);
+        assert_eq!(header.sgb(), SgbSupport::Unsupported);
+        assert_eq!(header.licensee(), Licensee::Old(0x01));
+        assert_eq!(header.destination(), Some(Destination::Japan));
+
+        data[DESTINATION_CODE] = 0x02;
+        assert_eq!(CartridgeHeader::parse(&data).destination(), None);
+    }
+
+    #[test]
+    fn test_rom_size() {
+        let mut data = rom(b"TEST", 0x00);
+        data[ROM_SIZE] = 0x52;
+        assert_eq!(CartridgeHeader::parse(&data).rom_size(), Some(KB!(1152)));
+
+        data[ROM_SIZE] = 0xff;
+        assert_eq!(CartridgeHeader::parse(&data).rom_size(), None);
+    }
+}
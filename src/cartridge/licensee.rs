@@ -0,0 +1,288 @@
+use std::fmt;
+
+/// Old licensee code that defers to the new licensee code
+pub const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+/// Publisher of a cartridge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Licensee {
+    /// Single byte code at 0x14b, used by cartridges released before the SGB
+    Old(u8),
+    /// Two ASCII characters at 0x144-0x145, used when the old code is 0x33
+    New([u8; 2]),
+}
+
+impl Licensee {
+    pub fn new(old_code: u8, new_code: [u8; 2]) -> Licensee {
+        if old_code == USE_NEW_LICENSEE_CODE {
+            Licensee::New(new_code)
+        } else {
+            Licensee::Old(old_code)
+        }
+    }
+
+    /// Name of the publisher, if the code is a known one
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            Licensee::Old(code) => old_licensee_name(code),
+            Licensee::New(code) => new_licensee_name(code),
+        }
+    }
+}
+
+impl fmt::Display for Licensee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.name(), *self) {
+            (Some(name), _) => write!(f, "{}", name),
+            (None, Licensee::Old(code)) => write!(f, "Unknown ({:#04x})", code),
+            (None, Licensee::New(code)) => {
+                write!(f, "Unknown ({})", String::from_utf8_lossy(&code))
+            }
+        }
+    }
+}
+
+fn old_licensee_name(code: u8) -> Option<&'static str> {
+    match code {
+        0x00 => Some("None"),
+        0x01 => Some("Nintendo"),
+        0x08 => Some("Capcom"),
+        0x09 => Some("Hot-B"),
+        0x0a => Some("Jaleco"),
+        0x0b => Some("Coconuts Japan"),
+        0x0c => Some("Elite Systems"),
+        0x13 => Some("Electronic Arts"),
+        0x18 => Some("Hudson Soft"),
+        0x19 => Some("ITC Entertainment"),
+        0x1a => Some("Yanoman"),
+        0x1d => Some("Japan Clary"),
+        0x1f => Some("Virgin Interactive"),
+        0x24 => Some("PCM Complete"),
+        0x25 => Some("San-X"),
+        0x28 => Some("Kotobuki Systems"),
+        0x29 => Some("Seta"),
+        0x30 => Some("Infogrames"),
+        0x31 => Some("Nintendo"),
+        0x32 => Some("Bandai"),
+        0x34 => Some("Konami"),
+        0x35 => Some("HectorSoft"),
+        0x38 => Some("Capcom"),
+        0x39 => Some("Banpresto"),
+        0x3c => Some("Entertainment International"),
+        0x3e => Some("Gremlin"),
+        0x41 => Some("Ubi Soft"),
+        0x42 => Some("Atlus"),
+        0x44 => Some("Malibu"),
+        0x46 => Some("Angel"),
+        0x47 => Some("Spectrum HoloByte"),
+        0x49 => Some("Irem"),
+        0x4a => Some("Virgin Interactive"),
+        0x4d => Some("Malibu"),
+        0x4f => Some("U.S. Gold"),
+        0x50 => Some("Absolute"),
+        0x51 => Some("Acclaim"),
+        0x52 => Some("Activision"),
+        0x53 => Some("American Sammy"),
+        0x54 => Some("GameTek"),
+        0x55 => Some("Park Place"),
+        0x56 => Some("LJN"),
+        0x57 => Some("Matchbox"),
+        0x59 => Some("Milton Bradley"),
+        0x5a => Some("Mindscape"),
+        0x5b => Some("Romstar"),
+        0x5c => Some("Naxat Soft"),
+        0x5d => Some("Tradewest"),
+        0x60 => Some("Titus"),
+        0x61 => Some("Virgin Interactive"),
+        0x67 => Some("Ocean"),
+        0x69 => Some("Electronic Arts"),
+        0x6e => Some("Elite Systems"),
+        0x6f => Some("Electro Brain"),
+        0x70 => Some("Infogrames"),
+        0x71 => Some("Interplay"),
+        0x72 => Some("Broderbund"),
+        0x73 => Some("Sculptured Software"),
+        0x75 => Some("The Sales Curve"),
+        0x78 => Some("THQ"),
+        0x79 => Some("Accolade"),
+        0x7a => Some("Triffix Entertainment"),
+        0x7c => Some("MicroProse"),
+        0x7f => Some("Kemco"),
+        0x80 => Some("Misawa Entertainment"),
+        0x83 => Some("LOZC"),
+        0x86 => Some("Tokuma Shoten"),
+        0x8b => Some("Bullet-Proof Software"),
+        0x8c => Some("Vic Tokai"),
+        0x8e => Some("Ape"),
+        0x8f => Some("I'Max"),
+        0x91 => Some("Chunsoft"),
+        0x92 => Some("Video System"),
+        0x93 => Some("Tsuburaya Productions"),
+        0x95 => Some("Varie"),
+        0x96 => Some("Yonezawa/S'Pal"),
+        0x97 => Some("Kaneko"),
+        0x99 => Some("Arc"),
+        0x9a => Some("Nihon Bussan"),
+        0x9b => Some("Tecmo"),
+        0x9c => Some("Imagineer"),
+        0x9d => Some("Banpresto"),
+        0x9f => Some("Nova"),
+        0xa1 => Some("Hori Electric"),
+        0xa2 => Some("Bandai"),
+        0xa4 => Some("Konami"),
+        0xa6 => Some("Kawada"),
+        0xa7 => Some("Takara"),
+        0xa9 => Some("Technos Japan"),
+        0xaa => Some("Broderbund"),
+        0xac => Some("Toei Animation"),
+        0xad => Some("Toho"),
+        0xaf => Some("Namco"),
+        0xb0 => Some("Acclaim"),
+        0xb1 => Some("ASCII or Nexsoft"),
+        0xb2 => Some("Bandai"),
+        0xb4 => Some("Square Enix"),
+        0xb6 => Some("HAL Laboratory"),
+        0xb7 => Some("SNK"),
+        0xb9 => Some("Pony Canyon"),
+        0xba => Some("Culture Brain"),
+        0xbb => Some("Sunsoft"),
+        0xbd => Some("Sony Imagesoft"),
+        0xbf => Some("Sammy"),
+        0xc0 => Some("Taito"),
+        0xc2 => Some("Kemco"),
+        0xc3 => Some("Squaresoft"),
+        0xc4 => Some("Tokuma Shoten"),
+        0xc5 => Some("Data East"),
+        0xc6 => Some("Tonkinhouse"),
+        0xc8 => Some("Koei"),
+        0xc9 => Some("UFL"),
+        0xca => Some("Ultra"),
+        0xcb => Some("Vap"),
+        0xcc => Some("Use Corporation"),
+        0xcd => Some("Meldac"),
+        0xce => Some("Pony Canyon"),
+        0xcf => Some("Angel"),
+        0xd0 => Some("Taito"),
+        0xd1 => Some("Sofel"),
+        0xd2 => Some("Quest"),
+        0xd3 => Some("Sigma Enterprises"),
+        0xd4 => Some("ASK Kodansha"),
+        0xd6 => Some("Naxat Soft"),
+        0xd7 => Some("Copya System"),
+        0xd9 => Some("Banpresto"),
+        0xda => Some("Tomy"),
+        0xdb => Some("LJN"),
+        0xdd => Some("NCS"),
+        0xde => Some("Human"),
+        0xdf => Some("Altron"),
+        0xe0 => Some("Jaleco"),
+        0xe1 => Some("Towa Chiki"),
+        0xe2 => Some("Yutaka"),
+        0xe3 => Some("Varie"),
+        0xe5 => Some("Epoch"),
+        0xe7 => Some("Athena"),
+        0xe8 => Some("Asmik Ace Entertainment"),
+        0xe9 => Some("Natsume"),
+        0xea => Some("King Records"),
+        0xeb => Some("Atlus"),
+        0xec => Some("Epic/Sony Records"),
+        0xee => Some("IGS"),
+        0xf0 => Some("A Wave"),
+        0xf3 => Some("Extreme Entertainment"),
+        0xff => Some("LJN"),
+        _ => None,
+    }
+}
+
+fn new_licensee_name(code: [u8; 2]) -> Option<&'static str> {
+    match &code {
+        b"00" => Some("None"),
+        b"01" => Some("Nintendo"),
+        b"08" => Some("Capcom"),
+        b"13" => Some("Electronic Arts"),
+        b"18" => Some("Hudson Soft"),
+        b"19" => Some("B-AI"),
+        b"20" => Some("KSS"),
+        b"22" => Some("Planning Office WADA"),
+        b"24" => Some("PCM Complete"),
+        b"25" => Some("San-X"),
+        b"28" => Some("Kemco"),
+        b"29" => Some("Seta"),
+        b"30" => Some("Viacom"),
+        b"31" => Some("Nintendo"),
+        b"32" => Some("Bandai"),
+        b"33" => Some("Ocean/Acclaim"),
+        b"34" => Some("Konami"),
+        b"35" => Some("HectorSoft"),
+        b"37" => Some("Taito"),
+        b"38" => Some("Hudson Soft"),
+        b"39" => Some("Banpresto"),
+        b"41" => Some("Ubi Soft"),
+        b"42" => Some("Atlus"),
+        b"44" => Some("Malibu"),
+        b"46" => Some("Angel"),
+        b"47" => Some("Bullet-Proof Software"),
+        b"49" => Some("Irem"),
+        b"50" => Some("Absolute"),
+        b"51" => Some("Acclaim"),
+        b"52" => Some("Activision"),
+        b"53" => Some("American Sammy"),
+        b"54" => Some("Konami"),
+        b"55" => Some("Hi Tech Entertainment"),
+        b"56" => Some("LJN"),
+        b"57" => Some("Matchbox"),
+        b"58" => Some("Mattel"),
+        b"59" => Some("Milton Bradley"),
+        b"60" => Some("Titus"),
+        b"61" => Some("Virgin Interactive"),
+        b"64" => Some("LucasArts"),
+        b"67" => Some("Ocean"),
+        b"69" => Some("Electronic Arts"),
+        b"70" => Some("Infogrames"),
+        b"71" => Some("Interplay"),
+        b"72" => Some("Broderbund"),
+        b"73" => Some("Sculptured Software"),
+        b"75" => Some("The Sales Curve"),
+        b"78" => Some("THQ"),
+        b"79" => Some("Accolade"),
+        b"80" => Some("Misawa Entertainment"),
+        b"83" => Some("LOZC"),
+        b"86" => Some("Tokuma Shoten"),
+        b"87" => Some("Tsukuda Original"),
+        b"91" => Some("Chunsoft"),
+        b"92" => Some("Video System"),
+        b"93" => Some("Ocean/Acclaim"),
+        b"95" => Some("Varie"),
+        b"96" => Some("Yonezawa/S'Pal"),
+        b"97" => Some("Kaneko"),
+        b"99" => Some("Pack-In-Video"),
+        b"9H" => Some("Bottom Up"),
+        b"A4" => Some("Konami"),
+        b"BL" => Some("MTO"),
+        b"DK" => Some("Kodansha"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_licensee() {
+        assert_eq!(Licensee::new(0x01, *b"00"), Licensee::Old(0x01));
+        assert_eq!(Licensee::new(0x33, *b"01"), Licensee::New(*b"01"));
+
+        assert_eq!(Licensee::Old(0x01).name(), Some("Nintendo"));
+        assert_eq!(Licensee::Old(0xaf).name(), Some("Namco"));
+        assert_eq!(Licensee::New(*b"A4").name(), Some("Konami"));
+        assert_eq!(Licensee::Old(0x02).name(), None);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Licensee::New(*b"08").to_string(), "Capcom");
+        assert_eq!(Licensee::Old(0x02).to_string(), "Unknown (0x02)");
+        assert_eq!(Licensee::New(*b"ZZ").to_string(), "Unknown (ZZ)");
+    }
+}
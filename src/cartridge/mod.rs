@@ -0,0 +1,471 @@
+#![allow(dead_code)]
+
+macro_rules! KB {
+    ($x:expr) => {
+        ($x * 1024)
+    };
+}
+
+use std::cmp;
+use std::fmt;
+use std::mem;
+
+use dat::{Dat, DatEntry, DumpStatus};
+use errors::{Error, ErrorKind, Result};
+use hash;
+
+mod header;
+mod licensee;
+mod validation;
+
+use self::header::HEADER_SIZE;
+pub use self::header::{CartridgeHeader, CgbSupport, Destination, SgbSupport};
+pub use self::licensee::Licensee;
+pub use self::validation::Validation;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CartridgeType {
+    RomOnly,
+    Mbc1,
+    Mbc1Ram,
+    Mbc1RamBattery,
+    Mbc2,
+    Mbc2Battery,
+    RomRam,
+    RomRamBattery,
+    Mmm01,
+    Mmm01Ram,
+    Mmm01RamBattery,
+    Mbc3TimerBattery,
+    Mbc3TimerRamBattery,
+    Mbc3,
+    Mbc3Ram,
+    Mbc3RamBattery,
+    Mbc5,
+    Mbc5Ram,
+    Mbc5RamBattery,
+    Mbc5Rumble,
+    Mbc5RumbleRam,
+    Mbc5RumbleRamBattery,
+    Mbc6,
+    Mbc7SensorRumbleRamBattery,
+    PocketCamera,
+    BandaiTama5,
+    Huc3,
+    Huc1RamBattery,
+}
+
+impl CartridgeType {
+    fn new(n: u8) -> Option<CartridgeType> {
+        use self::CartridgeType::*;
+
+        match n {
+            0x00 => Some(RomOnly),
+            0x01 => Some(Mbc1),
+            0x02 => Some(Mbc1Ram),
+            0x03 => Some(Mbc1RamBattery),
+            0x05 => Some(Mbc2),
+            0x06 => Some(Mbc2Battery),
+            0x08 => Some(RomRam),
+            0x09 => Some(RomRamBattery),
+            0x0B => Some(Mmm01),
+            0x0C => Some(Mmm01Ram),
+            0x0D => Some(Mmm01RamBattery),
+            0x0F => Some(Mbc3TimerBattery),
+            0x10 => Some(Mbc3TimerRamBattery),
+            0x11 => Some(Mbc3),
+            0x12 => Some(Mbc3Ram),
+            0x13 => Some(Mbc3RamBattery),
+            0x19 => Some(Mbc5),
+            0x1A => Some(Mbc5Ram),
+            0x1B => Some(Mbc5RamBattery),
+            0x1C => Some(Mbc5Rumble),
+            0x1D => Some(Mbc5RumbleRam),
+            0x1E => Some(Mbc5RumbleRamBattery),
+            0x20 => Some(Mbc6),
+            0x22 => Some(Mbc7SensorRumbleRamBattery),
+            0xFC => Some(PocketCamera),
+            0xFD => Some(BandaiTama5),
+            0xFE => Some(Huc3),
+            0xFF => Some(Huc1RamBattery),
+            _ => None,
+        }
+    }
+
+    /// Whether the emulator implements the memory controller of this type
+    pub fn is_supported(self) -> bool {
+        self == CartridgeType::RomOnly
+    }
+}
+
+impl fmt::Display for CartridgeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::CartridgeType::*;
+
+        let name = match *self {
+            RomOnly => "ROM ONLY",
+            Mbc1 => "MBC1",
+            Mbc1Ram => "MBC1+RAM",
+            Mbc1RamBattery => "MBC1+RAM+BATTERY",
+            Mbc2 => "MBC2",
+            Mbc2Battery => "MBC2+BATTERY",
+            RomRam => "ROM+RAM",
+            RomRamBattery => "ROM+RAM+BATTERY",
+            Mmm01 => "MMM01",
+            Mmm01Ram => "MMM01+RAM",
+            Mmm01RamBattery => "MMM01+RAM+BATTERY",
+            Mbc3TimerBattery => "MBC3+TIMER+BATTERY",
+            Mbc3TimerRamBattery => "MBC3+TIMER+RAM+BATTERY",
+            Mbc3 => "MBC3",
+            Mbc3Ram => "MBC3+RAM",
+            Mbc3RamBattery => "MBC3+RAM+BATTERY",
+            Mbc5 => "MBC5",
+            Mbc5Ram => "MBC5+RAM",
+            Mbc5RamBattery => "MBC5+RAM+BATTERY",
+            Mbc5Rumble => "MBC5+RUMBLE",
+            Mbc5RumbleRam => "MBC5+RUMBLE+RAM",
+            Mbc5RumbleRamBattery => "MBC5+RUMBLE+RAM+BATTERY",
+            Mbc6 => "MBC6",
+            Mbc7SensorRumbleRamBattery => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            PocketCamera => "POCKET CAMERA",
+            BandaiTama5 => "BANDAI TAMA5",
+            Huc3 => "HuC3",
+            Huc1RamBattery => "HuC1+RAM+BATTERY",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Logo every licensed cartridge must carry at 0x104-0x133. The boot rom
+/// refuses to start a cartridge whose logo doesn't match this one.
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00, 0x0d,
+    0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd, 0xd9, 0x99,
+    0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb, 0xb9, 0x33, 0x3e,
+];
+
+const INTERRUPTS_BEGIN: usize = 0x0000;
+const INTERRUPTS_END: usize = 0x00ff;
+const HEADER_BEGIN: usize = 0x0100;
+const HEADER_END: usize = 0x014f;
+const BANK0_BEGIN: usize = 0x0150;
+const BANK0_END: usize = 0x3fff;
+const BANK1_BEGIN: usize = 0x4000;
+const BANK1_END: usize = 0x7fff;
+
+// Smallest rom, filling both banks at 0x0000-0x7fff
+const MIN_ROM_SIZE: usize = KB!(32);
+
+// Value read from addresses not driven by any rom chip
+const OPEN_BUS: u8 = 0xff;
+
+pub struct Cartridge {
+    raw_data: Vec<u8>,
+    /// Size of the file the rom was loaded from, before it was resized to
+    /// match the header, or of the rom the DAT knows for an overdump
+    dump_size: usize,
+    header: CartridgeHeader,
+    crc32: u32,
+    sha1: [u8; 20],
+    /// DAT entry of the dump, once it has been identified
+    dat_entry: Option<DatEntry>,
+}
+
+impl Cartridge {
+    /// Load a rom dump, resizing it to the size stated in its header
+    pub fn new(raw_data: Vec<u8>) -> Result<Cartridge> {
+        let dump_size = raw_data.len();
+        if dump_size < HEADER_SIZE {
+            return Err(Error::new(
+                ErrorKind::RomTooSmall,
+                format!(
+                    "ROM is {} bytes long, too small to hold a cartridge header ({} bytes)",
+                    dump_size, HEADER_SIZE
+                ),
+            ));
+        }
+
+        let header = CartridgeHeader::parse(&raw_data);
+        let crc32 = hash::crc32(&raw_data);
+        let sha1 = hash::sha1(&raw_data);
+
+        Ok(Cartridge {
+            raw_data: fit_rom(raw_data, &header),
+            dump_size,
+            header,
+            crc32,
+            sha1,
+            dat_entry: None,
+        })
+    }
+
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    pub fn dump_size(&self) -> usize {
+        self.dump_size
+    }
+
+    /// CRC-32 of the dump, before it was resized
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// SHA-1 of the dump, before it was resized
+    pub fn sha1(&self) -> &[u8; 20] {
+        &self.sha1
+    }
+
+    /// Look the dump up in a DAT file, keeping the result for `dat_entry`.
+    ///
+    /// An overdump is cut down to the rom the DAT knows, so that the extra
+    /// data neither shows up in the address space nor counts towards the
+    /// global checksum, and a bad dump fails validation.
+    pub fn identify(&mut self, dat: &Dat) -> Option<&DatEntry> {
+        self.dat_entry = dat.lookup(&self.raw_data[..self.dump_size], self.crc32, &self.sha1);
+
+        if let Some(DatEntry {
+            status: DumpStatus::Overdump,
+            size,
+            ..
+        }) = self.dat_entry
+        {
+            self.raw_data.truncate(size);
+            self.raw_data = fit_rom(mem::take(&mut self.raw_data), &self.header);
+            self.dump_size = size;
+        }
+
+        self.dat_entry.as_ref()
+    }
+
+    pub fn dat_entry(&self) -> Option<&DatEntry> {
+        self.dat_entry.as_ref()
+    }
+
+    pub fn interrupts(&self) -> &[u8] {
+        &self.raw_data[INTERRUPTS_BEGIN..=INTERRUPTS_END]
+    }
+
+    pub fn raw_header(&self) -> &[u8] {
+        &self.raw_data[HEADER_BEGIN..=HEADER_END]
+    }
+
+    pub fn bank0(&self) -> &[u8] {
+        &self.raw_data[BANK0_BEGIN..=BANK0_END]
+    }
+
+    pub fn bank1(&self) -> &[u8] {
+        &self.raw_data[BANK1_BEGIN..=BANK1_END]
+    }
+
+    /// Checksum of 0x134-0x14c, computed the same way the boot rom does
+    pub fn compute_header_checksum(&self) -> u8 {
+        self.raw_data[0x134..=0x14c]
+            .iter()
+            .fold(0u8, |sum, v| sum.wrapping_sub(*v).wrapping_sub(1))
+    }
+
+    /// Sum of every byte in the dump, except for the global checksum itself
+    pub fn compute_global_checksum(&self) -> u16 {
+        self.raw_data[..self.dump_size]
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 0x14e && i != 0x14f)
+            .fold(0u16, |sum, (_, &v)| sum.wrapping_add(u16::from(v)))
+    }
+
+    pub fn validate(&self) -> Validation {
+        Validation::new(self)
+    }
+}
+
+/// Resize a dump to the size stated in its header.
+///
+/// Undersized dumps are padded with open bus reads up to the next power of
+/// two and then mirrored, the same way a smaller rom chip would show up in
+/// the address space. When the header has an unknown size, the size of the
+/// dump is used instead.
+fn fit_rom(mut raw_data: Vec<u8>, header: &CartridgeHeader) -> Vec<u8> {
+    let dump_size = raw_data.len();
+    let rom_size = header
+        .rom_size()
+        .unwrap_or_else(|| dump_size.next_power_of_two());
+    let rom_size = cmp::max(rom_size, MIN_ROM_SIZE);
+
+    if dump_size < rom_size {
+        raw_data.resize(cmp::min(dump_size.next_power_of_two(), rom_size), OPEN_BUS);
+        while raw_data.len() < rom_size {
+            let mirror = raw_data.clone();
+            raw_data.extend_from_slice(&mirror);
+        }
+        raw_data.truncate(rom_size);
+    }
+
+    raw_data
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// Builds roms for tests: 32 KiB of zeroes with the Nintendo logo, the
+    /// title RUSTYB and a valid header checksum
+    pub struct TestRom {
+        rom: Vec<u8>,
+        global_checksum: bool,
+    }
+
+    impl TestRom {
+        pub fn new() -> TestRom {
+            let mut rom = vec![0; KB!(32)];
+            rom[0x104..=0x133].copy_from_slice(&NINTENDO_LOGO);
+            rom[0x134..0x13a].copy_from_slice(b"RUSTYB");
+            TestRom {
+                rom,
+                global_checksum: false,
+            }
+        }
+
+        /// Set a byte of the header, or of the rest of the rom
+        pub fn set(mut self, addr: usize, value: u8) -> TestRom {
+            self.rom[addr] = value;
+            self
+        }
+
+        /// Make the global checksum valid as well, instead of leaving it at 0
+        pub fn global_checksum(mut self) -> TestRom {
+            self.global_checksum = true;
+            self
+        }
+
+        pub fn build(mut self) -> Vec<u8> {
+            self.rom[0x14d] = Cartridge::new(self.rom.clone())
+                .unwrap()
+                .compute_header_checksum();
+            if self.global_checksum {
+                let global = Cartridge::new(self.rom.clone())
+                    .unwrap()
+                    .compute_global_checksum();
+                self.rom[0x14e] = (global >> 8) as u8;
+                self.rom[0x14f] = global as u8;
+            }
+            self.rom
+        }
+    }
+
+    fn rom(size: usize, rom_size_code: u8) -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..size).map(|i| (i / 0x1000) as u8).collect();
+        rom[0x148] = rom_size_code;
+        rom
+    }
+
+    #[test]
+    fn test_rom_too_small() {
+        match Cartridge::new(vec![0; 0x14f]) {
+            Err(ref e) => match *e.kind() {
+                ErrorKind::RomTooSmall => {}
+                ref kind => panic!("Unexpected error kind {:?}", kind),
+            },
+            Ok(_) => panic!("Expected an error"),
+        }
+        assert!(Cartridge::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_exact_size() {
+        let cartridge = Cartridge::new(rom(KB!(64), 0x01)).unwrap();
+        assert_eq!(cartridge.raw_data.len(), KB!(64));
+        assert_eq!(cartridge.dump_size(), KB!(64));
+    }
+
+    #[test]
+    fn test_mirror_undersized_dump() {
+        // A 32 KiB dump of a 64 KiB rom shows up twice
+        let cartridge = Cartridge::new(rom(KB!(32), 0x01)).unwrap();
+        assert_eq!(cartridge.raw_data.len(), KB!(64));
+        assert_eq!(cartridge.dump_size(), KB!(32));
+        assert_eq!(cartridge.raw_data[0x4000], 0x04);
+        assert_eq!(cartridge.raw_data[0xc000], 0x04);
+    }
+
+    #[test]
+    fn test_pad_truncated_dump() {
+        // The missing part of a truncated dump reads as open bus
+        let cartridge = Cartridge::new(rom(KB!(20), 0x00)).unwrap();
+        assert_eq!(cartridge.raw_data.len(), KB!(32));
+        assert_eq!(cartridge.raw_data[0x4fff], 0x04);
+        assert_eq!(cartridge.raw_data[0x5000], OPEN_BUS);
+        assert_eq!(cartridge.bank1().len(), KB!(16));
+
+        // Header only dumps still fill both banks
+        let cartridge = Cartridge::new(rom(HEADER_SIZE, 0x00)).unwrap();
+        assert_eq!(cartridge.raw_data.len(), KB!(32));
+    }
+
+    #[test]
+    fn test_identify() {
+        let data = rom(KB!(32), 0x00);
+        let dat = Dat::parse(&format!(
+            r#"<game name="Test (Rev A)"><rom name="test.gb" size="32768" crc="{:08x}"/></game>"#,
+            hash::crc32(&data)
+        ))
+        .unwrap();
+
+        let mut cartridge = Cartridge::new(data.clone()).unwrap();
+        assert_eq!(cartridge.dat_entry(), None);
+        assert_eq!(cartridge.identify(&dat).unwrap().revision(), Some("A"));
+        assert_eq!(cartridge.dat_entry().unwrap().game, "Test (Rev A)");
+
+        // Hashes cover the dump, not the mirrored rom
+        let cartridge = Cartridge::new(data[..KB!(16)].to_vec()).unwrap();
+        assert_eq!(cartridge.crc32(), hash::crc32(&data[..KB!(16)]));
+    }
+
+    #[test]
+    fn test_identify_overdump() {
+        let data = rom(KB!(48), 0x00);
+        let dat = Dat::parse(&format!(
+            r#"<game name="Test"><rom name="test.gb" size="32768" crc="{:08x}"/></game>"#,
+            hash::crc32(&data[..KB!(32)])
+        ))
+        .unwrap();
+
+        let mut cartridge = Cartridge::new(data.clone()).unwrap();
+        assert!(!cartridge.validate().rom_size);
+        assert_eq!(cartridge.raw_data.len(), KB!(48));
+
+        // The extra data is dropped, leaving the rom the header describes
+        assert_eq!(
+            cartridge.identify(&dat).unwrap().status,
+            DumpStatus::Overdump
+        );
+        assert_eq!(cartridge.dump_size(), KB!(32));
+        assert_eq!(cartridge.raw_data, &data[..KB!(32)]);
+        assert!(cartridge.validate().rom_size);
+        assert_eq!(cartridge.crc32(), hash::crc32(&data));
+    }
+
+    #[test]
+    fn test_identify_bad_dump() {
+        let data = rom(KB!(32), 0x00);
+        let dat = Dat::parse(&format!(
+            r#"<game name="Test"><rom name="test.gb" size="32768" crc="{:08x}" status="baddump"/></game>"#,
+            hash::crc32(&data)
+        ))
+        .unwrap();
+
+        let mut cartridge = Cartridge::new(data).unwrap();
+        assert!(cartridge.validate().dump);
+        cartridge.identify(&dat);
+        assert!(!cartridge.validate().dump);
+        assert!(cartridge.validate().failures().contains(&"Known bad dump"));
+    }
+
+    #[test]
+    fn test_unknown_rom_size() {
+        let cartridge = Cartridge::new(rom(KB!(48), 0xff)).unwrap();
+        assert_eq!(cartridge.raw_data.len(), KB!(64));
+        assert_eq!(cartridge.header().rom_size(), None);
+    }
+}
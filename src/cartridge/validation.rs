@@ -0,0 +1,119 @@
+use super::{Cartridge, NINTENDO_LOGO};
+use dat::DumpStatus;
+
+/// Outcome of each of the integrity checks on a cartridge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Validation {
+    /// The logo at 0x104-0x133 matches the one the boot rom expects
+    pub logo: bool,
+    /// The checksum of 0x134-0x14c matches the one at 0x14d. The boot rom
+    /// refuses to start the cartridge when it doesn't.
+    pub header_checksum: bool,
+    /// The sum of every byte except 0x14e-0x14f matches the one stored
+    /// there. No hardware checks this one.
+    pub global_checksum: bool,
+    /// The dump is as large as the header says the rom is
+    pub rom_size: bool,
+    /// The DAT the dump was identified with doesn't know it as a bad dump
+    pub dump: bool,
+}
+
+impl Validation {
+    pub fn new(cartridge: &Cartridge) -> Validation {
+        let header = cartridge.header();
+        Validation {
+            logo: header.logo[..] == NINTENDO_LOGO[..],
+            header_checksum: cartridge.compute_header_checksum() == header.header_checksum,
+            global_checksum: cartridge.compute_global_checksum() == header.global_checksum,
+            rom_size: header.rom_size() == Some(cartridge.dump_size()),
+            dump: cartridge
+                .dat_entry()
+                .is_none_or(|entry| entry.status != DumpStatus::BadDump),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.logo && self.header_checksum && self.global_checksum && self.rom_size && self.dump
+    }
+
+    /// Description of every check that failed
+    pub fn failures(&self) -> Vec<&'static str> {
+        let mut failures = Vec::new();
+        if !self.logo {
+            failures.push("Nintendo logo doesn't match");
+        }
+        if !self.header_checksum {
+            failures.push("Header checksum doesn't match");
+        }
+        if !self.global_checksum {
+            failures.push("Global checksum doesn't match");
+        }
+        if !self.rom_size {
+            failures.push("ROM size doesn't match the header");
+        }
+        if !self.dump {
+            failures.push("Known bad dump");
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cartridge::test::TestRom;
+
+    fn valid_rom() -> Vec<u8> {
+        TestRom::new().global_checksum().build()
+    }
+
+    #[test]
+    fn test_valid() {
+        let validation = Validation::new(&Cartridge::new(valid_rom()).unwrap());
+        assert!(validation.is_valid());
+        assert!(validation.failures().is_empty());
+    }
+
+    #[test]
+    fn test_bad_logo() {
+        let mut rom = valid_rom();
+        rom[0x133] ^= 0xff;
+        let validation = Validation::new(&Cartridge::new(rom).unwrap());
+        assert!(!validation.logo);
+        assert!(validation.header_checksum);
+        assert_eq!(validation.failures()[0], "Nintendo logo doesn't match");
+    }
+
+    #[test]
+    fn test_bad_header_checksum() {
+        let mut rom = valid_rom();
+        rom[0x14d] = rom[0x14d].wrapping_add(1);
+        let validation = Validation::new(&Cartridge::new(rom).unwrap());
+        assert!(validation.logo);
+        assert!(!validation.header_checksum);
+        assert!(!validation.global_checksum);
+        assert_eq!(validation.failures().len(), 2);
+    }
+
+    #[test]
+    fn test_bad_global_checksum() {
+        let mut rom = valid_rom();
+        rom[0x7fff] = 0x01;
+        let validation = Validation::new(&Cartridge::new(rom).unwrap());
+        assert!(validation.header_checksum);
+        assert!(!validation.global_checksum);
+        assert!(!validation.is_valid());
+    }
+
+    #[test]
+    fn test_bad_rom_size() {
+        let mut rom = valid_rom();
+        rom.truncate(0x4000);
+        let validation = Validation::new(&Cartridge::new(rom).unwrap());
+        assert!(validation.header_checksum);
+        assert!(!validation.rom_size);
+        assert!(validation
+            .failures()
+            .contains(&"ROM size doesn't match the header"));
+    }
+}
@@ -0,0 +1,43 @@
+//! Number of dots (T-cycles) taken by each instruction.
+//!
+//! Conditional jumps, calls and returns are listed with the cycles taken when
+//! the condition is false. The extra cycles of a taken branch are added by the
+//! instruction itself.
+
+#[rustfmt::skip]
+pub const UNPREFIXED: [u8; 256] = [
+//  x0  x1  x2  x3  x4  x5  x6  x7  x8  x9  xa  xb  xc  xd  xe  xf
+     4, 12,  8,  8,  4,  4,  8,  4, 20,  8,  8,  8,  4,  4,  8,  4, // 0x
+     4, 12,  8,  8,  4,  4,  8,  4, 12,  8,  8,  8,  4,  4,  8,  4, // 1x
+     8, 12,  8,  8,  4,  4,  8,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 2x
+     8, 12,  8,  8, 12, 12, 12,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 3x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 4x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 5x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 6x
+     8,  8,  8,  8,  8,  8,  4,  8,  4,  4,  4,  4,  4,  4,  8,  4, // 7x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 8x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 9x
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // ax
+     4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // bx
+     8, 12, 12, 16, 12, 16,  8, 16,  8, 16, 12,  4, 12, 24,  8, 16, // cx
+     8, 12, 12,  4, 12, 16,  8, 16,  8, 16, 12,  4, 12,  4,  8, 16, // dx
+    12, 12,  8,  4,  4, 16,  8, 16, 16,  4, 16,  4,  4,  4,  8, 16, // ex
+    12, 12,  8,  4,  4, 16,  8, 16, 12,  8, 16,  4,  4,  4,  8, 16, // fx
+];
+
+// Extra cycles of a taken branch
+pub const JR_TAKEN: u32 = 4;
+pub const JP_TAKEN: u32 = 4;
+pub const CALL_TAKEN: u32 = 12;
+pub const RET_TAKEN: u32 = 12;
+
+/// Cycles of a CB prefixed instruction, prefix included. Instructions that
+/// operate on (HL) have to read it, and write it back unless they only test
+/// a bit.
+pub fn cb_prefixed(opcode: u8) -> u32 {
+    match opcode {
+        0x40..=0x7f if opcode & 0x07 == 0x06 => 12,
+        _ if opcode & 0x07 == 0x06 => 16,
+        _ => 8,
+    }
+}
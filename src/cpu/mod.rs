@@ -1,3 +1,4 @@
+mod cycles;
 mod opcodes;
 mod printer;
 mod test;
@@ -6,6 +7,9 @@ use std::fmt;
 
 use self::printer::Printer;
 use errors::{Error, ErrorKind, Result};
+use joypad::{Button, Joypad};
+use model::Model;
+use registers::{BCPD, BCPS, DMA, IF, LCDC, LY, OCPD, OCPS, P1, STAT, VBK};
 use {u16_to_u8, u8_to_u16};
 
 const MEM_SIZE: usize = 64 * 1024;
@@ -14,8 +18,35 @@ const MEM_SIZE: usize = 64 * 1024;
 // Memory offsets
 //
 
+const MEM_VRAM_BEGIN: usize = 0x8000;
+const MEM_VRAM_END: usize = 0x9fff;
+const MEM_OAM_BEGIN: usize = 0xfe00;
+const MEM_OAM_END: usize = 0xfe9f;
+const MEM_ECHO_BEGIN: usize = 0xe000;
 const MEM_HW_IO_REG_OFFSET: usize = 0xff00;
 
+const VRAM_BANK_SIZE: usize = 0x2000;
+const OAM_SIZE: usize = MEM_OAM_END - MEM_OAM_BEGIN + 1;
+// The echo RAM mirrors the work RAM 0x2000 bytes lower
+const ECHO_OFFSET: usize = 0x2000;
+const PALETTE_RAM_SIZE: usize = 64;
+const PALETTE_INDEX_MASK: u8 = 0b0011_1111;
+const PALETTE_AUTO_INCREMENT: u8 = 0b1000_0000;
+
+const INTERRUPT_JOYPAD: u8 = 0b0001_0000;
+
+//
+// LCD modes, as reported by the lower bits of STAT
+//
+
+const LCD_MODE_MASK: u8 = 0b0000_0011;
+const LCD_MODE_OAM_SCAN: u8 = 2;
+const LCD_MODE_DRAWING: u8 = 3;
+const LCD_ENABLE: u8 = 0b1000_0000;
+
+// The OAM is accessed by the PPU in rows of 8 bytes
+const OAM_ROW_SIZE: usize = 8;
+
 ///
 ///  16bit Hi   Lo   Name/Function
 ///  AF    A    -    Accumulator & Flags
@@ -37,6 +68,21 @@ pub struct Cpu {
     pc: u16,
     status: u8, // status flag: sign, zero, parity, carry, aux carry
     mem: [u8; MEM_SIZE],
+    model: Model,
+    /// OAM row the PPU is reading while scanning the OAM
+    oam_row: usize,
+    /// Extra cycles taken by the current instruction because it branched
+    branch_cycles: u32,
+    /// Whether a CGB is running a CGB cartridge, with its extra VRAM bank and
+    /// colour palettes
+    cgb_mode: bool,
+    /// The second VRAM bank of the CGB. The first one is in `mem`.
+    vram_bank1: Vec<u8>,
+    bg_palettes: [u8; PALETTE_RAM_SIZE],
+    obj_palettes: [u8; PALETTE_RAM_SIZE],
+    joypad: Joypad,
+    /// Last value written to P1 and not yet seen by the SGB
+    p1_write: Option<u8>,
 }
 
 impl Clone for Cpu {
@@ -53,6 +99,14 @@ impl Clone for Cpu {
         clone.pc = self.pc;
         clone.status = self.status;
         clone.mem.clone_from_slice(&self.mem);
+        clone.model = self.model;
+        clone.oam_row = self.oam_row;
+        clone.cgb_mode = self.cgb_mode;
+        clone.vram_bank1.clone_from(&self.vram_bank1);
+        clone.bg_palettes = self.bg_palettes;
+        clone.obj_palettes = self.obj_palettes;
+        clone.joypad = self.joypad;
+        clone.p1_write = self.p1_write;
 
         clone
     }
@@ -60,11 +114,11 @@ impl Clone for Cpu {
 
 impl fmt::Display for Cpu {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return write!(
+        write!(
             f,
             "[a: {:02x}, b: {:02x}, c: {:02x}, d: {:02x}, e: {:02x}, h: {:02x}, l: {:02x}, status: {:08b}, sp: {:04x}, pc: {:04x}]",
             self.a, self.b, self.c, self.d, self.e, self.h, self.l, self.status, self.sp, self.pc,
-        );
+        )
     }
 }
 
@@ -76,7 +130,7 @@ pub enum Flag {
 }
 
 impl Flag {
-    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[rustfmt::skip]
     fn mask(&self) -> u8 {
         match *self {
             Flag::Zero =>      0b1000_0000,
@@ -101,23 +155,92 @@ impl Cpu {
             pc: 0,
             status: 0,
             mem: [0; MEM_SIZE],
+            model: Model::Dmg,
+            oam_row: 0,
+            branch_cycles: 0,
+            cgb_mode: false,
+            vram_bank1: vec![0; VRAM_BANK_SIZE],
+            bg_palettes: [0; PALETTE_RAM_SIZE],
+            obj_palettes: [0; PALETTE_RAM_SIZE],
+            joypad: Joypad::new(),
+            p1_write: None,
         }
     }
 
-    pub fn init(&mut self) {
-        // TODO I'm assuming that we are running on a GB for now.
-        // When we support multiple types, the value of the A register must change
-        // accordingly:
-        //  A=$01-GB/SGB, $FF-GBP, $11-GBC
-        self.set_af(0x01b0);
-        self.set_bc(0x0013);
-        self.set_de(0x00d8);
-        self.set_hl(0x014d);
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+    }
+
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    /// Press or release a button. Pressing one of the selected group
+    /// requests the joypad interrupt.
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        let lines = self.joypad_lines();
+        self.joypad.set(button, pressed);
+        self.update_joypad_interrupt(lines);
+    }
+
+    /// Select the SGB controller read through P1
+    pub fn set_joypad_player(&mut self, player: u8) {
+        self.joypad.set_player(player);
+    }
+
+    /// The value last written to P1, if it was written since the last call.
+    /// The SGB receives its commands through it.
+    pub fn take_p1_write(&mut self) -> Option<u8> {
+        self.p1_write.take()
+    }
+
+    /// Tell which OAM row the PPU is reading, for the OAM corruption bug
+    pub fn set_oam_row(&mut self, row: usize) {
+        self.oam_row = row;
+    }
+
+    /// Set the registers to the values the boot rom of `model` leaves them in.
+    ///
+    /// `cgb_mode` tells whether a CGB is running a CGB cartridge, as opposed
+    /// to running a DMG cartridge in compatibility mode.
+    pub fn init(&mut self, model: Model, cgb_mode: bool, header_checksum: u8) {
+        // The DMG and MGB boot roms leave the flags from comparing the
+        // header checksum, so H and C are only reset if it is 0x00.
+        let checksum_flags = if header_checksum == 0 { 0x80 } else { 0xb0 };
+
+        #[rustfmt::skip]
+        let (af, bc, de, hl) = match model {
+            Model::Dmg0 =>            (0x0100, 0xff13, 0x00c1, 0x8403),
+            Model::Dmg =>             (0x0100 | checksum_flags, 0x0013, 0x00d8, 0x014d),
+            Model::Mgb =>             (0xff00 | checksum_flags, 0x0013, 0x00d8, 0x014d),
+            Model::Sgb =>             (0x0100, 0x0014, 0x0000, 0xc060),
+            Model::Sgb2 =>            (0xff00, 0x0014, 0x0000, 0xc060),
+            Model::Cgb if cgb_mode => (0x1180, 0x0000, 0xff56, 0x000d),
+            Model::Cgb =>             (0x1180, 0x0000, 0x0008, 0x007c),
+            Model::Agb if cgb_mode => (0x1100, 0x0100, 0xff56, 0x000d),
+            Model::Agb =>             (0x1100, 0x0100, 0x0008, 0x007c),
+        };
+
+        self.set_af(af);
+        self.set_bc(bc);
+        self.set_de(de);
+        self.set_hl(hl);
 
         self.pc = 0x100;
         self.sp = 0xfffe;
 
-        println!("{}", self);
+        // The CGB boot rom leaves every background colour white
+        if cgb_mode {
+            self.bg_palettes = [0xff; PALETTE_RAM_SIZE];
+        }
+
+        if cfg!(feature = "verbose") {
+            println!("{}", self);
+        }
     }
 
     /// Set this CPU's state to the given cpu
@@ -133,12 +256,24 @@ impl Cpu {
         self.pc = cpu.pc;
         self.status = cpu.status;
         self.mem.clone_from_slice(&cpu.mem);
+        self.model = cpu.model;
+        self.oam_row = cpu.oam_row;
+        self.cgb_mode = cpu.cgb_mode;
+        self.vram_bank1.clone_from(&cpu.vram_bank1);
+        self.bg_palettes = cpu.bg_palettes;
+        self.obj_palettes = cpu.obj_palettes;
+        self.joypad = cpu.joypad;
+        self.p1_write = cpu.p1_write;
     }
 
     //
     // Manage memory
     //
 
+    pub fn get_mem(&self, i: usize) -> u8 {
+        self.mem[i]
+    }
+
     pub fn set_mem(&mut self, i: usize, value: u8) {
         self.mem[i] = value
     }
@@ -151,13 +286,217 @@ impl Cpu {
         self.mem[i..=j].copy_from_slice(data);
     }
 
-    pub fn push_stack(&mut self, data: &[u8]) {
-        let top = self.sp as usize;
-        let bottom = top - data.len();
+    /// Byte at `i` of a VRAM bank, whichever bank the CPU has selected
+    pub fn get_vram(&self, bank: usize, i: usize) -> u8 {
+        match bank {
+            0 => self.mem[i],
+            _ => self.vram_bank1[i - MEM_VRAM_BEGIN],
+        }
+    }
+
+    /// Write a byte as the CPU would, through the registers it goes through
+    #[cfg(test)]
+    pub fn write_io(&mut self, i: usize, value: u8) {
+        self.write_byte(i, value);
+    }
+
+    #[cfg(test)]
+    pub fn set_vram(&mut self, bank: usize, i: usize, value: u8) {
+        match bank {
+            0 => self.mem[i] = value,
+            _ => self.vram_bank1[i - MEM_VRAM_BEGIN] = value,
+        }
+    }
+
+    /// The 8 background palettes of the CGB, 4 little endian colours each
+    pub fn bg_palette_ram(&self) -> &[u8] {
+        &self.bg_palettes
+    }
+
+    /// The 8 object palettes of the CGB, 4 little endian colours each
+    pub fn obj_palette_ram(&self) -> &[u8] {
+        &self.obj_palettes
+    }
+
+    /// Whether the CPU has selected the second VRAM bank
+    fn vram_bank1_selected(&self) -> bool {
+        self.cgb_mode && self.mem[VBK] & 1 == 1
+    }
+
+    /// Read a byte as seen by the CPU.
+    ///
+    /// While the LCD is on, the PPU locks the OAM during the OAM scan and
+    /// both the VRAM and OAM while drawing. Locked memory reads as 0xff.
+    fn read_byte(&self, i: usize) -> u8 {
+        if !self.accessible(i) {
+            return 0xff;
+        }
+
+        match i {
+            P1 => self.joypad.read(self.mem[i]),
+            MEM_VRAM_BEGIN..=MEM_VRAM_END if self.vram_bank1_selected() => {
+                self.vram_bank1[i - MEM_VRAM_BEGIN]
+            }
+            VBK if self.cgb_mode => self.mem[i] | 0b1111_1110,
+            BCPD if self.cgb_mode => {
+                self.bg_palettes[(self.mem[BCPS] & PALETTE_INDEX_MASK) as usize]
+            }
+            OCPD if self.cgb_mode => {
+                self.obj_palettes[(self.mem[OCPS] & PALETTE_INDEX_MASK) as usize]
+            }
+            _ => self.mem[i],
+        }
+    }
+
+    /// Write a byte as the CPU would. Writes to memory locked by the PPU are
+    /// ignored.
+    fn write_byte(&mut self, i: usize, value: u8) {
+        if !self.accessible(i) {
+            return;
+        }
+
+        self.mem[i] = match i {
+            // The LCD mode and coincidence flag are read only
+            STAT => (value & !0b0000_0111) | (self.mem[i] & 0b0000_0111),
+            LY => self.mem[i],
+            DMA => {
+                self.oam_dma(value);
+                value
+            }
+            P1 => {
+                let lines = self.joypad_lines();
+                self.p1_write = Some(value);
+                self.mem[i] = Joypad::write(value);
+                self.update_joypad_interrupt(lines);
+                return;
+            }
+            MEM_VRAM_BEGIN..=MEM_VRAM_END if self.vram_bank1_selected() => {
+                self.vram_bank1[i - MEM_VRAM_BEGIN] = value;
+                return;
+            }
+            VBK if self.cgb_mode => value & 1,
+            BCPD if self.cgb_mode => {
+                self.write_palette(BCPS, value);
+                return;
+            }
+            OCPD if self.cgb_mode => {
+                self.write_palette(OCPS, value);
+                return;
+            }
+            _ => value,
+        };
+    }
+
+    /// OAM DMA: copy the 0xa0 bytes starting at `page` × 0x100 to the OAM.
+    /// The copy happens at once, rather than over 160 M-cycles.
+    fn oam_dma(&mut self, page: u8) {
+        let mut source = usize::from(page) << 8;
+        if source >= MEM_ECHO_BEGIN {
+            source -= ECHO_OFFSET;
+        }
+
+        for i in 0..OAM_SIZE {
+            let addr = source + i;
+            self.mem[MEM_OAM_BEGIN + i] = match addr {
+                MEM_VRAM_BEGIN..=MEM_VRAM_END if self.vram_bank1_selected() => {
+                    self.vram_bank1[addr - MEM_VRAM_BEGIN]
+                }
+                _ => self.mem[addr],
+            };
+        }
+    }
 
-        self.set_mem_range(bottom + 1, top, data);
+    /// Write to the palette RAM selected by the index register `spec`,
+    /// moving to the next byte if it asks to
+    fn write_palette(&mut self, spec: usize, value: u8) {
+        let spec_value = self.mem[spec];
+        let index = (spec_value & PALETTE_INDEX_MASK) as usize;
+        if spec == BCPS {
+            self.bg_palettes[index] = value;
+        } else {
+            self.obj_palettes[index] = value;
+        }
+
+        if spec_value & PALETTE_AUTO_INCREMENT != 0 {
+            let next = (spec_value + 1) & PALETTE_INDEX_MASK;
+            self.mem[spec] = PALETTE_AUTO_INCREMENT | next;
+        }
+    }
 
-        self.sp = bottom as u16;
+    /// The P1 input lines, low for the pressed buttons of the selected
+    /// groups
+    fn joypad_lines(&self) -> u8 {
+        self.joypad.read(self.mem[P1]) & 0x0f
+    }
+
+    /// Request the joypad interrupt if an input line went from high to low
+    /// since it read `lines`
+    fn update_joypad_interrupt(&mut self, lines: u8) {
+        if lines & !self.joypad_lines() != 0 {
+            self.mem[IF] |= INTERRUPT_JOYPAD;
+        }
+    }
+
+    fn lcd_mode(&self) -> Option<u8> {
+        if self.mem[LCDC] & LCD_ENABLE == LCD_ENABLE {
+            Some(self.mem[STAT] & LCD_MODE_MASK)
+        } else {
+            None
+        }
+    }
+
+    fn accessible(&self, i: usize) -> bool {
+        let mode = self.lcd_mode();
+
+        match i {
+            MEM_VRAM_BEGIN..=MEM_VRAM_END => mode != Some(LCD_MODE_DRAWING),
+            MEM_OAM_BEGIN..=MEM_OAM_END => {
+                mode != Some(LCD_MODE_OAM_SCAN) && mode != Some(LCD_MODE_DRAWING)
+            }
+            BCPD | OCPD => mode != Some(LCD_MODE_DRAWING),
+            _ => true,
+        }
+    }
+
+    /// The DMG OAM corruption bug.
+    ///
+    /// Incrementing or decrementing a 16 bit register pointing to
+    /// 0xfe00-0xfeff while the PPU is scanning the OAM corrupts the row it is
+    /// reading. The first word of the row is mixed with the first and third
+    /// words of the preceding row, and the rest of the row is replaced by the
+    /// preceding row. The first row is never corrupted.
+    fn oam_bug(&mut self, addr: u16) {
+        if self.model.is_cgb()
+            || !(0xfe00..=0xfeff).contains(&addr)
+            || self.lcd_mode() != Some(LCD_MODE_OAM_SCAN)
+            || self.oam_row == 0
+        {
+            return;
+        }
+
+        let row = MEM_OAM_BEGIN + self.oam_row * OAM_ROW_SIZE;
+        let prev = row - OAM_ROW_SIZE;
+        let word = |cpu: &Cpu, i: usize| u8_to_u16(cpu.mem[i + 1], cpu.mem[i]);
+
+        let a = word(self, row);
+        let b = word(self, prev);
+        let c = word(self, prev + 4);
+        let (hi, lo) = u16_to_u8(((a ^ c) & (b ^ c)) ^ c);
+
+        self.mem[row] = lo;
+        self.mem[row + 1] = hi;
+        for i in 2..OAM_ROW_SIZE {
+            self.mem[row + i] = self.mem[prev + i];
+        }
+    }
+
+    /// Push `data` on the stack, through the bus like any other write
+    pub fn push_stack(&mut self, data: &[u8]) {
+        for &byte in data.iter().rev() {
+            self.write_byte(self.sp as usize, byte);
+            self.oam_bug(self.sp);
+            self.sp = self.sp.wrapping_sub(1);
+        }
     }
 
     pub fn push_stack_u16(&mut self, n: u16) {
@@ -165,13 +504,15 @@ impl Cpu {
         self.push_stack(&[b1, b2]);
     }
 
-    pub fn pop_stack(&mut self, n: usize) -> &[u8] {
-        let bottom = (self.sp as usize) + 1;
-        let top = bottom + n - 1;
-
-        self.sp = top as u16;
-
-        self.get_mem_range(bottom, top)
+    /// Pop `n` bytes off the stack, through the bus like any other read
+    pub fn pop_stack(&mut self, n: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(n);
+        for _ in 0..n {
+            self.oam_bug(self.sp);
+            self.sp = self.sp.wrapping_add(1);
+            data.push(self.read_byte(self.sp as usize));
+        }
+        data
     }
 
     pub fn pop_stack_u16(&mut self) -> u16 {
@@ -263,20 +604,34 @@ impl Cpu {
     // Tick
     //
 
-    pub fn tick(&mut self) -> Result<()> {
-        self.print_curr();
-        if self.peek_byte() == opcodes::PREFIX_CB {
-            self.handle_cbprefixed()
-        } else {
-            self.handle_unprefixed()
+    /// Run the next instruction, returning the number of dots (T-cycles) it
+    /// took
+    pub fn tick(&mut self) -> Result<u32> {
+        if cfg!(feature = "verbose") {
+            self.print_curr();
         }
+        self.branch_cycles = 0;
+
+        let opcode = self.peek_byte();
+        let cycles = if opcode == opcodes::PREFIX_CB {
+            let opcode = self.read_byte(self.pc.wrapping_add(1) as usize);
+            self.handle_cbprefixed()?;
+            cycles::cb_prefixed(opcode)
+        } else {
+            self.handle_unprefixed()?;
+            u32::from(cycles::UNPREFIXED[opcode as usize])
+        };
+
+        Ok(cycles + self.branch_cycles)
     }
 
     pub fn handle_unprefixed(&mut self) -> Result<()> {
         let opcode = self.consume_byte();
 
-        print!("{:04x} - {:02x} ", self.pc, opcode);
-        println!("{}", self);
+        if cfg!(feature = "verbose") {
+            print!("{:04x} - {:02x} ", self.pc, opcode);
+            println!("{}", self);
+        }
 
         match opcode {
             opcodes::CALL_A16 => self.call_a16(),
@@ -338,6 +693,7 @@ impl Cpu {
             opcodes::LD_C_H => self.ld_r8_r8(|cpu| cpu.h, |cpu, n| cpu.c = n),
             opcodes::LD_C_L => self.ld_r8_r8(|cpu| cpu.l, |cpu, n| cpu.c = n),
             opcodes::LD_C_HL => self.ld_r8_r16(Cpu::get_hl, |cpu, n| cpu.c = n),
+            opcodes::LD_C_A => self.ld_r8_r8(|cpu| cpu.a, |cpu, n| cpu.c = n),
 
             opcodes::LD_D_B => self.ld_r8_r8(|cpu| cpu.b, |cpu, n| cpu.d = n),
             opcodes::LD_D_C => self.ld_r8_r8(|cpu| cpu.c, |cpu, n| cpu.d = n),
@@ -346,6 +702,7 @@ impl Cpu {
             opcodes::LD_D_H => self.ld_r8_r8(|cpu| cpu.h, |cpu, n| cpu.d = n),
             opcodes::LD_D_L => self.ld_r8_r8(|cpu| cpu.l, |cpu, n| cpu.d = n),
             opcodes::LD_D_HL => self.ld_r8_r16(Cpu::get_hl, |cpu, n| cpu.d = n),
+            opcodes::LD_D_A => self.ld_r8_r8(|cpu| cpu.a, |cpu, n| cpu.d = n),
 
             opcodes::LD_E_B => self.ld_r8_r8(|cpu| cpu.b, |cpu, n| cpu.e = n),
             opcodes::LD_E_C => self.ld_r8_r8(|cpu| cpu.c, |cpu, n| cpu.e = n),
@@ -354,6 +711,7 @@ impl Cpu {
             opcodes::LD_E_H => self.ld_r8_r8(|cpu| cpu.h, |cpu, n| cpu.e = n),
             opcodes::LD_E_L => self.ld_r8_r8(|cpu| cpu.l, |cpu, n| cpu.e = n),
             opcodes::LD_E_HL => self.ld_r8_r16(Cpu::get_hl, |cpu, n| cpu.e = n),
+            opcodes::LD_E_A => self.ld_r8_r8(|cpu| cpu.a, |cpu, n| cpu.e = n),
 
             opcodes::LD_H_B => self.ld_r8_r8(|cpu| cpu.b, |cpu, n| cpu.h = n),
             opcodes::LD_H_C => self.ld_r8_r8(|cpu| cpu.c, |cpu, n| cpu.h = n),
@@ -380,7 +738,9 @@ impl Cpu {
             opcodes::LD_HL_H => self.ld_addr_r8(Cpu::get_hl, |cpu| cpu.h),
             opcodes::LD_HL_L => self.ld_addr_r8(Cpu::get_hl, |cpu| cpu.l),
 
-            opcodes::LD_A_FF00C => self.ld_a(|cpu| cpu.mem[(0xff00 + u16::from(cpu.c)) as usize]),
+            opcodes::LD_A_FF00C => {
+                self.ld_a(|cpu| cpu.read_byte((0xff00 + u16::from(cpu.c)) as usize))
+            }
             opcodes::LD_FF00C_A => self.ld_addr_a(|cpu| 0xff00 + u16::from(cpu.c)),
 
             opcodes::LD_HL_D8 => {
@@ -451,7 +811,7 @@ impl Cpu {
             opcodes::ADD_A_L => self.add_a(|cpu| cpu.l),
             opcodes::ADD_A_HL => {
                 let addr = self.get_hl() as usize;
-                self.add_a(|cpu| cpu.mem[addr])
+                self.add_a(|cpu| cpu.read_byte(addr))
             }
             opcodes::ADD_A_D8 => self.add_a(|cpu| cpu.consume_byte()),
             opcodes::ADD_HL_BC => self.add_hl(Cpu::get_bc),
@@ -469,7 +829,7 @@ impl Cpu {
             opcodes::SUB_A_L => self.sub_a(|cpu| cpu.l),
             opcodes::SUB_A_HL => {
                 let subr = self.get_hl() as usize;
-                self.sub_a(|cpu| cpu.mem[subr])
+                self.sub_a(|cpu| cpu.read_byte(subr))
             }
             opcodes::SUB_A_D8 => self.sub_a(|cpu| cpu.consume_byte()),
 
@@ -482,7 +842,7 @@ impl Cpu {
             opcodes::AND_A_L => self.and_a(|cpu| cpu.l),
             opcodes::AND_A_HL => {
                 let addr = self.get_hl() as usize;
-                self.and_a(|cpu| cpu.mem[addr])
+                self.and_a(|cpu| cpu.read_byte(addr))
             }
             opcodes::AND_A_D8 => self.and_a(|cpu| cpu.consume_byte()),
 
@@ -495,7 +855,7 @@ impl Cpu {
             opcodes::XOR_A_L => self.xor_a(|cpu| cpu.l),
             opcodes::XOR_A_HL => {
                 let addr = self.get_hl() as usize;
-                self.xor_a(|cpu| cpu.mem[addr])
+                self.xor_a(|cpu| cpu.read_byte(addr))
             }
             opcodes::XOR_A_D8 => self.xor_a(|cpu| cpu.consume_byte()),
 
@@ -508,7 +868,7 @@ impl Cpu {
             opcodes::OR_A_L => self.or_a(|cpu| cpu.l),
             opcodes::OR_A_HL => {
                 let addr = self.get_hl() as usize;
-                self.or_a(|cpu| cpu.mem[addr])
+                self.or_a(|cpu| cpu.read_byte(addr))
             }
             opcodes::OR_A_D8 => self.or_a(|cpu| cpu.consume_byte()),
 
@@ -538,11 +898,12 @@ impl Cpu {
             opcodes::ADC_A_L => self.adc_a(|cpu| cpu.l),
             opcodes::ADC_A_HL => {
                 let addr = self.get_hl() as usize;
-                self.adc_a(|cpu| cpu.mem[addr])
+                self.adc_a(|cpu| cpu.read_byte(addr))
             }
             opcodes::ADC_A_D8 => self.adc_a(|cpu| cpu.consume_byte()),
             opcodes::RLCA => self.rlc_a(),
             opcodes::RRCA => self.rrca(),
+            opcodes::RLA => self.rla(),
 
             opcodes::LDHL_SP_R8 => self.ldhl_sp_r8(),
             opcodes::LD_A16_SP => self.ld_a16_sp(),
@@ -579,8 +940,10 @@ impl Cpu {
         self.consume_byte(); // Consume the cb prefix
         let opcode = self.consume_byte();
 
-        print!("{:04x} - {:02x} ", self.pc, opcode);
-        println!("{}", self);
+        if cfg!(feature = "verbose") {
+            print!("{:04x} - {:02x} ", self.pc, opcode);
+            println!("{}", self);
+        }
 
         match opcode {
             opcodes::SRL_A => self.srl(|cpu| cpu.a, |cpu, n| cpu.a = n),
@@ -591,13 +954,93 @@ impl Cpu {
             opcodes::SRL_H => self.srl(|cpu| cpu.h, |cpu, n| cpu.h = n),
             opcodes::SRL_L => self.srl(|cpu| cpu.l, |cpu, n| cpu.l = n),
             opcodes::SRL_HL => self.srl(
-                |cpu| cpu.mem[cpu.get_hl() as usize],
+                |cpu| cpu.read_byte(cpu.get_hl() as usize),
                 |cpu, n| {
                     let addr = cpu.get_hl() as usize;
-                    cpu.set_mem(addr, n);
+                    cpu.write_byte(addr, n);
                 },
             ),
 
+            opcodes::RL_B => self.rl(|cpu| cpu.b, |cpu, n| cpu.b = n),
+            opcodes::RL_C => self.rl(|cpu| cpu.c, |cpu, n| cpu.c = n),
+            opcodes::RL_D => self.rl(|cpu| cpu.d, |cpu, n| cpu.d = n),
+            opcodes::RL_E => self.rl(|cpu| cpu.e, |cpu, n| cpu.e = n),
+            opcodes::RL_H => self.rl(|cpu| cpu.h, |cpu, n| cpu.h = n),
+            opcodes::RL_L => self.rl(|cpu| cpu.l, |cpu, n| cpu.l = n),
+            opcodes::RL_HL => self.rl(
+                |cpu| cpu.read_byte(cpu.get_hl() as usize),
+                |cpu, n| {
+                    let addr = cpu.get_hl() as usize;
+                    cpu.write_byte(addr, n);
+                },
+            ),
+            opcodes::RL_A => self.rl(|cpu| cpu.a, |cpu, n| cpu.a = n),
+
+            opcodes::BIT_0_B => self.bit(0, |cpu| cpu.b),
+            opcodes::BIT_0_C => self.bit(0, |cpu| cpu.c),
+            opcodes::BIT_0_D => self.bit(0, |cpu| cpu.d),
+            opcodes::BIT_0_E => self.bit(0, |cpu| cpu.e),
+            opcodes::BIT_0_H => self.bit(0, |cpu| cpu.h),
+            opcodes::BIT_0_L => self.bit(0, |cpu| cpu.l),
+            opcodes::BIT_0_HL => self.bit(0, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_0_A => self.bit(0, |cpu| cpu.a),
+            opcodes::BIT_1_B => self.bit(1, |cpu| cpu.b),
+            opcodes::BIT_1_C => self.bit(1, |cpu| cpu.c),
+            opcodes::BIT_1_D => self.bit(1, |cpu| cpu.d),
+            opcodes::BIT_1_E => self.bit(1, |cpu| cpu.e),
+            opcodes::BIT_1_H => self.bit(1, |cpu| cpu.h),
+            opcodes::BIT_1_L => self.bit(1, |cpu| cpu.l),
+            opcodes::BIT_1_HL => self.bit(1, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_1_A => self.bit(1, |cpu| cpu.a),
+            opcodes::BIT_2_B => self.bit(2, |cpu| cpu.b),
+            opcodes::BIT_2_C => self.bit(2, |cpu| cpu.c),
+            opcodes::BIT_2_D => self.bit(2, |cpu| cpu.d),
+            opcodes::BIT_2_E => self.bit(2, |cpu| cpu.e),
+            opcodes::BIT_2_H => self.bit(2, |cpu| cpu.h),
+            opcodes::BIT_2_L => self.bit(2, |cpu| cpu.l),
+            opcodes::BIT_2_HL => self.bit(2, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_2_A => self.bit(2, |cpu| cpu.a),
+            opcodes::BIT_3_B => self.bit(3, |cpu| cpu.b),
+            opcodes::BIT_3_C => self.bit(3, |cpu| cpu.c),
+            opcodes::BIT_3_D => self.bit(3, |cpu| cpu.d),
+            opcodes::BIT_3_E => self.bit(3, |cpu| cpu.e),
+            opcodes::BIT_3_H => self.bit(3, |cpu| cpu.h),
+            opcodes::BIT_3_L => self.bit(3, |cpu| cpu.l),
+            opcodes::BIT_3_HL => self.bit(3, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_3_A => self.bit(3, |cpu| cpu.a),
+            opcodes::BIT_4_B => self.bit(4, |cpu| cpu.b),
+            opcodes::BIT_4_C => self.bit(4, |cpu| cpu.c),
+            opcodes::BIT_4_D => self.bit(4, |cpu| cpu.d),
+            opcodes::BIT_4_E => self.bit(4, |cpu| cpu.e),
+            opcodes::BIT_4_H => self.bit(4, |cpu| cpu.h),
+            opcodes::BIT_4_L => self.bit(4, |cpu| cpu.l),
+            opcodes::BIT_4_HL => self.bit(4, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_4_A => self.bit(4, |cpu| cpu.a),
+            opcodes::BIT_5_B => self.bit(5, |cpu| cpu.b),
+            opcodes::BIT_5_C => self.bit(5, |cpu| cpu.c),
+            opcodes::BIT_5_D => self.bit(5, |cpu| cpu.d),
+            opcodes::BIT_5_E => self.bit(5, |cpu| cpu.e),
+            opcodes::BIT_5_H => self.bit(5, |cpu| cpu.h),
+            opcodes::BIT_5_L => self.bit(5, |cpu| cpu.l),
+            opcodes::BIT_5_HL => self.bit(5, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_5_A => self.bit(5, |cpu| cpu.a),
+            opcodes::BIT_6_B => self.bit(6, |cpu| cpu.b),
+            opcodes::BIT_6_C => self.bit(6, |cpu| cpu.c),
+            opcodes::BIT_6_D => self.bit(6, |cpu| cpu.d),
+            opcodes::BIT_6_E => self.bit(6, |cpu| cpu.e),
+            opcodes::BIT_6_H => self.bit(6, |cpu| cpu.h),
+            opcodes::BIT_6_L => self.bit(6, |cpu| cpu.l),
+            opcodes::BIT_6_HL => self.bit(6, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_6_A => self.bit(6, |cpu| cpu.a),
+            opcodes::BIT_7_B => self.bit(7, |cpu| cpu.b),
+            opcodes::BIT_7_C => self.bit(7, |cpu| cpu.c),
+            opcodes::BIT_7_D => self.bit(7, |cpu| cpu.d),
+            opcodes::BIT_7_E => self.bit(7, |cpu| cpu.e),
+            opcodes::BIT_7_H => self.bit(7, |cpu| cpu.h),
+            opcodes::BIT_7_L => self.bit(7, |cpu| cpu.l),
+            opcodes::BIT_7_HL => self.bit(7, |cpu| cpu.read_byte(cpu.get_hl() as usize)),
+            opcodes::BIT_7_A => self.bit(7, |cpu| cpu.a),
+
             s => {
                 return Err(Error::new(
                     ErrorKind::UnknownInstruction,
@@ -610,7 +1053,7 @@ impl Cpu {
     }
 
     fn peek_byte(&self) -> u8 {
-        self.mem[self.pc as usize]
+        self.read_byte(self.pc as usize)
     }
 
     fn consume_byte(&mut self) -> u8 {
@@ -673,6 +1116,7 @@ impl Cpu {
     {
         if condition(self) {
             self.call_a16();
+            self.branch_cycles = cycles::CALL_TAKEN;
         }
     }
 
@@ -684,7 +1128,8 @@ impl Cpu {
         F: Fn(&Cpu) -> u16,
     {
         let addr = f(self) as usize;
-        self.mem[addr] = self.a;
+        let a = self.a;
+        self.write_byte(addr, a);
     }
 
     /// **Description**
@@ -724,7 +1169,7 @@ impl Cpu {
         G: Fn(&Cpu) -> u16,
         F: Fn(&mut Cpu, u8),
     {
-        let r2 = self.mem[getter(self) as usize] as u8;
+        let r2 = self.read_byte(getter(self) as usize);
         setter(self, r2);
     }
 
@@ -740,7 +1185,7 @@ impl Cpu {
         F: Fn(&mut Cpu, u8),
     {
         let addr = self.consume_16_addr();
-        let r2 = self.mem[addr as usize] as u8;
+        let r2 = self.read_byte(addr as usize);
         f(self, r2);
     }
 
@@ -827,6 +1272,7 @@ impl Cpu {
     {
         if condition(self) {
             self.jp_a16();
+            self.branch_cycles = cycles::JP_TAKEN;
         } else {
             // Ensure that the address is consumed even if we don't jump
             self.consume_16_addr();
@@ -840,7 +1286,8 @@ impl Cpu {
     /// n = one byte immediate value.
     fn ldh_a8_a(&mut self) {
         let n = self.consume_byte() as usize;
-        self.mem[MEM_HW_IO_REG_OFFSET + n] = self.a;
+        let a = self.a;
+        self.write_byte(MEM_HW_IO_REG_OFFSET + n, a);
     }
 
     ///**Description:**
@@ -850,7 +1297,7 @@ impl Cpu {
     /// n = one byte immediate value.
     fn ldh_a_a8(&mut self) {
         let n = self.consume_byte() as usize;
-        self.a = self.mem[MEM_HW_IO_REG_OFFSET + n];
+        self.a = self.read_byte(MEM_HW_IO_REG_OFFSET + n);
     }
 
     ///**Description:**
@@ -889,7 +1336,7 @@ impl Cpu {
     /// Put value at address HL into A. Decrement HL.
     /// Same as: LD A,(HL) - DEC HL
     fn ldd_a_hl(&mut self) {
-        self.ld_a(|cpu| cpu.mem[cpu.get_hl() as usize]);
+        self.ld_a(|cpu| cpu.read_byte(cpu.get_hl() as usize));
         self.dec_r16(Cpu::get_hl, Cpu::set_hl);
     }
 
@@ -912,6 +1359,7 @@ impl Cpu {
     {
         if condition(self) {
             self.ret();
+            self.branch_cycles = cycles::RET_TAKEN;
         }
     }
 
@@ -973,14 +1421,14 @@ impl Cpu {
         G: Fn(&Cpu) -> u16,
     {
         let addr = getter(self) as usize;
-        let old_value = self.mem[addr];
+        let old_value = self.read_byte(addr);
         let new_value = old_value.wrapping_add(1);
 
         self.set_flag_to(&Flag::Zero, new_value == 0);
         self.set_flag_to(&Flag::HalfCarry, old_value & 0xf == 0xf);
         self.reset_flag(&Flag::Sub);
 
-        self.mem[addr] = new_value;
+        self.write_byte(addr, new_value);
     }
 
     ///**Description:**
@@ -994,6 +1442,7 @@ impl Cpu {
         S: Fn(&mut Cpu, u16),
     {
         let curr_value = getter(self);
+        self.oam_bug(curr_value);
         setter(self, curr_value.wrapping_add(1));
     }
 
@@ -1027,14 +1476,14 @@ impl Cpu {
         G: Fn(&Cpu) -> u16,
     {
         let addr = getter(self) as usize;
-        let old_value = self.mem[addr];
+        let old_value = self.read_byte(addr);
         let new_value = old_value.wrapping_sub(1);
 
         self.set_flag_to(&Flag::Zero, new_value == 0);
         self.set_flag_to(&Flag::HalfCarry, old_value & 0xf == 0);
         self.set_flag(&Flag::Sub);
 
-        self.mem[addr] = new_value;
+        self.write_byte(addr, new_value);
     }
 
     ///**Description:**
@@ -1048,6 +1497,7 @@ impl Cpu {
         S: Fn(&mut Cpu, u16),
     {
         let curr_value = getter(self);
+        self.oam_bug(curr_value);
         setter(self, curr_value.wrapping_sub(1));
     }
 
@@ -1118,7 +1568,7 @@ impl Cpu {
     ///  N - Reset.
     ///  H - Set if carry from bit 11 (always set on subtraction).
     ///  C - Set if carry from bit 15 (always set on subtraction).
-    #[cfg_attr(feature = "clippy", allow(cast_lossless))]
+    #[allow(clippy::cast_lossless)]
     fn add_sp_imm(&mut self) {
         let old_value = self.sp;
         let n = i16::from(self.consume_byte() as i8) as u16;
@@ -1253,6 +1703,7 @@ impl Cpu {
     {
         if condition(self) {
             self.jr_r8();
+            self.branch_cycles = cycles::JR_TAKEN;
         } else {
             // Ensure that the address is consumed even if we don't jump
             self.consume_byte();
@@ -1315,7 +1766,7 @@ impl Cpu {
     ///  C - Set for no borrow. (Set if A < n.)
     /// TODO: implement this with the SUB instruction instead
     fn cp_hl(&mut self) {
-        let n = self.mem[self.get_hl() as usize];
+        let n = self.read_byte(self.get_hl() as usize);
         let a = self.a;
 
         self.set_flag_to(&Flag::Zero, a == n);
@@ -1333,7 +1784,8 @@ impl Cpu {
         G: Fn(&Cpu) -> u16,
     {
         let addr = r1(self) as usize;
-        self.mem[addr] = r2(self);
+        let value = r2(self);
+        self.write_byte(addr, value);
     }
 
     ///**Description:**
@@ -1410,6 +1862,27 @@ impl Cpu {
         self.a = a;
     }
 
+    ///**Description:**
+    ///  Rotate A left through Carry flag.
+    ///
+    ///**Flags affected:**
+    ///  Z - Reset.
+    ///  N - Reset.
+    ///  H - Reset.
+    ///  C - Contains old bit 7 data.
+    fn rla(&mut self) {
+        let carry = if self.flag(&Flag::Carry) { 1 } else { 0 };
+        let msb = self.a >> 7;
+        let a = (self.a << 1) | carry;
+
+        self.reset_flag(&Flag::Zero);
+        self.reset_flag(&Flag::Sub);
+        self.reset_flag(&Flag::HalfCarry);
+        self.set_flag_to(&Flag::Carry, msb == 1);
+
+        self.a = a;
+    }
+
     ///**Description:**
     ///  Put SP + n effective address into HL.
     ///
@@ -1477,4 +1950,51 @@ impl Cpu {
         self.set_flag_to(&Flag::Carry, carry);
         self.set_flag_to(&Flag::Zero, result == 0);
     }
+
+    ///**Description:**
+    /// Rotate n left through Carry flag.
+    ///
+    ///**Use with:**
+    ///  n = A,B,C,D,E,H,L,(HL)
+    ///**Flags affected:**
+    ///  Z - Set if result is zero.
+    ///  N - Reset.
+    ///  H - Reset.
+    ///  C - Contains old bit 7 data.
+    fn rl<G, S>(&mut self, g: G, s: S)
+    where
+        G: Fn(&Cpu) -> u8,
+        S: Fn(&mut Cpu, u8),
+    {
+        let value = g(self);
+        let carry = if self.flag(&Flag::Carry) { 1 } else { 0 };
+        let result = (value << 1) | carry;
+
+        s(self, result);
+        self.set_flag_to(&Flag::Zero, result == 0);
+        self.reset_flag(&Flag::Sub);
+        self.reset_flag(&Flag::HalfCarry);
+        self.set_flag_to(&Flag::Carry, value & 0x80 == 0x80);
+    }
+
+    ///**Description:**
+    /// Test bit b in register r.
+    ///
+    ///**Use with:**
+    ///  b = 0 - 7, r = A,B,C,D,E,H,L,(HL)
+    ///**Flags affected:**
+    ///  Z - Set if bit b of register r is 0.
+    ///  N - Reset.
+    ///  H - Set.
+    ///  C - Not affected.
+    fn bit<G>(&mut self, b: u8, g: G)
+    where
+        G: Fn(&Cpu) -> u8,
+    {
+        let value = g(self);
+
+        self.set_flag_to(&Flag::Zero, value & (1 << b) == 0);
+        self.reset_flag(&Flag::Sub);
+        self.set_flag(&Flag::HalfCarry);
+    }
 }
@@ -44,6 +44,7 @@ pub const LD_C_E: u8 = 0x4b;
 pub const LD_C_H: u8 = 0x4c;
 pub const LD_C_L: u8 = 0x4d;
 pub const LD_C_HL: u8 = 0x4e;
+pub const LD_C_A: u8 = 0x4f;
 pub const LD_D_B: u8 = 0x50;
 pub const LD_D_C: u8 = 0x51;
 pub const LD_D_D: u8 = 0x52;
@@ -51,6 +52,7 @@ pub const LD_D_E: u8 = 0x53;
 pub const LD_D_H: u8 = 0x54;
 pub const LD_D_L: u8 = 0x55;
 pub const LD_D_HL: u8 = 0x56;
+pub const LD_D_A: u8 = 0x57;
 pub const LD_E_B: u8 = 0x58;
 pub const LD_E_C: u8 = 0x59;
 pub const LD_E_D: u8 = 0x5a;
@@ -58,6 +60,7 @@ pub const LD_E_E: u8 = 0x5b;
 pub const LD_E_H: u8 = 0x5c;
 pub const LD_E_L: u8 = 0x5d;
 pub const LD_E_HL: u8 = 0x5e;
+pub const LD_E_A: u8 = 0x5f;
 pub const LD_H_B: u8 = 0x60;
 pub const LD_H_C: u8 = 0x61;
 pub const LD_H_D: u8 = 0x62;
@@ -212,6 +215,7 @@ pub const ADC_A_HL: u8 = 0x8e;
 pub const ADC_A_D8: u8 = 0xce;
 pub const RLCA: u8 = 0x07;
 pub const RRCA: u8 = 0x0f;
+pub const RLA: u8 = 0x17;
 pub const EI: u8 = 0xfb;
 pub const RST_00: u8 = 0xc7;
 pub const RST_08: u8 = 0xcf;
@@ -232,3 +236,77 @@ pub const SRL_E: u8 = 0x3b;
 pub const SRL_H: u8 = 0x3c;
 pub const SRL_L: u8 = 0x3d;
 pub const SRL_HL: u8 = 0x3e;
+
+pub const RL_B: u8 = 0x10;
+pub const RL_C: u8 = 0x11;
+pub const RL_D: u8 = 0x12;
+pub const RL_E: u8 = 0x13;
+pub const RL_H: u8 = 0x14;
+pub const RL_L: u8 = 0x15;
+pub const RL_HL: u8 = 0x16;
+pub const RL_A: u8 = 0x17;
+
+pub const BIT_0_B: u8 = 0x40;
+pub const BIT_0_C: u8 = 0x41;
+pub const BIT_0_D: u8 = 0x42;
+pub const BIT_0_E: u8 = 0x43;
+pub const BIT_0_H: u8 = 0x44;
+pub const BIT_0_L: u8 = 0x45;
+pub const BIT_0_HL: u8 = 0x46;
+pub const BIT_0_A: u8 = 0x47;
+pub const BIT_1_B: u8 = 0x48;
+pub const BIT_1_C: u8 = 0x49;
+pub const BIT_1_D: u8 = 0x4a;
+pub const BIT_1_E: u8 = 0x4b;
+pub const BIT_1_H: u8 = 0x4c;
+pub const BIT_1_L: u8 = 0x4d;
+pub const BIT_1_HL: u8 = 0x4e;
+pub const BIT_1_A: u8 = 0x4f;
+pub const BIT_2_B: u8 = 0x50;
+pub const BIT_2_C: u8 = 0x51;
+pub const BIT_2_D: u8 = 0x52;
+pub const BIT_2_E: u8 = 0x53;
+pub const BIT_2_H: u8 = 0x54;
+pub const BIT_2_L: u8 = 0x55;
+pub const BIT_2_HL: u8 = 0x56;
+pub const BIT_2_A: u8 = 0x57;
+pub const BIT_3_B: u8 = 0x58;
+pub const BIT_3_C: u8 = 0x59;
+pub const BIT_3_D: u8 = 0x5a;
+pub const BIT_3_E: u8 = 0x5b;
+pub const BIT_3_H: u8 = 0x5c;
+pub const BIT_3_L: u8 = 0x5d;
+pub const BIT_3_HL: u8 = 0x5e;
+pub const BIT_3_A: u8 = 0x5f;
+pub const BIT_4_B: u8 = 0x60;
+pub const BIT_4_C: u8 = 0x61;
+pub const BIT_4_D: u8 = 0x62;
+pub const BIT_4_E: u8 = 0x63;
+pub const BIT_4_H: u8 = 0x64;
+pub const BIT_4_L: u8 = 0x65;
+pub const BIT_4_HL: u8 = 0x66;
+pub const BIT_4_A: u8 = 0x67;
+pub const BIT_5_B: u8 = 0x68;
+pub const BIT_5_C: u8 = 0x69;
+pub const BIT_5_D: u8 = 0x6a;
+pub const BIT_5_E: u8 = 0x6b;
+pub const BIT_5_H: u8 = 0x6c;
+pub const BIT_5_L: u8 = 0x6d;
+pub const BIT_5_HL: u8 = 0x6e;
+pub const BIT_5_A: u8 = 0x6f;
+pub const BIT_6_B: u8 = 0x70;
+pub const BIT_6_C: u8 = 0x71;
+pub const BIT_6_D: u8 = 0x72;
+pub const BIT_6_E: u8 = 0x73;
+pub const BIT_6_H: u8 = 0x74;
+pub const BIT_6_L: u8 = 0x75;
+pub const BIT_6_HL: u8 = 0x76;
+pub const BIT_6_A: u8 = 0x77;
+pub const BIT_7_B: u8 = 0x78;
+pub const BIT_7_C: u8 = 0x79;
+pub const BIT_7_D: u8 = 0x7a;
+pub const BIT_7_E: u8 = 0x7b;
+pub const BIT_7_H: u8 = 0x7c;
+pub const BIT_7_L: u8 = 0x7d;
+pub const BIT_7_HL: u8 = 0x7e;
+pub const BIT_7_A: u8 = 0x7f;
@@ -131,6 +131,7 @@ impl<'a> Printer<'a> {
             opcodes::LD_C_H => println!("LD\tC,H"),
             opcodes::LD_C_L => println!("LD\tC,L"),
             opcodes::LD_C_HL => println!("LD\tC,(HL)"),
+            opcodes::LD_C_A => println!("LD\tC,A"),
             opcodes::LD_D_B => println!("LD\tD,B,"),
             opcodes::LD_D_C => println!("LD\tD,C"),
             opcodes::LD_D_D => println!("LD\tD,D"),
@@ -138,6 +139,7 @@ impl<'a> Printer<'a> {
             opcodes::LD_D_H => println!("LD\tD,H"),
             opcodes::LD_D_L => println!("LD\tD,L"),
             opcodes::LD_D_HL => println!("LD\tD,(HL)"),
+            opcodes::LD_D_A => println!("LD\tD,A"),
             opcodes::LD_E_B => println!("LD\tE,B,"),
             opcodes::LD_E_C => println!("LD\tE,C"),
             opcodes::LD_E_D => println!("LD\tE,D"),
@@ -145,6 +147,7 @@ impl<'a> Printer<'a> {
             opcodes::LD_E_H => println!("LD\tE,H"),
             opcodes::LD_E_L => println!("LD\tE,L"),
             opcodes::LD_E_HL => println!("LD\tE,(HL)"),
+            opcodes::LD_E_A => println!("LD\tE,A"),
             opcodes::LD_H_B => println!("LD\tH,B,"),
             opcodes::LD_H_C => println!("LD\tH,C"),
             opcodes::LD_H_D => println!("LD\tH,D"),
@@ -299,6 +302,7 @@ impl<'a> Printer<'a> {
             opcodes::ADC_A_D8 => println!("ADC\tA,{}", self.print_8_imm()),
             opcodes::RLCA => println!("RLCA"),
             opcodes::RRCA => println!("RRCA"),
+            opcodes::RLA => println!("RLA"),
 
             opcodes::LDHL_SP_R8 => println!("LDHL\tSP,{}", self.print_8_sig()),
             opcodes::LD_A16_SP => println!("LD\t{},SP", self.read_16_addr()),
@@ -327,7 +331,6 @@ impl<'a> Printer<'a> {
         let opcode = self.cpu.mem[addr];
         print!("({:02x}{:02x}) ", opcodes::PREFIX_CB, opcode);
         match opcode {
-
             opcodes::SRL_A => println!("SRL A"),
             opcodes::SRL_B => println!("SRL B"),
             opcodes::SRL_C => println!("SRL C"),
@@ -337,6 +340,80 @@ impl<'a> Printer<'a> {
             opcodes::SRL_L => println!("SRL L"),
             opcodes::SRL_HL => println!("SRL (HL)"),
 
+            opcodes::RL_B => println!("RL B"),
+            opcodes::RL_C => println!("RL C"),
+            opcodes::RL_D => println!("RL D"),
+            opcodes::RL_E => println!("RL E"),
+            opcodes::RL_H => println!("RL H"),
+            opcodes::RL_L => println!("RL L"),
+            opcodes::RL_HL => println!("RL (HL)"),
+            opcodes::RL_A => println!("RL A"),
+
+            opcodes::BIT_0_B => println!("BIT 0,B"),
+            opcodes::BIT_0_C => println!("BIT 0,C"),
+            opcodes::BIT_0_D => println!("BIT 0,D"),
+            opcodes::BIT_0_E => println!("BIT 0,E"),
+            opcodes::BIT_0_H => println!("BIT 0,H"),
+            opcodes::BIT_0_L => println!("BIT 0,L"),
+            opcodes::BIT_0_HL => println!("BIT 0,(HL)"),
+            opcodes::BIT_0_A => println!("BIT 0,A"),
+            opcodes::BIT_1_B => println!("BIT 1,B"),
+            opcodes::BIT_1_C => println!("BIT 1,C"),
+            opcodes::BIT_1_D => println!("BIT 1,D"),
+            opcodes::BIT_1_E => println!("BIT 1,E"),
+            opcodes::BIT_1_H => println!("BIT 1,H"),
+            opcodes::BIT_1_L => println!("BIT 1,L"),
+            opcodes::BIT_1_HL => println!("BIT 1,(HL)"),
+            opcodes::BIT_1_A => println!("BIT 1,A"),
+            opcodes::BIT_2_B => println!("BIT 2,B"),
+            opcodes::BIT_2_C => println!("BIT 2,C"),
+            opcodes::BIT_2_D => println!("BIT 2,D"),
+            opcodes::BIT_2_E => println!("BIT 2,E"),
+            opcodes::BIT_2_H => println!("BIT 2,H"),
+            opcodes::BIT_2_L => println!("BIT 2,L"),
+            opcodes::BIT_2_HL => println!("BIT 2,(HL)"),
+            opcodes::BIT_2_A => println!("BIT 2,A"),
+            opcodes::BIT_3_B => println!("BIT 3,B"),
+            opcodes::BIT_3_C => println!("BIT 3,C"),
+            opcodes::BIT_3_D => println!("BIT 3,D"),
+            opcodes::BIT_3_E => println!("BIT 3,E"),
+            opcodes::BIT_3_H => println!("BIT 3,H"),
+            opcodes::BIT_3_L => println!("BIT 3,L"),
+            opcodes::BIT_3_HL => println!("BIT 3,(HL)"),
+            opcodes::BIT_3_A => println!("BIT 3,A"),
+            opcodes::BIT_4_B => println!("BIT 4,B"),
+            opcodes::BIT_4_C => println!("BIT 4,C"),
+            opcodes::BIT_4_D => println!("BIT 4,D"),
+            opcodes::BIT_4_E => println!("BIT 4,E"),
+            opcodes::BIT_4_H => println!("BIT 4,H"),
+            opcodes::BIT_4_L => println!("BIT 4,L"),
+            opcodes::BIT_4_HL => println!("BIT 4,(HL)"),
+            opcodes::BIT_4_A => println!("BIT 4,A"),
+            opcodes::BIT_5_B => println!("BIT 5,B"),
+            opcodes::BIT_5_C => println!("BIT 5,C"),
+            opcodes::BIT_5_D => println!("BIT 5,D"),
+            opcodes::BIT_5_E => println!("BIT 5,E"),
+            opcodes::BIT_5_H => println!("BIT 5,H"),
+            opcodes::BIT_5_L => println!("BIT 5,L"),
+            opcodes::BIT_5_HL => println!("BIT 5,(HL)"),
+            opcodes::BIT_5_A => println!("BIT 5,A"),
+            opcodes::BIT_6_B => println!("BIT 6,B"),
+            opcodes::BIT_6_C => println!("BIT 6,C"),
+            opcodes::BIT_6_D => println!("BIT 6,D"),
+            opcodes::BIT_6_E => println!("BIT 6,E"),
+            opcodes::BIT_6_H => println!("BIT 6,H"),
+            opcodes::BIT_6_L => println!("BIT 6,L"),
+            opcodes::BIT_6_HL => println!("BIT 6,(HL)"),
+            opcodes::BIT_6_A => println!("BIT 6,A"),
+            opcodes::BIT_7_B => println!("BIT 7,B"),
+            opcodes::BIT_7_C => println!("BIT 7,C"),
+            opcodes::BIT_7_D => println!("BIT 7,D"),
+            opcodes::BIT_7_E => println!("BIT 7,E"),
+            opcodes::BIT_7_H => println!("BIT 7,H"),
+            opcodes::BIT_7_L => println!("BIT 7,L"),
+            opcodes::BIT_7_HL => println!("BIT 7,(HL)"),
+            opcodes::BIT_7_A => println!("BIT 7,A"),
+
             n => panic!("Unknown instruction {:02x}@{:04x}", n, addr),
         }
     }
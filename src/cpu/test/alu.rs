@@ -1,4 +1,5 @@
 #![cfg(test)]
+#![allow(clippy::identity_op)]
 use super::*;
 
 //
@@ -684,7 +685,7 @@ fn test_add_sp_r8() {
     let cpu = &mut new_cpu();
     cpu.sp = 0x0017;
     cpu.mem[0] = opcodes::ADD_SP_R8;
-    cpu.mem[1] = -1 as i8 as u8;
+    cpu.mem[1] = -1i8 as u8;
     cpu.tick().unwrap();
 
     assert_eq!(cpu.sp, 0x0016);
@@ -166,3 +166,20 @@ fn test_jr_c_r8() {
     _test_jr_flag_set(opcodes::JR_C_R8, Flag::Carry, true);
     _test_jr_flag_reset(opcodes::JR_C_R8, Flag::Carry, false);
 }
+
+#[test]
+fn test_branch_cycles() {
+    let mut cpu = Cpu::new();
+    cpu.sp = 0xfffe;
+    cpu.mem[0] = opcodes::JR_Z_R8;
+    cpu.mem[2] = opcodes::JR_NZ_R8;
+    cpu.mem[4] = opcodes::CALL_NZ_A16;
+    cpu.mem[5] = 0x00;
+    cpu.mem[6] = 0x10;
+    cpu.mem[0x1000] = opcodes::RET_C;
+
+    assert_eq!(cpu.tick().unwrap(), 8);
+    assert_eq!(cpu.tick().unwrap(), 12);
+    assert_eq!(cpu.tick().unwrap(), 24);
+    assert_eq!(cpu.tick().unwrap(), 8);
+}
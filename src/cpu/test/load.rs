@@ -148,7 +148,7 @@ fn test_ld_ff00c_a() {
     cpu.mem[0] = opcodes::LD_FF00C_A;
 
     cpu.tick().unwrap();
-    assert_eq!(cpu.mem[(0xff04 as u16) as usize], 0x72);
+    assert_eq!(cpu.mem[0xff04], 0x72);
 }
 
 #[test]
@@ -308,6 +308,21 @@ fn test_ld_c_l() {
     _test_ld_reg_reg(|cpu| cpu.c, |cpu, n| cpu.l = n, 0x72, opcodes::LD_C_L);
 }
 
+#[test]
+fn test_ld_c_a() {
+    _test_ld_reg_reg(|cpu| cpu.c, |cpu, n| cpu.a = n, 0x72, opcodes::LD_C_A);
+}
+
+#[test]
+fn test_ld_d_a() {
+    _test_ld_reg_reg(|cpu| cpu.d, |cpu, n| cpu.a = n, 0x72, opcodes::LD_D_A);
+}
+
+#[test]
+fn test_ld_e_a() {
+    _test_ld_reg_reg(|cpu| cpu.e, |cpu, n| cpu.a = n, 0x72, opcodes::LD_E_A);
+}
+
 #[test]
 fn test_ld_c_hl() {
     _test_ld_reg_addr(|cpu| cpu.c, Cpu::set_hl, 5, 0xb00b, opcodes::LD_C_HL);
@@ -708,7 +723,7 @@ fn test_ldhl_sp_r8() {
     let cpu = &mut new_cpu();
     cpu.sp = 0x0017;
     cpu.mem[0] = opcodes::LDHL_SP_R8;
-    cpu.mem[1] = -1 as i8 as u8;
+    cpu.mem[1] = -1i8 as u8;
     cpu.tick().unwrap();
 
     assert_eq!(cpu.get_hl(), 0x0016);
@@ -424,3 +424,157 @@ fn test_rrca() {
     assert!(!cpu.flag(&Flag::Carry));
     assert!(!cpu.flag(&Flag::Zero));
 }
+
+#[test]
+fn test_rla() {
+    let mut cpu = Cpu::new();
+    cpu.mem[0] = opcodes::RLA;
+    cpu.a = 0x95;
+    cpu.set_flag(&Flag::Carry);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.a, 0x2b);
+    assert!(cpu.flag(&Flag::Carry));
+    assert!(!cpu.flag(&Flag::Zero));
+
+    cpu = Cpu::new();
+    cpu.mem[cpu.pc as usize] = opcodes::RLA;
+    cpu.a = 0x80;
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.a, 0);
+    assert!(cpu.flag(&Flag::Carry));
+    assert!(!cpu.flag(&Flag::Zero));
+}
+
+fn _test_rl_reg<G, S>(opcode: u8, reg_getter: G, reg_setter: S)
+where
+    G: Fn(&Cpu) -> u8,
+    S: Fn(&mut Cpu, u8),
+{
+    let prepare_and_execute = |value, carry| {
+        let mut cpu = Cpu::new();
+        reg_setter(&mut cpu, value);
+        cpu.set_flag_to(&Flag::Carry, carry);
+        cpu.mem[0] = opcodes::PREFIX_CB;
+        cpu.mem[1] = opcode;
+
+        cpu.tick().unwrap();
+
+        cpu
+    };
+
+    // Test rotation through carry
+    let cpu = prepare_and_execute(0b0100_1000, true);
+    assert_eq!(reg_getter(&cpu), 0b1001_0001);
+    assert!(!cpu.flag(&Flag::Zero));
+    assert!(!cpu.flag(&Flag::Sub));
+    assert!(!cpu.flag(&Flag::HalfCarry));
+    assert!(!cpu.flag(&Flag::Carry));
+
+    // Test carry and zero flags are set
+    let cpu = prepare_and_execute(0b1000_0000, false);
+    assert_eq!(reg_getter(&cpu), 0);
+    assert!(cpu.flag(&Flag::Zero));
+    assert!(cpu.flag(&Flag::Carry));
+}
+
+#[test]
+fn test_rl_a() {
+    _test_rl_reg(opcodes::RL_A, |cpu| cpu.a, |cpu, n| cpu.a = n);
+}
+
+#[test]
+fn test_rl_b() {
+    _test_rl_reg(opcodes::RL_B, |cpu| cpu.b, |cpu, n| cpu.b = n);
+}
+
+#[test]
+fn test_rl_c() {
+    _test_rl_reg(opcodes::RL_C, |cpu| cpu.c, |cpu, n| cpu.c = n);
+}
+
+#[test]
+fn test_rl_d() {
+    _test_rl_reg(opcodes::RL_D, |cpu| cpu.d, |cpu, n| cpu.d = n);
+}
+
+#[test]
+fn test_rl_e() {
+    _test_rl_reg(opcodes::RL_E, |cpu| cpu.e, |cpu, n| cpu.e = n);
+}
+
+#[test]
+fn test_rl_h() {
+    _test_rl_reg(opcodes::RL_H, |cpu| cpu.h, |cpu, n| cpu.h = n);
+}
+
+#[test]
+fn test_rl_l() {
+    _test_rl_reg(opcodes::RL_L, |cpu| cpu.l, |cpu, n| cpu.l = n);
+}
+
+#[test]
+fn test_rl_hl() {
+    _test_rl_reg(
+        opcodes::RL_HL,
+        |cpu| cpu.mem[cpu.get_hl() as usize],
+        |cpu, n| {
+            cpu.set_hl(0x55);
+            cpu.set_mem(0x55, n);
+        },
+    );
+}
+
+fn _test_bit<S>(opcode: u8, bit: u8, reg_setter: S)
+where
+    S: Fn(&mut Cpu, u8),
+{
+    let prepare_and_execute = |value| {
+        let mut cpu = Cpu::new();
+        reg_setter(&mut cpu, value);
+        cpu.set_flag(&Flag::Sub);
+        cpu.set_flag(&Flag::Carry);
+        cpu.mem[0] = opcodes::PREFIX_CB;
+        cpu.mem[1] = opcode;
+
+        cpu.tick().unwrap();
+
+        cpu
+    };
+
+    let cpu = prepare_and_execute(1 << bit);
+    assert!(!cpu.flag(&Flag::Zero));
+    assert!(!cpu.flag(&Flag::Sub));
+    assert!(cpu.flag(&Flag::HalfCarry));
+    assert!(cpu.flag(&Flag::Carry));
+
+    let cpu = prepare_and_execute(!(1 << bit));
+    assert!(cpu.flag(&Flag::Zero));
+    assert!(!cpu.flag(&Flag::Sub));
+    assert!(cpu.flag(&Flag::HalfCarry));
+    assert!(cpu.flag(&Flag::Carry));
+}
+
+#[test]
+fn test_bit_0_b() {
+    _test_bit(opcodes::BIT_0_B, 0, |cpu, n| cpu.b = n);
+}
+
+#[test]
+fn test_bit_3_a() {
+    _test_bit(opcodes::BIT_3_A, 3, |cpu, n| cpu.a = n);
+}
+
+#[test]
+fn test_bit_7_h() {
+    _test_bit(opcodes::BIT_7_H, 7, |cpu, n| cpu.h = n);
+}
+
+#[test]
+fn test_bit_5_hl() {
+    _test_bit(opcodes::BIT_5_HL, 5, |cpu, n| {
+        cpu.set_hl(0x55);
+        cpu.set_mem(0x55, n);
+    });
+}
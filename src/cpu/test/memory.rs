@@ -0,0 +1,317 @@
+#![cfg(test)]
+use super::*;
+
+fn cpu_in_lcd_mode(mode: u8) -> Cpu {
+    let mut cpu = Cpu::new();
+    cpu.mem[LCDC] = LCD_ENABLE;
+    cpu.mem[STAT] = mode;
+    cpu
+}
+
+#[test]
+fn test_vram_locked_while_drawing() {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_DRAWING);
+    cpu.mem[0] = opcodes::LD_A_HL;
+    cpu.mem[0x8010] = 0x42;
+    cpu.set_hl(0x8010);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.a, 0xff);
+
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_DRAWING);
+    cpu.mem[0] = opcodes::LD_HL_A;
+    cpu.a = 0x42;
+    cpu.set_hl(0x8010);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0x8010], 0x00);
+}
+
+#[test]
+fn test_vram_accessible_during_oam_scan() {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_OAM_SCAN);
+    cpu.mem[0] = opcodes::LD_A_HL;
+    cpu.mem[0x8010] = 0x42;
+    cpu.set_hl(0x8010);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.a, 0x42);
+}
+
+#[test]
+fn test_oam_locked() {
+    for &mode in &[LCD_MODE_OAM_SCAN, LCD_MODE_DRAWING] {
+        let mut cpu = cpu_in_lcd_mode(mode);
+        cpu.mem[0] = opcodes::LD_A_HL;
+        cpu.mem[0xfe00] = 0x42;
+        cpu.set_hl(0xfe00);
+
+        cpu.tick().unwrap();
+        assert_eq!(cpu.a, 0xff);
+    }
+
+    // HBlank
+    let mut cpu = cpu_in_lcd_mode(0);
+    cpu.mem[0] = opcodes::LD_A_HL;
+    cpu.mem[0xfe00] = 0x42;
+    cpu.set_hl(0xfe00);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.a, 0x42);
+}
+
+#[test]
+fn test_memory_accessible_with_lcd_off() {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_DRAWING);
+    cpu.mem[LCDC] = 0;
+    cpu.mem[0] = opcodes::LD_HL_A;
+    cpu.a = 0x42;
+    cpu.set_hl(0x8010);
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0x8010], 0x42);
+}
+
+#[test]
+fn test_stat_mode_read_only() {
+    let mut cpu = cpu_in_lcd_mode(0b0000_0110);
+    cpu.mem[0] = opcodes::LDH_A8_A;
+    cpu.mem[1] = 0x41;
+    cpu.a = 0b0100_0001;
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[STAT], 0b0100_0110);
+}
+
+#[test]
+fn test_ly_read_only() {
+    let mut cpu = Cpu::new();
+    cpu.mem[0] = opcodes::LDH_A8_A;
+    cpu.mem[1] = 0x44;
+    cpu.mem[LY] = 0x90;
+    cpu.a = 0x12;
+
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[LY], 0x90);
+}
+
+fn cpu_with_oam_rows() -> Cpu {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_OAM_SCAN);
+    for i in 0..0xa0 {
+        cpu.mem[MEM_OAM_BEGIN + i] = i as u8;
+    }
+    cpu.oam_row = 2;
+    cpu.mem[0] = opcodes::INC_HL;
+    cpu.set_hl(0xfe40);
+    cpu
+}
+
+#[test]
+fn test_oam_bug() {
+    let mut cpu = cpu_with_oam_rows();
+    cpu.tick().unwrap();
+
+    // a = 0x1110, b = 0x0908, c = 0x0d0c
+    // ((a ^ c) & (b ^ c)) ^ c = (0x1c1c & 0x0404) ^ 0x0d0c = 0x0908
+    assert_eq!(cpu.get_hl(), 0xfe41);
+    assert_eq!(
+        cpu.get_mem_range(0xfe10, 0xfe17),
+        &[0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f]
+    );
+    // Other rows are left alone
+    assert_eq!(cpu.mem[0xfe08], 0x08);
+    assert_eq!(cpu.mem[0xfe18], 0x18);
+}
+
+#[test]
+fn test_oam_bug_not_triggered() {
+    // Outside of the OAM scan
+    let mut cpu = cpu_with_oam_rows();
+    cpu.mem[STAT] = 0;
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0xfe10], 0x10);
+
+    // Register not pointing to the OAM
+    let mut cpu = cpu_with_oam_rows();
+    cpu.set_hl(0xc000);
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0xfe10], 0x10);
+
+    // The CGB doesn't have the bug
+    let mut cpu = cpu_with_oam_rows();
+    cpu.set_model(Model::Cgb);
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0xfe10], 0x10);
+
+    // The first row is never corrupted
+    let mut cpu = cpu_with_oam_rows();
+    cpu.oam_row = 0;
+    cpu.tick().unwrap();
+    assert_eq!(cpu.mem[0xfe00], 0x00);
+}
+
+#[test]
+fn test_oam_bug_not_triggered_above_oam() {
+    // The IO registers, HRAM and the stack are past the OAM
+    for &(opcode, addr) in &[
+        (opcodes::INC_HL, 0xff80),
+        (opcodes::DEC_HL, 0xff80),
+        (opcodes::INC_SP, 0xfffe),
+        (opcodes::DEC_SP, 0xfffe),
+    ] {
+        let mut cpu = cpu_with_oam_rows();
+        cpu.mem[0] = opcode;
+        cpu.set_hl(addr);
+        cpu.sp = addr;
+        cpu.tick().unwrap();
+        for i in 0..0xa0 {
+            assert_eq!(cpu.mem[MEM_OAM_BEGIN + i], i as u8);
+        }
+    }
+}
+
+#[test]
+fn test_stack_locked() {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_DRAWING);
+    cpu.mem[0xfe10] = 0x10;
+    cpu.mem[0xfe11] = 0x11;
+    cpu.sp = 0xfe11;
+
+    cpu.push_stack_u16(0x1234);
+    assert_eq!(cpu.sp, 0xfe0f);
+    assert_eq!(cpu.get_mem_range(0xfe10, 0xfe11), &[0x10, 0x11]);
+    assert_eq!(cpu.pop_stack_u16(), 0xffff);
+}
+
+#[test]
+fn test_oam_bug_stack() {
+    let mut cpu = cpu_with_oam_rows();
+    cpu.sp = 0xfe42;
+    cpu.pop_stack_u16();
+    assert_eq!(cpu.sp, 0xfe44);
+    assert_eq!(cpu.mem[0xfe12], 0x0a);
+}
+
+#[test]
+fn test_vram_banks() {
+    let mut cpu = Cpu::new();
+    cpu.write_byte(VBK, 0x01);
+    cpu.write_byte(0x8010, 0x42);
+    // Without CGB mode, there is a single bank
+    assert_eq!(cpu.mem[0x8010], 0x42);
+
+    let mut cpu = Cpu::new();
+    cpu.set_cgb_mode(true);
+    assert_eq!(cpu.read_byte(VBK), 0xfe);
+
+    cpu.write_byte(VBK, 0xff);
+    assert_eq!(cpu.read_byte(VBK), 0xff);
+    cpu.write_byte(0x8010, 0x42);
+    assert_eq!(cpu.read_byte(0x8010), 0x42);
+    assert_eq!(cpu.mem[0x8010], 0x00);
+    assert_eq!(cpu.get_vram(1, 0x8010), 0x42);
+
+    cpu.write_byte(VBK, 0x00);
+    assert_eq!(cpu.read_byte(0x8010), 0x00);
+}
+
+#[test]
+fn test_palette_ram() {
+    let mut cpu = Cpu::new();
+    cpu.set_cgb_mode(true);
+
+    // Auto-increment wraps around after the last byte
+    cpu.write_byte(BCPS, 0xbe);
+    cpu.write_byte(BCPD, 0x12);
+    cpu.write_byte(BCPD, 0x34);
+    cpu.write_byte(BCPD, 0x56);
+    assert_eq!(cpu.read_byte(BCPS), 0x81);
+    assert_eq!(cpu.bg_palette_ram()[0x3e], 0x12);
+    assert_eq!(cpu.bg_palette_ram()[0x3f], 0x34);
+    assert_eq!(cpu.bg_palette_ram()[0x00], 0x56);
+
+    // Without it, the index stays put
+    cpu.write_byte(OCPS, 0x05);
+    cpu.write_byte(OCPD, 0x12);
+    cpu.write_byte(OCPD, 0x34);
+    assert_eq!(cpu.read_byte(OCPS), 0x05);
+    assert_eq!(cpu.read_byte(OCPD), 0x34);
+    assert_eq!(cpu.obj_palette_ram()[0x05], 0x34);
+    assert_eq!(cpu.bg_palette_ram()[0x05], 0x00);
+}
+
+#[test]
+fn test_palette_ram_locked_while_drawing() {
+    let mut cpu = cpu_in_lcd_mode(LCD_MODE_DRAWING);
+    cpu.set_cgb_mode(true);
+    cpu.write_byte(BCPS, 0x80);
+    cpu.write_byte(BCPD, 0x12);
+
+    assert_eq!(cpu.bg_palette_ram()[0], 0x00);
+    assert_eq!(cpu.read_byte(BCPS), 0x80);
+    assert_eq!(cpu.read_byte(BCPD), 0xff);
+}
+
+#[test]
+fn test_oam_dma() {
+    let mut cpu = Cpu::new();
+    for i in 0..0xa0 {
+        cpu.mem[0xc000 + i] = i as u8;
+    }
+
+    cpu.write_io(DMA, 0xc0);
+    assert_eq!(cpu.mem[DMA], 0xc0);
+    assert_eq!(cpu.mem[0xfe00], 0x00);
+    assert_eq!(cpu.mem[0xfe9f], 0x9f);
+
+    // The echo RAM reads the work RAM
+    cpu.mem[0xc000] = 0x42;
+    cpu.write_io(DMA, 0xe0);
+    assert_eq!(cpu.mem[0xfe00], 0x42);
+}
+
+#[test]
+fn test_joypad() {
+    let mut cpu = Cpu::new();
+    cpu.write_io(P1, 0xff);
+    assert_eq!(cpu.read_byte(P1), 0xff);
+
+    // Pressing a button of a group that isn't selected leaves the input
+    // lines high
+    cpu.set_button(Button::Down, true);
+    assert_eq!(cpu.mem[IF], 0);
+    assert_eq!(cpu.read_byte(P1), 0xff);
+
+    // Selecting the group pulls the line of the held button low
+    cpu.write_io(P1, 0xe0);
+    assert_eq!(cpu.read_byte(P1), 0xe7);
+    assert_eq!(cpu.mem[IF], INTERRUPT_JOYPAD);
+
+    cpu.mem[IF] = 0;
+    cpu.set_button(Button::Down, false);
+    assert_eq!(cpu.read_byte(P1), 0xef);
+    assert_eq!(cpu.mem[IF], 0);
+
+    // As does pressing a button of the selected group
+    cpu.set_button(Button::Right, true);
+    assert_eq!(cpu.read_byte(P1), 0xee);
+    assert_eq!(cpu.mem[IF], INTERRUPT_JOYPAD);
+
+    // A line that is already low doesn't request it again
+    cpu.mem[IF] = 0;
+    cpu.write_io(P1, 0xc0);
+    cpu.set_button(Button::A, true);
+    assert_eq!(cpu.read_byte(P1), 0xce);
+    assert_eq!(cpu.mem[IF], 0);
+}
+
+#[test]
+fn test_p1_write() {
+    let mut cpu = Cpu::new();
+    assert_eq!(cpu.take_p1_write(), None);
+
+    cpu.write_io(P1, 0x20);
+    cpu.write_io(P1, 0x30);
+    assert_eq!(cpu.take_p1_write(), Some(0x30));
+    assert_eq!(cpu.take_p1_write(), None);
+}
@@ -5,12 +5,13 @@ mod alu;
 mod jump;
 mod load;
 mod logic;
+mod memory;
 mod stack;
 
 #[test]
 fn test_get_flag() {
     let mut cpu = Cpu::new();
-    cpu.init();
+    cpu.init(Model::Dmg, false, 0x00);
 
     cpu.status = 0xff;
     assert!(cpu.flag(&Flag::Zero));
@@ -43,7 +44,7 @@ fn test_get_flag() {
 #[test]
 fn test_set_flag() {
     let mut cpu = Cpu::new();
-    cpu.init();
+    cpu.init(Model::Dmg, false, 0x00);
 
     // This assumes that `Flag::mask()` is implemented correctly
     cpu.status = 0x00;
@@ -78,7 +79,7 @@ fn test_set_flag() {
 #[test]
 fn test_reset_flag() {
     let mut cpu = Cpu::new();
-    cpu.init();
+    cpu.init(Model::Dmg, false, 0x00);
 
     // This assumes that `Flag::mask()` is implemented correctly
     cpu.status = 0xff;
@@ -218,7 +219,7 @@ fn test_pop_stack() {
 
     {
         let result = cpu.pop_stack(3);
-        assert_eq!([0xff, 0xee, 0xcc], result);
+        assert_eq!([0xff, 0xee, 0xcc], result[..]);
     }
 
     assert_eq!(0xfffe, cpu.sp);
@@ -288,3 +289,48 @@ fn test_load_from() {
     assert_eq!(cpu2.sp, 0xff12);
     assert_eq!(cpu2.mem[0], 0xff);
 }
+
+#[test]
+fn test_init() {
+    let mut cpu = Cpu::new();
+    cpu.init(Model::Dmg, false, 0x3a);
+
+    assert_eq!(cpu.get_af(), 0x01b0);
+    assert_eq!(cpu.get_bc(), 0x0013);
+    assert_eq!(cpu.get_de(), 0x00d8);
+    assert_eq!(cpu.get_hl(), 0x014d);
+    assert_eq!(cpu.sp, 0xfffe);
+    assert_eq!(cpu.pc, 0x0100);
+
+    cpu.init(Model::Dmg, false, 0x00);
+    assert_eq!(cpu.get_af(), 0x0180);
+
+    cpu.init(Model::Mgb, false, 0x3a);
+    assert_eq!(cpu.a, 0xff);
+
+    cpu.init(Model::Sgb, false, 0x3a);
+    assert_eq!(cpu.get_af(), 0x0100);
+    assert_eq!(cpu.get_hl(), 0xc060);
+
+    cpu.init(Model::Cgb, true, 0x3a);
+    assert_eq!(cpu.get_af(), 0x1180);
+    assert_eq!(cpu.get_de(), 0xff56);
+
+    cpu.init(Model::Cgb, false, 0x3a);
+    assert_eq!(cpu.get_de(), 0x0008);
+
+    cpu.init(Model::Agb, true, 0x3a);
+    assert_eq!(cpu.get_af(), 0x1100);
+    assert_eq!(cpu.get_bc(), 0x0100);
+}
+
+#[test]
+fn test_cycles() {
+    let mut cpu = Cpu::new();
+    cpu.set_mem_range(0, 5, &[0x00, 0x21, 0x00, 0xc0, 0xcb, 0x46]);
+
+    // nop; ld hl, d16; bit 0, (hl)
+    assert_eq!(cpu.tick().unwrap(), 4);
+    assert_eq!(cpu.tick().unwrap(), 12);
+    assert_eq!(cpu.tick().unwrap(), 12);
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+
+use errors::{Error, ErrorKind, Result};
+use hash::crc32;
+use read_file;
+
+/// State of a dump, as recorded by the DAT or found while matching it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DumpStatus {
+    Good,
+    /// Confirmed by more than one dump of the same cartridge
+    Verified,
+    /// Known to be incorrect, but the best dump there is
+    BadDump,
+    /// Matches a known rom followed by extra data
+    Overdump,
+}
+
+impl fmt::Display for DumpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            DumpStatus::Good => "Good",
+            DumpStatus::Verified => "Verified",
+            DumpStatus::BadDump => "Bad dump",
+            DumpStatus::Overdump => "Overdump",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Rom entry of a DAT file
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatEntry {
+    /// Canonical name of the game, e.g. "Tetris (World) (Rev 1)"
+    pub game: String,
+    pub size: usize,
+    pub crc32: u32,
+    pub sha1: Option<[u8; 20]>,
+    pub status: DumpStatus,
+}
+
+impl DatEntry {
+    /// Revision taken from the "(Rev X)" tag of the game name
+    pub fn revision(&self) -> Option<&str> {
+        let begin = self.game.find("(Rev ")? + "(Rev ".len();
+        let end = self.game[begin..].find(')')? + begin;
+        Some(&self.game[begin..end])
+    }
+}
+
+/// Known good dumps, read from a No-Intro or Redump style XML DAT file
+pub struct Dat {
+    entries: Vec<DatEntry>,
+}
+
+impl Dat {
+    pub fn load(file_name: &str) -> Result<Dat> {
+        let data = read_file(file_name)?;
+        Dat::parse(&String::from_utf8_lossy(&data))
+    }
+
+    /// Read the `rom` elements of every `game` (or `machine`) element. The
+    /// rest of the file is ignored.
+    pub fn parse(xml: &str) -> Result<Dat> {
+        let mut entries = Vec::new();
+        let mut game = None;
+
+        let mut rest = xml;
+        while let Some(begin) = rest.find('<') {
+            rest = &rest[begin..];
+
+            // Comments may contain anything, including tags
+            if rest.starts_with("<!--") {
+                let end = rest
+                    .find("-->")
+                    .ok_or_else(|| malformed("Unterminated comment"))?;
+                rest = &rest[end + 3..];
+                continue;
+            }
+
+            let end = rest
+                .find('>')
+                .ok_or_else(|| malformed("Unterminated tag"))?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let (name, attributes) = match tag.find(char::is_whitespace) {
+                Some(i) => (&tag[..i], &tag[i..]),
+                None => (tag.trim_end_matches('/'), ""),
+            };
+
+            match name {
+                "game" | "machine" => game = attribute(attributes, "name"),
+                "/game" | "/machine" => game = None,
+                "rom" => {
+                    let game = game
+                        .clone()
+                        .ok_or_else(|| malformed("rom element outside of a game"))?;
+                    entries.push(rom_entry(game, attributes)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Dat { entries })
+    }
+
+    pub fn entries(&self) -> &[DatEntry] {
+        &self.entries
+    }
+
+    /// Find the entry of a dump, given its CRC-32 and SHA-1, comparing the
+    /// SHA-1 too when the DAT has one. A dump that isn't known may still be
+    /// an overdump of one that is, so the beginning of the dump is also
+    /// checked against every smaller entry.
+    pub fn lookup(&self, data: &[u8], crc: u32, digest: &[u8; 20]) -> Option<DatEntry> {
+        if let Some(entry) = self.entries.iter().find(|entry| {
+            entry.size == data.len()
+                && entry.crc32 == crc
+                && entry.sha1.is_none_or(|sha1| sha1 == *digest)
+        }) {
+            return Some(entry.clone());
+        }
+
+        let mut sizes: Vec<usize> = self
+            .entries
+            .iter()
+            .map(|entry| entry.size)
+            .filter(|&size| size < data.len())
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        for size in sizes.into_iter().rev() {
+            let crc = crc32(&data[..size]);
+            if let Some(entry) = self
+                .entries
+                .iter()
+                .find(|entry| entry.size == size && entry.crc32 == crc)
+            {
+                return Some(DatEntry {
+                    status: DumpStatus::Overdump,
+                    ..entry.clone()
+                });
+            }
+        }
+
+        None
+    }
+}
+
+fn rom_entry(game: String, attributes: &str) -> Result<DatEntry> {
+    let size = attribute(attributes, "size")
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| malformed("rom element without a valid size"))?;
+    let crc32 = attribute(attributes, "crc")
+        .and_then(|crc| u32::from_str_radix(&crc, 16).ok())
+        .ok_or_else(|| malformed("rom element without a valid crc"))?;
+    let sha1 = attribute(attributes, "sha1").and_then(|sha1| parse_sha1(&sha1));
+    let status = match attribute(attributes, "status").as_deref() {
+        Some("verified") => DumpStatus::Verified,
+        Some("baddump") => DumpStatus::BadDump,
+        _ => DumpStatus::Good,
+    };
+
+    Ok(DatEntry {
+        game,
+        size,
+        crc32,
+        sha1,
+        status,
+    })
+}
+
+/// Value of the attribute called `name`, with XML entities decoded
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let quote = rest[eq + 1..].trim_start().chars().next()?;
+        let value_begin = rest.find(quote)? + 1;
+        let value_end = rest[value_begin..].find(quote)? + value_begin;
+        if key == name {
+            return Some(unescape(&rest[value_begin..value_end]));
+        }
+        rest = &rest[value_end + 1..];
+    }
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_sha1(hex: &str) -> Option<[u8; 20]> {
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut digest = [0; 20];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+fn malformed(message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("Malformed DAT file: {}", message),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hash::sha1;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "">
+<datafile>
+    <header>
+        <name>Nintendo - Game Boy</name>
+    </header>
+    <!-- <game name="Commented out"><rom name="x" size="1" crc="00000000"/></game> -->
+    <game name="Tetris (World) (Rev 1)">
+        <description>Tetris (World) (Rev 1)</description>
+        <rom name="Tetris (World) (Rev 1).gb" size="4" crc="ed82fd02" sha1="a8b1f2b5ea4b1bb2b0a1c3a9e4e6a9e70b6f0a4c" status="verified"/>
+    </game>
+    <game name="Dr. Mario &amp; Friends (Japan)">
+        <rom name="Dr. Mario.gb" size="8" crc="9ae0dfb5" status="baddump"/>
+    </game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_parse() {
+        let dat = Dat::parse(DAT).unwrap();
+        let entries = dat.entries();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].game, "Tetris (World) (Rev 1)");
+        assert_eq!(entries[0].size, 4);
+        assert_eq!(entries[0].crc32, 0xed82_fd02);
+        assert_eq!(entries[0].sha1.unwrap()[0], 0xa8);
+        assert_eq!(entries[0].status, DumpStatus::Verified);
+        assert_eq!(entries[0].revision(), Some("1"));
+
+        assert_eq!(entries[1].game, "Dr. Mario & Friends (Japan)");
+        assert_eq!(entries[1].sha1, None);
+        assert_eq!(entries[1].status, DumpStatus::BadDump);
+        assert_eq!(entries[1].revision(), None);
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        assert!(Dat::parse("<game name=\"x\"><rom size=\"1\"/></game>").is_err());
+        assert!(Dat::parse("<rom size=\"1\" crc=\"00000000\"/>").is_err());
+        assert!(Dat::parse("<game name=\"x\"").is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let game = |size, data: &[u8]| DatEntry {
+            game: "Game".to_string(),
+            size,
+            crc32: crc32(data),
+            sha1: None,
+            status: DumpStatus::Good,
+        };
+        let mut dat = Dat {
+            entries: vec![game(4, b"rust"), game(6, b"rustyb")],
+        };
+        let lookup = |dat: &Dat, data: &[u8]| dat.lookup(data, crc32(data), &sha1(data));
+
+        let entry = lookup(&dat, b"rustyb").unwrap();
+        assert_eq!(entry.size, 6);
+        assert_eq!(entry.status, DumpStatus::Good);
+
+        // The largest known rom at the beginning of the dump
+        let entry = lookup(&dat, b"rustyboy").unwrap();
+        assert_eq!(entry.size, 6);
+        assert_eq!(entry.status, DumpStatus::Overdump);
+
+        assert_eq!(lookup(&dat, b"rusty").unwrap().status, DumpStatus::Overdump);
+        assert_eq!(lookup(&dat, b"other"), None);
+
+        // A matching CRC-32 isn't enough when the SHA-1 differs
+        dat.entries[1].sha1 = Some([0; 20]);
+        assert_eq!(lookup(&dat, b"rustyb").unwrap().size, 4);
+        dat.entries[1].sha1 = Some(sha1(b"rustyb"));
+        assert_eq!(lookup(&dat, b"rustyb").unwrap().size, 6);
+    }
+}
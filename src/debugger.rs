@@ -1,5 +1,8 @@
 use cpu::Cpu;
 use errors::Result;
+use image::Image;
+use palette::{ColorCorrection, DmgPalette};
+use ppu::viewer::{Viewer, TILEMAPS};
 use std::io::stdout;
 
 use std::io;
@@ -16,17 +19,29 @@ const DEBUG_PRINT_CPU: &str = "cpu";
 const DEBUG_BACKTRACK: &str = "p";
 // Step n times
 const DEBUG_UNTIL_N: &str = "n";
+// Save an image of every tile in VRAM
+const DEBUG_TILES: &str = "tiles";
+// Save an image of a tilemap, 0 for 0x9800 or 1 for 0x9c00
+const DEBUG_TILEMAP: &str = "tilemap";
+// Print the objects in OAM
+const DEBUG_OAM: &str = "oam";
+// Save an image of the objects in OAM
+const DEBUG_SPRITES: &str = "sprites";
 
 pub struct Debugger {
     previous_state: Cpu,
     n_iteration: u64,
+    dmg_palette: DmgPalette,
+    color_correction: ColorCorrection,
 }
 
 impl Debugger {
-    pub fn new() -> Debugger {
+    pub fn new(dmg_palette: DmgPalette, color_correction: ColorCorrection) -> Debugger {
         Debugger {
             previous_state: Cpu::new(),
             n_iteration: 0,
+            dmg_palette,
+            color_correction,
         }
     }
 
@@ -48,14 +63,18 @@ impl Debugger {
         vec
     }
 
-    fn step(&mut self, cpu: &mut Cpu) -> Result<()> {
+    fn step(&mut self, cpu: &mut Cpu) -> Result<u32> {
         self.previous_state = cpu.clone();
-        cpu.tick()?;
+        let cycles = cpu.tick()?;
         self.n_iteration += 1;
-        Ok(())
+        Ok(cycles)
     }
 
-    pub fn tick(&mut self, cpu: &mut Cpu) -> Result<()> {
+    /// Run a debugger command, returning the number of dots taken by the
+    /// instructions it ran
+    pub fn tick(&mut self, cpu: &mut Cpu) -> Result<u32> {
+        let mut cycles = 0;
+
         print!("rustyboy({})> ", self.n_iteration);
         stdout().flush()?;
 
@@ -77,6 +96,37 @@ impl Debugger {
                     println!("{:02x}", n);
                 }
             }
+            DEBUG_TILES => match cmd.get(1) {
+                Some(path) => {
+                    let viewer = Viewer::new(cpu, self.dmg_palette, self.color_correction);
+                    save(&viewer.tiles(), path);
+                }
+                None => println!("Usage: {} <path>", DEBUG_TILES),
+            },
+            DEBUG_TILEMAP => {
+                let map = cmd
+                    .get(1)
+                    .and_then(|map| map.parse::<usize>().ok())
+                    .and_then(|map| TILEMAPS.get(map));
+                match (map, cmd.get(2)) {
+                    (Some(&map), Some(path)) => {
+                        let viewer = Viewer::new(cpu, self.dmg_palette, self.color_correction);
+                        save(&viewer.tilemap(map), path);
+                    }
+                    _ => println!("Usage: {} <0|1> <path>", DEBUG_TILEMAP),
+                }
+            }
+            DEBUG_OAM => {
+                let viewer = Viewer::new(cpu, self.dmg_palette, self.color_correction);
+                print!("{}", viewer.oam_table());
+            }
+            DEBUG_SPRITES => match cmd.get(1) {
+                Some(path) => {
+                    let viewer = Viewer::new(cpu, self.dmg_palette, self.color_correction);
+                    save(&viewer.sprites(), path);
+                }
+                None => println!("Usage: {} <path>", DEBUG_SPRITES),
+            },
             DEBUG_UNTIL_N => {
                 let pc_val = &cmd[1..2]
                     .iter()
@@ -84,13 +134,21 @@ impl Debugger {
                     .collect::<Vec<u16>>()[0];
 
                 for _ in 0..=*pc_val {
-                    self.step(cpu)?;
+                    cycles += self.step(cpu)?;
                 }
             }
             _ => {
-                self.step(cpu)?;
+                cycles += self.step(cpu)?;
             }
         }
-        Ok(())
+        Ok(cycles)
+    }
+}
+
+/// Save a dump, reporting failures without stopping the emulation
+fn save(image: &Image, path: &str) {
+    match image.save(path) {
+        Ok(()) => println!("Saved {}", path),
+        Err(error) => println!("Could not save {}: {}", path, error),
     }
 }
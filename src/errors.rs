@@ -19,7 +19,7 @@ enum Repr {
 #[derive(Debug)]
 struct Custom {
     kind: ErrorKind,
-    error: Box<error::Error + Send + Sync>,
+    error: Box<dyn error::Error + Send + Sync>,
 }
 
 #[derive(Debug)]
@@ -27,6 +27,8 @@ pub enum ErrorKind {
     IO,
     InvalidInput,
     Validation,
+    /// The file is too small to hold a cartridge header
+    RomTooSmall,
     UnknownInstruction,
 }
 
@@ -36,6 +38,7 @@ impl ErrorKind {
             ErrorKind::IO => "IO error",
             ErrorKind::InvalidInput => "Invalid input",
             ErrorKind::Validation => "Validation error",
+            ErrorKind::RomTooSmall => "ROM too small",
             ErrorKind::UnknownInstruction => "Unknown instruction",
         }
     }
@@ -44,7 +47,7 @@ impl ErrorKind {
 impl Error {
     pub fn new<E>(kind: ErrorKind, error: E) -> Error
     where
-        E: Into<Box<error::Error + Send + Sync>>,
+        E: Into<Box<dyn error::Error + Send + Sync>>,
     {
         Error {
             repr: Repr::Custom(Custom {
@@ -59,6 +62,13 @@ impl Error {
             repr: Repr::Simple(kind),
         }
     }
+
+    pub fn kind(&self) -> &ErrorKind {
+        match self.repr {
+            Repr::Simple(ref kind) => kind,
+            Repr::Custom(ref c) => &c.kind,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -71,17 +81,10 @@ impl fmt::Display for Error {
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match self.repr {
-            Repr::Simple(ref kind) => kind.as_str(),
-            Repr::Custom(ref c) => c.error.description(),
-        }
-    }
-
-    fn cause(&self) -> Option<&error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self.repr {
             Repr::Simple(..) => None,
-            Repr::Custom(ref c) => c.error.cause(),
+            Repr::Custom(ref c) => c.error.source(),
         }
     }
 }
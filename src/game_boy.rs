@@ -1,9 +1,24 @@
-use cartridge::Cartridge;
+use blend::{Blender, FrameBlending};
+use boot_rom::{self, BootRom};
+use cartridge::{Cartridge, CgbSupport, SgbSupport, NINTENDO_LOGO};
 use cpu::Cpu;
+use dat::Dat;
 use debugger::Debugger;
 use errors::{Error, ErrorKind, Result};
-use read_file;
-use Config;
+use image::Image;
+use joypad::Button;
+use model::Model;
+use palette::{ColorCorrection, DmgPalette};
+use patch;
+use ppu::viewer::{Viewer, TILEMAPS};
+use ppu::Ppu;
+use registers::*;
+use sgb::Sgb;
+use {read_file, read_rom};
+use {u16_to_u8, BootMode, Config, ValidationPolicy};
+
+pub use ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use sgb::{SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH};
 
 const MEM_CARTRIDGE_INTERRUPTS_BEGIN: usize = 0x0000;
 const MEM_CARTRIDGE_INTERRUPTS_END: usize = 0x00ff;
@@ -14,109 +29,533 @@ const MEM_CARTRIDGE_BANK_0_END: usize = 0x3fff;
 const MEM_CARTRIDGE_BANK_1_BEGIN: usize = 0x4000;
 const MEM_CARTRIDGE_BANK_1_END: usize = 0x7fff;
 
-const MEM_CHECKSUM_BEGIN: usize = 0x104;
-const MEM_CHECKSUM_END: usize = 0x133;
-
 //
-// Memory map
+// Built-in boot sequence
 //
 
-// IO Registers
-const TIMA: usize = 0xff05;
-const TMA: usize = 0xff06;
-const TAC: usize = 0xff07;
-const NR10: usize = 0xff10;
-const NR11: usize = 0xff11;
-const NR12: usize = 0xff12;
-const NR14: usize = 0xff14;
-const NR21: usize = 0xff16;
-const NR22: usize = 0xff17;
-const NR24: usize = 0xff19;
-const NR30: usize = 0xff1a;
-const NR31: usize = 0xff1b;
-const NR32: usize = 0xff1c;
-const NR33: usize = 0xff1e;
-const NR41: usize = 0xff20;
-const NR42: usize = 0xff21;
-const NR43: usize = 0xff22;
-const NR44: usize = 0xff23;
-const NR50: usize = 0xff24;
-const NR51: usize = 0xff25;
-const NR52: usize = 0xff26;
-const LCDC: usize = 0xff40;
-const SCY: usize = 0xff42;
-const SCX: usize = 0xff43;
-const LYC: usize = 0xff45;
-const BGP: usize = 0xff47;
-const OBP0: usize = 0xff48;
-const OBP1: usize = 0xff49;
-const WY: usize = 0xff4a;
-const WX: usize = 0xff4b;
-
-// Interrupt enable flag
-const IE: usize = 0xffff;
+const VRAM_BEGIN: usize = 0x8000;
+const VRAM_END: usize = 0x9fff;
+
+// The logo is drawn with tiles 0x01-0x18 and the (R) symbol with tile 0x19
+const BOOT_LOGO_TILES: usize = 0x8010;
+const BOOT_REGISTERED_TILE: usize = 0x8190;
+const BOOT_REGISTERED_TILE_INDEX: u8 = 0x19;
+const BOOT_TILEMAP_LOGO_TOP: usize = 0x9904;
+const BOOT_TILEMAP_LOGO_BOTTOM: usize = 0x9924;
+const BOOT_TILEMAP_REGISTERED: usize = 0x9910;
+const BOOT_REGISTERED_SYMBOL: [u8; 8] = [0x3c, 0x42, 0xb9, 0xa5, 0xb9, 0xa5, 0x42, 0x3c];
+
+// The logo starts 0x64 lines above its final position and moves down one line
+// per frame. The chime plays near the end of the scroll, and the logo then
+// stays in place for another 0x20 frames before the cartridge starts.
+const BOOT_SCROLL_STEPS: u8 = 0x64;
+const BOOT_WAIT_STEPS: u8 = 0x20;
+const BOOT_FIRST_NOTE_STEP: u8 = 0x62;
+const BOOT_SECOND_NOTE_STEP: u8 = 0x64;
+const BOOT_FIRST_NOTE: u8 = 0x83;
+const BOOT_SECOND_NOTE: u8 = 0xc1;
+// The PPU runs this many cycles at a time while the boot sequence plays
+const BOOT_SEQUENCE_CYCLES: u32 = 4;
 
 pub struct GameBoy {
+    model: Model,
     cpu: Cpu,
+    ppu: Ppu,
+    dmg_palette: DmgPalette,
+    color_correction: ColorCorrection,
+    blender: Blender,
+    /// The last frame blended with the ones before it, when blending is on
+    blended: Option<Vec<u8>>,
+    /// The SGB colouring the screen and drawing the border, on the SGB
+    /// models
+    sgb: Option<Sgb>,
     cartridge: Cartridge,
+    boot_rom: Option<BootRom>,
+    hle_boot: bool,
+    validation: ValidationPolicy,
+    /// Frames of the built-in boot sequence shown so far, while it runs
+    boot_sequence: Option<u8>,
     debugger: Debugger,
 }
 
 impl GameBoy {
     pub fn new(config: &Config) -> Result<GameBoy> {
         // Initialize cartridge
-        let cartridge_data = read_file(&config.rom_name)?;
-        let cartridge = Cartridge::new(cartridge_data);
+        let mut cartridge = Cartridge::new(GameBoy::load_rom(config)?)?;
+        if let Some(ref dat) = config.dat {
+            cartridge.identify(&Dat::load(dat)?);
+        }
+        GameBoy::check_rom(&cartridge, config.validation)?;
+
+        // Initialize boot rom
+        let boot_rom = match config.boot_mode {
+            BootMode::Rom(ref boot_rom_name) => Some(BootRom::new(read_file(boot_rom_name)?)?),
+            _ => None,
+        };
+
+        let model = GameBoy::select_model(config, &cartridge, &boot_rom)?;
+        let sgb = if model.is_sgb() {
+            Some(Sgb::new(cartridge.header().sgb() == SgbSupport::Supported))
+        } else {
+            None
+        };
 
-        Ok(GameBoy {
+        let mut game_boy = GameBoy {
+            model,
             cpu: Cpu::new(),
+            ppu: Ppu::new(config.renderer),
+            dmg_palette: config.dmg_palette,
+            color_correction: config.color_correction,
+            blender: Blender::new(config.frame_blending),
+            blended: None,
+            sgb,
             cartridge,
-            debugger: Debugger::new(),
-        })
+            boot_rom,
+            hle_boot: config.boot_mode == BootMode::Hle,
+            validation: config.validation,
+            boot_sequence: None,
+            debugger: Debugger::new(config.dmg_palette, config.color_correction),
+        };
+        game_boy.power_on()?;
+
+        Ok(game_boy)
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        self.init_memory();
-        self.cpu.init();
+    /// Read the rom, applying the configured patch or the one next to it
+    fn load_rom(config: &Config) -> Result<Vec<u8>> {
+        let rom = read_rom(&config.rom_name, config.archive_entry.as_deref())?;
 
-        self.check_rom()?;
+        let patch_name = match config.patch {
+            Some(ref patch_name) => patch_name.clone(),
+            None => match patch::find_sibling(&config.rom_name) {
+                Some(path) => path.to_string_lossy().into_owned(),
+                None => return Ok(rom),
+            },
+        };
 
-        // TODO Boot sequence (logo screen and musical notes)
+        println!("Applying patch: {}", patch_name);
+        patch::apply(&rom, &read_file(&patch_name)?)
+    }
 
-        // TODO game loop
-        println!("Running rom with title: {}", self.cartridge.title());
-        if let Some(rom_type) = self.cartridge.cartridge_type() {
+    /// Use the configured model if there is one, otherwise pick one that can
+    /// run both the cartridge and the boot rom.
+    fn select_model(
+        config: &Config,
+        cartridge: &Cartridge,
+        boot_rom: &Option<BootRom>,
+    ) -> Result<Model> {
+        let model = config.model.unwrap_or_else(|| Model::detect(cartridge));
+
+        match *boot_rom {
+            Some(ref boot_rom) if boot_rom.is_cgb() != model.is_cgb() => {
+                if config.model.is_some() {
+                    Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The boot rom can't be used with the {} model", model),
+                    ))
+                } else if boot_rom.is_cgb() {
+                    Ok(Model::Cgb)
+                } else {
+                    Ok(Model::Dmg)
+                }
+            }
+            _ => Ok(model),
+        }
+    }
+
+    /// Map the cartridge and either the boot rom or the state it leaves
+    /// behind
+    fn power_on(&mut self) -> Result<()> {
+        self.cpu.set_model(self.model);
+        self.cpu.set_cgb_mode(self.cgb_mode());
+        self.map_cartridge();
+
+        if self.boot_rom.is_some() {
+            // The boot rom starts at 0x0000 with every register and IO port
+            // in its power up state, and is responsible for leaving them
+            // in the state the cartridge expects.
+            self.map_boot_rom();
+        } else if self.hle_boot {
+            self.hle_boot()?;
+        } else {
+            self.start_cartridge();
+        }
+
+        Ok(())
+    }
+
+    /// Leave every register and IO port the way the boot rom does, and jump
+    /// to the cartridge entry point
+    fn start_cartridge(&mut self) {
+        self.init_memory();
+        self.cpu.init(
+            self.model,
+            self.cgb_mode(),
+            self.cartridge.header().header_checksum,
+        );
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        println!("Running on model: {}", self.model);
+        println!("Running rom with title: {}", self.cartridge.header().title);
+        if let Some(entry) = self.cartridge.dat_entry() {
+            println!("Identified as: {} ({})", entry.game, entry.status);
+        }
+        if let Some(rom_type) = self.cartridge.header().cartridge_type() {
             println!("Running rom with type: {:?}", rom_type);
         }
 
         loop {
-            if cfg!(feature = "debug") {
-                self.debugger.tick(&mut self.cpu)?;
+            self.run_frame()?;
+        }
+    }
+
+    /// Run until the PPU finishes drawing the current frame, at the start of
+    /// VBlank
+    pub fn run_frame(&mut self) -> Result<()> {
+        let frames = self.ppu.frames();
+        while self.ppu.frames() == frames {
+            self.step()?;
+        }
+
+        if self.blender.mode() != FrameBlending::Off {
+            let frame = self.unblended_rgba();
+            self.blended = Some(self.blender.blend(&frame));
+        }
+        Ok(())
+    }
+
+    /// Press or release a button of the joypad
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.cpu.set_button(button, pressed);
+    }
+
+    /// Number of frames completed since power on
+    pub fn frame_count(&self) -> u64 {
+        self.ppu.frames()
+    }
+
+    /// Shade of every pixel of the last frame, row by row, from 0 (lightest)
+    /// to 3 (darkest). In CGB mode, the colour index of every pixel in its
+    /// palette instead.
+    pub fn frame(&self) -> &[u8] {
+        self.ppu.framebuffer()
+    }
+
+    /// 15 bit colour of every pixel of the last frame, row by row, when
+    /// running a CGB cartridge on a CGB or as coloured by the SGB. Red is in
+    /// the lowest 5 bits, followed by green and blue.
+    pub fn frame_colors(&self) -> Option<&[u16]> {
+        match self.sgb {
+            Some(ref sgb) => Some(sgb.screen()),
+            None => self.ppu.colors(),
+        }
+    }
+
+    /// The last frame as 8 bit RGBA, row by row, in the configured DMG
+    /// palette or with the configured CGB colour correction. The SGB
+    /// colours are shown as is, the way a TV does.
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        match self.blended {
+            Some(ref blended) => blended.clone(),
+            None => self.unblended_rgba(),
+        }
+    }
+
+    fn unblended_rgba(&self) -> Vec<u8> {
+        if let Some(ref sgb) = self.sgb {
+            return sgb
+                .screen()
+                .iter()
+                .flat_map(|&color| ColorCorrection::Raw.rgba(color).to_vec())
+                .collect();
+        }
+
+        match self.ppu.colors() {
+            Some(colors) => colors
+                .iter()
+                .flat_map(|&color| self.color_correction.rgba(color).to_vec())
+                .collect(),
+            None => self
+                .ppu
+                .framebuffer()
+                .iter()
+                .flat_map(|&shade| self.dmg_palette.rgba(shade).to_vec())
+                .collect(),
+        }
+    }
+
+    /// The last frame as 8 bit RGBA inside the 256×224 SGB border, row by
+    /// row, on the SGB models
+    pub fn sgb_frame_rgba(&self) -> Option<Vec<u8>> {
+        self.sgb.as_ref().map(|sgb| sgb.compose(&self.frame_rgba()))
+    }
+
+    /// The last frame as an image, inside the border on the SGB
+    pub fn screenshot(&self) -> Image {
+        match self.sgb_frame_rgba() {
+            Some(rgba) => Image::new(SGB_SCREEN_WIDTH, SGB_SCREEN_HEIGHT, rgba),
+            None => Image::new(SCREEN_WIDTH, SCREEN_HEIGHT, self.frame_rgba()),
+        }
+    }
+
+    /// Every tile in VRAM, in a grid 16 tiles wide. On the CGB, the tiles of
+    /// the second bank are to the right of the first.
+    pub fn dump_tiles(&self) -> Image {
+        self.viewer().tiles()
+    }
+
+    /// The tilemaps at 0x9800 and 0x9c00 as 256×256 images, with the
+    /// viewport outlined
+    pub fn dump_tilemaps(&self) -> Vec<Image> {
+        let viewer = self.viewer();
+        TILEMAPS.iter().map(|&map| viewer.tilemap(map)).collect()
+    }
+
+    /// The position, tile and attributes of every object in OAM, as a table
+    pub fn dump_oam(&self) -> String {
+        self.viewer().oam_table()
+    }
+
+    /// Every object in OAM, in a grid 8 objects wide
+    pub fn dump_sprites(&self) -> Image {
+        self.viewer().sprites()
+    }
+
+    fn viewer(&self) -> Viewer<'_> {
+        Viewer::new(&self.cpu, self.dmg_palette, self.color_correction)
+    }
+
+    /// Run until `frame` frames are complete, and save the last one to `path`
+    /// as a PNG or PPM, scaled up by `scale`
+    pub fn save_screenshot_at(&mut self, frame: u64, path: &str, scale: usize) -> Result<()> {
+        // Don't run all the frames for nothing
+        if scale == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The screenshot scale must be at least 1",
+            ));
+        }
+
+        while self.frame_count() < frame {
+            self.run_frame()?;
+        }
+        self.screenshot().scale(scale)?.save(path)
+    }
+
+    fn step(&mut self) -> Result<()> {
+        let cycles = if self.boot_sequence.is_some() {
+            // Nothing runs on the CPU while the built-in boot sequence plays
+            BOOT_SEQUENCE_CYCLES
+        } else if cfg!(feature = "debug") {
+            self.debugger.tick(&mut self.cpu)?
+        } else {
+            self.cpu.tick()?
+        };
+        if let Some(value) = self.cpu.take_p1_write() {
+            if let Some(ref mut sgb) = self.sgb {
+                sgb.write_p1(value);
+                self.cpu.set_joypad_player(sgb.player());
+            }
+        }
+
+        let frames = self.ppu.frames();
+        self.ppu.step(&mut self.cpu, cycles);
+        if self.ppu.frames() != frames {
+            if let Some(ref mut sgb) = self.sgb {
+                sgb.frame(self.ppu.framebuffer());
+            }
+            self.hle_boot_frame();
+        }
+
+        self.update_boot_rom_mapping();
+        Ok(())
+    }
+
+    /// Whether a CGB is running a CGB cartridge, instead of running a DMG
+    /// cartridge in compatibility mode
+    fn cgb_mode(&self) -> bool {
+        self.model.is_cgb() && self.cartridge.header().cgb() != CgbSupport::DmgOnly
+    }
+
+    /// Built-in replacement for the boot rom.
+    ///
+    /// Validates the cartridge the same way the boot rom of the selected
+    /// model does and draws its logo. Over the following frames the logo
+    /// scrolls down into place, the chime plays and the cartridge is
+    /// started, leaving the logo in VRAM. The SGB boot rom hands the logo
+    /// over to the SNES instead, so the cartridge starts right away there.
+    /// Instead of locking up, a cartridge that fails validation results in
+    /// an error unless the validation policy lets it run.
+    fn hle_boot(&mut self) -> Result<()> {
+        self.hle_boot_validate()?;
+        if self.model.is_sgb() {
+            self.start_cartridge();
+            return Ok(());
+        }
+
+        self.hle_boot_draw_logo();
+
+        self.cpu.set_mem(NR52, 0x80);
+        self.cpu.set_mem(NR11, 0x80);
+        self.cpu.set_mem(NR12, 0xf3);
+        self.cpu.set_mem(NR51, 0xf3);
+        self.cpu.set_mem(NR50, 0x77);
+        self.cpu.set_mem(BGP, 0xfc);
+
+        self.cpu.set_mem(SCY, BOOT_SCROLL_STEPS);
+        self.cpu.set_mem(LCDC, 0x91);
+
+        self.boot_sequence = Some(0);
+        Ok(())
+    }
+
+    /// Advance the built-in boot sequence at the start of VBlank, starting
+    /// the cartridge once it is over
+    fn hle_boot_frame(&mut self) {
+        if let Some(step) = self.boot_sequence {
+            let step = step + 1;
+            self.hle_boot_step(step);
+
+            if step == BOOT_SCROLL_STEPS + BOOT_WAIT_STEPS {
+                self.boot_sequence = None;
+                self.start_cartridge();
             } else {
-                self.cpu.tick()?;
+                self.boot_sequence = Some(step);
             }
         }
     }
 
-    fn check_rom(&self) -> Result<()> {
-        // Validate ROM checksum
-        let sum = self
-            .cpu
-            .get_mem_range(MEM_CHECKSUM_BEGIN, MEM_CHECKSUM_END)
-            .iter()
-            .fold(25u8, |sum, v| sum.wrapping_add(*v));
+    fn hle_boot_draw_logo(&mut self) {
+        for addr in VRAM_BEGIN..=VRAM_END {
+            self.cpu.set_mem(addr, 0);
+        }
+
+        // Each 4x4 logo tile is scaled up to 8x8. Every nibble of the logo is
+        // a row of 4 pixels, which becomes two rows of 8 pixels.
+        let mut addr = BOOT_LOGO_TILES;
+        let logo = self.cartridge.header().logo;
+        for &byte in logo.iter() {
+            for &nibble in &[byte >> 4, byte & 0x0f] {
+                let row = scale_nibble(nibble);
+                self.cpu.set_mem(addr, row);
+                self.cpu.set_mem(addr + 2, row);
+                addr += 4;
+            }
+        }
+
+        for (i, &row) in BOOT_REGISTERED_SYMBOL.iter().enumerate() {
+            self.cpu.set_mem(BOOT_REGISTERED_TILE + i * 2, row);
+        }
+
+        for i in 0..12 {
+            self.cpu.set_mem(BOOT_TILEMAP_LOGO_TOP + i, 0x01 + i as u8);
+            self.cpu
+                .set_mem(BOOT_TILEMAP_LOGO_BOTTOM + i, 0x0d + i as u8);
+        }
+        self.cpu
+            .set_mem(BOOT_TILEMAP_REGISTERED, BOOT_REGISTERED_TILE_INDEX);
+    }
+
+    fn hle_boot_step(&mut self, step: u8) {
+        let note = match step {
+            BOOT_FIRST_NOTE_STEP => Some(BOOT_FIRST_NOTE),
+            BOOT_SECOND_NOTE_STEP => Some(BOOT_SECOND_NOTE),
+            _ => None,
+        };
+        if let Some(note) = note {
+            self.cpu.set_mem(NR13, note);
+            self.cpu.set_mem(NR14, 0x87);
+        }
 
-        match sum {
-            0 => Err(Error::new(
+        if step <= BOOT_SCROLL_STEPS {
+            let scy = self.cpu.get_mem(SCY);
+            self.cpu.set_mem(SCY, scy - 1);
+        }
+    }
+
+    /// Run the checks of the boot rom, refusing a cartridge that fails them
+    /// if the validation policy is strict. Under the other policies,
+    /// `check_rom` has already warned about these failures or ignored them.
+    fn hle_boot_validate(&self) -> Result<()> {
+        if self.validation != ValidationPolicy::Strict {
+            return Ok(());
+        }
+
+        // The SGB boot rom doesn't check the logo, and the CGB one only
+        // checks its top half.
+        let checked_logo = match self.model {
+            Model::Sgb | Model::Sgb2 => 0,
+            Model::Cgb | Model::Agb => NINTENDO_LOGO.len() / 2,
+            _ => NINTENDO_LOGO.len(),
+        };
+
+        let mut failures = Vec::new();
+        if self.cartridge.header().logo[..checked_logo] != NINTENDO_LOGO[..checked_logo] {
+            failures.push("Nintendo logo fails the boot rom check");
+        }
+        if self.cartridge.compute_header_checksum() != self.cartridge.header().header_checksum {
+            failures.push("Header checksum fails the boot rom check");
+        }
+
+        GameBoy::report_failures(&failures, self.validation, "ROM failed the boot checks")
+    }
+
+    fn map_boot_rom(&mut self) {
+        if let Some(ref boot_rom) = self.boot_rom {
+            self.cpu
+                .set_mem_range(boot_rom::LOW_BEGIN, boot_rom::LOW_END, boot_rom.low());
+            if let Some(high) = boot_rom.high() {
+                self.cpu
+                    .set_mem_range(boot_rom::HIGH_BEGIN, boot_rom::HIGH_END, high);
+            }
+        }
+    }
+
+    /// Any write to the BOOT register unmaps the boot rom, restoring the
+    /// cartridge contents it was overlaid on. This can't be undone until the
+    /// next power cycle.
+    fn update_boot_rom_mapping(&mut self) {
+        if self.boot_rom.is_some() && self.cpu.get_mem(BOOT) != 0 {
+            self.boot_rom = None;
+            self.map_cartridge();
+        }
+    }
+
+    /// Check the logo and both checksums of the cartridge, refusing or
+    /// reporting the ones that fail according to `policy`.
+    fn check_rom(cartridge: &Cartridge, policy: ValidationPolicy) -> Result<()> {
+        if policy == ValidationPolicy::Ignore {
+            return Ok(());
+        }
+
+        let failures = cartridge.validate().failures();
+        GameBoy::report_failures(&failures, policy, "ROM failed validation")
+    }
+
+    /// Refuse a cartridge that failed some checks with `message`, warn about
+    /// them or ignore them, according to `policy`
+    fn report_failures(failures: &[&str], policy: ValidationPolicy, message: &str) -> Result<()> {
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        match policy {
+            ValidationPolicy::Strict => Err(Error::new(
                 ErrorKind::Validation,
-                "ROM failed checksum validation",
+                format!("{}: {}", message, failures.join(", ")),
             )),
-            _ => Ok(()),
+            ValidationPolicy::Ignore => Ok(()),
+            ValidationPolicy::Warn => {
+                for failure in failures {
+                    eprintln!("Warning: {}", failure);
+                }
+                Ok(())
+            }
         }
     }
 
-    fn init_memory(&mut self) {
-        // Copy cartridge
+    fn map_cartridge(&mut self) {
         self.cpu.set_mem_range(
             MEM_CARTRIDGE_INTERRUPTS_BEGIN,
             MEM_CARTRIDGE_INTERRUPTS_END,
@@ -125,7 +564,7 @@ impl GameBoy {
         self.cpu.set_mem_range(
             MEM_CARTRIDGE_HEADER_BEGIN,
             MEM_CARTRIDGE_HEADER_END,
-            self.cartridge.header(),
+            self.cartridge.raw_header(),
         );
         self.cpu.set_mem_range(
             MEM_CARTRIDGE_BANK_0_BEGIN,
@@ -138,8 +577,11 @@ impl GameBoy {
             MEM_CARTRIDGE_BANK_1_END,
             self.cartridge.bank1(),
         );
+    }
 
+    fn init_memory(&mut self) {
         // Initialize IO registers
+        self.cpu.set_mem(P1, 0xcf);
         self.cpu.set_mem(TIMA, 0x00);
         self.cpu.set_mem(TMA, 0x00);
         self.cpu.set_mem(TAC, 0x00);
@@ -160,7 +602,8 @@ impl GameBoy {
         self.cpu.set_mem(NR44, 0xbf);
         self.cpu.set_mem(NR50, 0x77);
         self.cpu.set_mem(NR51, 0xf3);
-        self.cpu.set_mem(NR52, 0xf1); // TODO This assumes we are running on a GB
+        self.cpu
+            .set_mem(NR52, if self.model.is_sgb() { 0xf0 } else { 0xf1 });
         self.cpu.set_mem(LCDC, 0x91);
         self.cpu.set_mem(SCY, 0x00);
         self.cpu.set_mem(SCX, 0x00);
@@ -172,5 +615,371 @@ impl GameBoy {
         self.cpu.set_mem(WX, 0x00);
 
         self.cpu.set_mem(IE, 0x00);
+
+        // Registers whose value depends on how long the boot rom took to run,
+        // or that behave differently in the CGB
+        let (div, _) = u16_to_u8(self.model.div_counter());
+        self.cpu.set_mem(DIV, div);
+        self.cpu
+            .set_mem(SC, if self.model.is_cgb() { 0x7f } else { 0x7e });
+        self.cpu
+            .set_mem(DMA, if self.model.is_cgb() { 0x00 } else { 0xff });
+        self.cpu.set_mem(
+            STAT,
+            if self.model == Model::Dmg0 {
+                0x81
+            } else {
+                0x85
+            },
+        );
+    }
+}
+
+/// Doubles every bit in the nibble, i.e. 0b1011 becomes 0b11001111
+fn scale_nibble(nibble: u8) -> u8 {
+    (0..4).fold(0, |row, bit| {
+        if nibble & (1 << bit) != 0 {
+            row | (0b11 << (bit * 2))
+        } else {
+            row
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cartridge::test::TestRom;
+    use terminal::KEY_HOLD;
+    use Renderer;
+
+    fn game_boy(rom: Vec<u8>) -> GameBoy {
+        GameBoy {
+            model: Model::Dmg,
+            cpu: Cpu::new(),
+            ppu: Ppu::new(Renderer::Scanline),
+            dmg_palette: DmgPalette::Grey,
+            color_correction: ColorCorrection::Raw,
+            blender: Blender::new(FrameBlending::Off),
+            blended: None,
+            sgb: None,
+            cartridge: Cartridge::new(rom).unwrap(),
+            boot_rom: None,
+            hle_boot: true,
+            validation: ValidationPolicy::Strict,
+            boot_sequence: None,
+            debugger: Debugger::new(DmgPalette::Grey, ColorCorrection::Raw),
+        }
+    }
+
+    /// Power on and play the whole boot sequence
+    fn boot(game_boy: &mut GameBoy) {
+        game_boy.power_on().unwrap();
+        while game_boy.boot_sequence.is_some() {
+            game_boy.run_frame().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scale_nibble() {
+        assert_eq!(scale_nibble(0b0000), 0b0000_0000);
+        assert_eq!(scale_nibble(0b1011), 0b1100_1111);
+        assert_eq!(scale_nibble(0b1111), 0b1111_1111);
+    }
+
+    #[test]
+    fn test_hle_boot() {
+        let mut rom = TestRom::new().build();
+        // jp 0x150; ld a, 0x42; ld (0xc000), a; jr -2
+        rom[0x100..0x103].copy_from_slice(&[0xc3, 0x50, 0x01]);
+        rom[0x150..0x157].copy_from_slice(&[0x3e, 0x42, 0xea, 0x00, 0xc0, 0x18, 0xfe]);
+        let mut game_boy = game_boy(rom);
+        game_boy.power_on().unwrap();
+        assert_eq!(game_boy.cpu.get_mem(SCY), BOOT_SCROLL_STEPS);
+        assert_eq!(game_boy.cpu.get_mem(LCDC), 0x91);
+
+        // The logo starts above the screen and scrolls down a line per frame
+        game_boy.run_frame().unwrap();
+        assert!(game_boy.frame().iter().all(|&shade| shade == 0));
+        assert_eq!(game_boy.cpu.get_mem(SCY), BOOT_SCROLL_STEPS - 1);
+        for _ in 1..BOOT_FIRST_NOTE_STEP {
+            game_boy.run_frame().unwrap();
+        }
+        assert!(game_boy.frame().contains(&3));
+        assert_eq!(game_boy.cpu.get_mem(SCY), 2);
+        assert_eq!(game_boy.cpu.get_mem(NR13), BOOT_FIRST_NOTE);
+
+        // The cartridge starts once the logo has been in place for a while
+        for _ in BOOT_FIRST_NOTE_STEP..BOOT_SCROLL_STEPS + BOOT_WAIT_STEPS - 1 {
+            game_boy.run_frame().unwrap();
+        }
+        assert_eq!(game_boy.cpu.get_mem(SCY), 0);
+        assert_eq!(game_boy.cpu.get_mem(NR13), BOOT_SECOND_NOTE);
+        assert_eq!(game_boy.cpu.get_mem(0xc000), 0);
+        game_boy.run_frame().unwrap();
+        assert!(game_boy.boot_sequence.is_none());
+        assert_eq!(game_boy.cpu.get_mem(NR52), 0xf1);
+        game_boy.run_frame().unwrap();
+        assert_eq!(game_boy.cpu.get_mem(0xc000), 0x42);
+
+        // First logo byte (0xce) is drawn as the first four rows of tile 1
+        assert_eq!(
+            game_boy.cpu.get_mem_range(0x8010, 0x8017),
+            &[0xf0, 0x00, 0xf0, 0x00, 0xfc, 0x00, 0xfc, 0x00]
+        );
+        assert_eq!(game_boy.cpu.get_mem(0x8190), 0x3c);
+
+        assert_eq!(game_boy.cpu.get_mem(0x9904), 0x01);
+        assert_eq!(game_boy.cpu.get_mem(0x990f), 0x0c);
+        assert_eq!(game_boy.cpu.get_mem(0x9910), 0x19);
+        assert_eq!(game_boy.cpu.get_mem(0x9924), 0x0d);
+        assert_eq!(game_boy.cpu.get_mem(0x992f), 0x18);
+    }
+
+    #[test]
+    fn test_hle_boot_bad_logo() {
+        let mut rom = TestRom::new().build();
+        rom[0x104] = 0;
+
+        let mut game_boy = game_boy(rom);
+        game_boy.map_cartridge();
+        assert!(game_boy.hle_boot().is_err());
+    }
+
+    #[test]
+    fn test_hle_boot_validation_policy() {
+        let mut rom = TestRom::new().build();
+        rom[0x104] = 0;
+
+        let mut game_boy = game_boy(rom);
+        game_boy.map_cartridge();
+        game_boy.validation = ValidationPolicy::Warn;
+        assert!(game_boy.hle_boot().is_ok());
+        game_boy.validation = ValidationPolicy::Ignore;
+        assert!(game_boy.hle_boot().is_ok());
+    }
+
+    #[test]
+    fn test_hle_boot_bad_header_checksum() {
+        let mut rom = TestRom::new().build();
+        rom[0x14d] = rom[0x14d].wrapping_add(1);
+
+        let mut game_boy = game_boy(rom);
+        game_boy.map_cartridge();
+        assert!(game_boy.hle_boot().is_err());
+    }
+
+    #[test]
+    fn test_hle_boot_cgb_checks_top_half_of_logo() {
+        let mut rom = TestRom::new().build();
+        rom[0x133] = 0;
+
+        let mut game_boy = game_boy(rom);
+        game_boy.model = Model::Cgb;
+        game_boy.map_cartridge();
+        assert!(game_boy.hle_boot().is_ok());
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let mut rom = TestRom::new().build();
+        // jr -2
+        rom[0x100] = 0x18;
+        rom[0x101] = 0xfe;
+        let mut game_boy = game_boy(rom);
+        boot(&mut game_boy);
+        let frames = game_boy.frame_count();
+        assert_eq!(frames, u64::from(BOOT_SCROLL_STEPS + BOOT_WAIT_STEPS));
+
+        game_boy.run_frame().unwrap();
+        assert_eq!(game_boy.frame_count(), frames + 1);
+        assert_eq!(game_boy.cpu.get_mem(LY), 144);
+
+        game_boy.run_frame().unwrap();
+        assert_eq!(game_boy.frame_count(), frames + 2);
+
+        // The logo left by the boot sequence
+        let frame = game_boy.frame();
+        assert_eq!(frame.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+        assert_eq!(frame[0], 0);
+        assert!(frame.contains(&3));
+        assert_eq!(game_boy.frame_rgba().len(), frame.len() * 4);
+
+        let screenshot = game_boy.screenshot();
+        assert_eq!(screenshot.width(), SCREEN_WIDTH);
+        assert_eq!(screenshot.rgba(), &game_boy.frame_rgba()[..]);
+
+        // The palette is applied to the shades
+        game_boy.dmg_palette = DmgPalette::Green;
+        assert_eq!(&game_boy.frame_rgba()[..4], &[0x9b, 0xbc, 0x0f, 0xff]);
+    }
+
+    #[test]
+    fn test_run_frame_with_lcd_off() {
+        let mut rom = TestRom::new().build();
+        // jp 0x150; xor a; ldh (0x40), a; jr -2
+        rom[0x100..0x103].copy_from_slice(&[0xc3, 0x50, 0x01]);
+        rom[0x150..0x155].copy_from_slice(&[0xaf, 0xe0, 0x40, 0x18, 0xfe]);
+        let mut game_boy = game_boy(rom);
+        boot(&mut game_boy);
+        let frames = game_boy.frame_count();
+
+        // Frames still complete, and they are blank
+        game_boy.run_frame().unwrap();
+        game_boy.run_frame().unwrap();
+        assert_eq!(game_boy.frame_count(), frames + 2);
+        assert_eq!(game_boy.cpu.get_mem(LY), 0);
+        assert!(game_boy.frame().iter().all(|&shade| shade == 0));
+    }
+
+    #[test]
+    fn test_sgb() {
+        // Send PAL01 with red as colour 0 through P1, one ld a, n and
+        // ldh (0x00), a at a time
+        let mut packet = [0; 16];
+        packet[..2].copy_from_slice(&[0x01, 0x1f]);
+        let mut writes = vec![0x00, 0x30];
+        for bit in 0..128 {
+            let one = (packet[bit / 8] >> (bit % 8)) & 1 != 0;
+            writes.extend(&[if one { 0x10 } else { 0x20 }, 0x30]);
+        }
+        writes.extend(&[0x20, 0x30]);
+        let mut code: Vec<u8> = writes
+            .iter()
+            .flat_map(|&p1| vec![0x3e, p1, 0xe0, 0x00])
+            .collect();
+        code.extend(&[0x18, 0xfe]);
+
+        let mut rom = TestRom::new().build();
+        rom[0x100..0x103].copy_from_slice(&[0xc3, 0x50, 0x01]);
+        rom[0x150..0x150 + code.len()].copy_from_slice(&code);
+        let mut game_boy = game_boy(rom);
+        game_boy.model = Model::Sgb;
+        game_boy.sgb = Some(Sgb::new(true));
+        game_boy.power_on().unwrap();
+        game_boy.run_frame().unwrap();
+
+        // Nothing is drawn on the SGB, so the screen is all colour 0
+        assert_eq!(game_boy.frame_colors().unwrap()[0], 0x001f);
+
+        // And so is the empty border around it
+        let red = [0xff, 0x00, 0x00, 0xff];
+        let screenshot = game_boy.screenshot();
+        assert_eq!(
+            (screenshot.width(), screenshot.height()),
+            (SGB_SCREEN_WIDTH, SGB_SCREEN_HEIGHT)
+        );
+        assert_eq!(screenshot.pixel(0, 0), red);
+        assert_eq!(screenshot.pixel(100, 100), red);
+    }
+
+    #[test]
+    fn test_frame_blending() {
+        let mut rom = TestRom::new().build();
+        rom[0x100] = 0x18;
+        rom[0x101] = 0xfe;
+        let mut plain = game_boy(rom.clone());
+        let mut blended = game_boy(rom);
+        blended.blender = Blender::new(FrameBlending::Mix);
+        boot(&mut plain);
+        boot(&mut blended);
+
+        // The first frame has nothing to be blended with
+        plain.run_frame().unwrap();
+        blended.run_frame().unwrap();
+        assert_eq!(blended.frame_rgba(), plain.frame_rgba());
+
+        // Blending only changes what is shown
+        plain.run_frame().unwrap();
+        blended.run_frame().unwrap();
+        assert_eq!(blended.frame(), plain.frame());
+        assert_eq!(blended.frame_rgba().len(), plain.frame_rgba().len());
+        assert_eq!(
+            blended.cpu.get_mem_range(0x8000, 0xffff),
+            plain.cpu.get_mem_range(0x8000, 0xffff)
+        );
+    }
+
+    #[test]
+    fn test_save_screenshot_at_scale_0() {
+        let mut game_boy = game_boy(TestRom::new().build());
+        game_boy.power_on().unwrap();
+        match game_boy.save_screenshot_at(10, "screenshot.png", 0) {
+            Err(ref e) => match *e.kind() {
+                ErrorKind::InvalidInput => {}
+                ref kind => panic!("Unexpected error kind {:?}", kind),
+            },
+            Ok(()) => panic!("Saved a screenshot scaled by 0"),
+        }
+        assert_eq!(game_boy.frame_count(), 0);
+    }
+
+    #[test]
+    fn test_dumps() {
+        let mut game_boy = game_boy(TestRom::new().build());
+        game_boy.power_on().unwrap();
+
+        // The logo tiles left by the boot sequence
+        let tiles = game_boy.dump_tiles();
+        assert_eq!((tiles.width(), tiles.height()), (128, 192));
+        assert!(tiles.rgba().chunks(4).any(|pixel| pixel[0] != 0xff));
+
+        let tilemaps = game_boy.dump_tilemaps();
+        assert_eq!(tilemaps.len(), 2);
+        assert_eq!(tilemaps[0].width(), 256);
+
+        assert_eq!(game_boy.dump_oam().lines().count(), 41);
+        assert_eq!(game_boy.dump_sprites().width(), 64);
+    }
+
+    #[test]
+    fn test_check_rom() {
+        // The test rom has a valid logo and header checksum, but its global
+        // checksum is left at zero
+        let cartridge = Cartridge::new(TestRom::new().build()).unwrap();
+        assert!(GameBoy::check_rom(&cartridge, ValidationPolicy::Strict).is_err());
+        assert!(GameBoy::check_rom(&cartridge, ValidationPolicy::Warn).is_ok());
+        assert!(GameBoy::check_rom(&cartridge, ValidationPolicy::Ignore).is_ok());
+
+        let cartridge = Cartridge::new(TestRom::new().global_checksum().build()).unwrap();
+        assert!(GameBoy::check_rom(&cartridge, ValidationPolicy::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_select_model() {
+        let config = |model| Config {
+            rom_name: String::new(),
+            boot_mode: BootMode::Skip,
+            model,
+            validation: ValidationPolicy::Warn,
+            patch: None,
+            archive_entry: None,
+            dat: None,
+            renderer: Renderer::Scanline,
+            dmg_palette: DmgPalette::Grey,
+            color_correction: ColorCorrection::Raw,
+            frame_blending: FrameBlending::Off,
+            terminal: false,
+            key_hold: KEY_HOLD,
+            screenshot: None,
+            scale: 1,
+        };
+        let mut rom = TestRom::new().build();
+        rom[0x143] = 0x80;
+        let cartridge = Cartridge::new(rom).unwrap();
+        let dmg_boot_rom = Some(BootRom::new(vec![0; 0x100]).unwrap());
+
+        let model = GameBoy::select_model(&config(None), &cartridge, &None).unwrap();
+        assert_eq!(model, Model::Cgb);
+
+        let model = GameBoy::select_model(&config(Some(Model::Mgb)), &cartridge, &None).unwrap();
+        assert_eq!(model, Model::Mgb);
+
+        let model = GameBoy::select_model(&config(None), &cartridge, &dmg_boot_rom).unwrap();
+        assert_eq!(model, Model::Dmg);
+
+        assert!(
+            GameBoy::select_model(&config(Some(Model::Cgb)), &cartridge, &dmg_boot_rom).is_err()
+        );
     }
 }
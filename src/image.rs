@@ -0,0 +1,251 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use errors::{Error, ErrorKind, Result};
+use hash::{adler32, crc32};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const PNG_BIT_DEPTH: u8 = 8;
+const PNG_COLOR_TYPE_RGBA: u8 = 6;
+const PNG_FILTER_NONE: u8 = 0;
+
+// zlib header for a deflate stream with a 32K window and no dictionary
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x01];
+const DEFLATE_MAX_STORED: usize = 0xffff;
+
+/// An 8 bit RGBA image
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, rgba: Vec<u8>) -> Image {
+        assert_eq!(rgba.len(), width * height * 4);
+        Image {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Pixels row by row, 4 bytes each
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [
+            self.rgba[i],
+            self.rgba[i + 1],
+            self.rgba[i + 2],
+            self.rgba[i + 3],
+        ]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        let i = (y * self.width + x) * 4;
+        self.rgba[i..i + 4].copy_from_slice(&rgba);
+    }
+
+    /// The image scaled up by an integer `factor`, repeating every pixel
+    pub fn scale(&self, factor: usize) -> Result<Image> {
+        if factor == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "An image can't be scaled by 0",
+            ));
+        }
+
+        let width = self.width * factor;
+        let mut rgba = Vec::with_capacity(self.rgba.len() * factor * factor);
+
+        for row in self.rgba.chunks(self.width * 4) {
+            let mut scaled = Vec::with_capacity(width * 4);
+            for pixel in row.chunks(4) {
+                for _ in 0..factor {
+                    scaled.extend_from_slice(pixel);
+                }
+            }
+            for _ in 0..factor {
+                rgba.extend_from_slice(&scaled);
+            }
+        }
+
+        Ok(Image::new(width, self.height * factor, rgba))
+    }
+
+    /// Encode the image as a PNG. The pixels are stored uncompressed.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[PNG_BIT_DEPTH, PNG_COLOR_TYPE_RGBA, 0, 0, 0]);
+
+        // Every row starts with the filter it was encoded with
+        let mut raw = Vec::with_capacity(self.rgba.len() + self.height);
+        for row in self.rgba.chunks(self.width * 4) {
+            raw.push(PNG_FILTER_NONE);
+            raw.extend_from_slice(row);
+        }
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Encode the image as a binary PPM, dropping the alpha channel
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in self.rgba.chunks(4) {
+            ppm.extend_from_slice(&pixel[..3]);
+        }
+        ppm
+    }
+
+    /// Save the image as a PNG or PPM, depending on the extension of `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        let data = match extension.as_deref() {
+            Some("png") => self.to_png(),
+            Some("ppm") => self.to_ppm(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unknown image format for {}, expected one of: png, ppm",
+                        path.display()
+                    ),
+                ))
+            }
+        };
+
+        File::create(path)?.write_all(&data)?;
+        Ok(())
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let begin = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[begin..]);
+
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// A zlib stream holding `data` in stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut zlib = ZLIB_HEADER.to_vec();
+
+    let mut blocks = data.chunks(DEFLATE_MAX_STORED).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use archive::inflate::inflate;
+
+    fn image() -> Image {
+        Image::new(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8])
+    }
+
+    #[test]
+    fn test_scale() {
+        let scaled = image().scale(2).unwrap();
+        assert_eq!(scaled.width(), 4);
+        assert_eq!(scaled.height(), 2);
+        assert_eq!(
+            &scaled.rgba()[..16],
+            &[1, 2, 3, 4, 1, 2, 3, 4, 5, 6, 7, 8, 5, 6, 7, 8]
+        );
+        assert_eq!(&scaled.rgba()[..16], &scaled.rgba()[16..]);
+
+        assert_eq!(image().scale(1).unwrap(), image());
+        assert!(image().scale(0).is_err());
+    }
+
+    #[test]
+    fn test_pixels() {
+        let mut image = image();
+        assert_eq!(image.pixel(1, 0), [5, 6, 7, 8]);
+
+        image.set_pixel(0, 0, [9, 9, 9, 9]);
+        assert_eq!(image.rgba(), &[9, 9, 9, 9, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[1, 2, 3, 5, 6, 7]);
+        assert_eq!(image().to_ppm(), expected);
+    }
+
+    #[test]
+    fn test_png() {
+        let png = image().to_png();
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+
+        // IHDR
+        assert_eq!(&png[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        assert_eq!(&png[29..33], &crc32(&png[12..29]).to_be_bytes());
+
+        // IDAT, holding a zlib stream of the filtered rows
+        let len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let zlib = &png[41..41 + len];
+        let raw = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(inflate(&zlib[2..]).unwrap().0, raw);
+        assert_eq!(&zlib[len - 4..], &adler32(&raw).to_be_bytes());
+
+        assert_eq!(&png[png.len() - 12..png.len() - 4], b"\0\0\0\0IEND");
+    }
+
+    #[test]
+    fn test_zlib_stored_blocks() {
+        let data = vec![0x5a; DEFLATE_MAX_STORED + 10];
+        let zlib = zlib_stored(&data);
+        assert_eq!(inflate(&zlib[2..]).unwrap().0, data);
+        assert_eq!(inflate(&zlib_stored(&[])[2..]).unwrap().0, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_save_unknown_format() {
+        assert!(image().save("screenshot.bmp").is_err());
+    }
+}
@@ -0,0 +1,390 @@
+use cartridge::Cartridge;
+use dat::Dat;
+use errors::{Error, ErrorKind, Result};
+use hash::to_hex;
+use read_rom;
+
+/// Arguments of the `info` command
+pub struct InfoConfig {
+    pub rom_names: Vec<String>,
+    pub json: bool,
+    /// DAT file used to identify the roms
+    pub dat: Option<String>,
+}
+
+impl InfoConfig {
+    pub fn new<I>(args: I) -> Result<InfoConfig>
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut rom_names = Vec::new();
+        let mut json = false;
+        let mut dat = None;
+
+        // Skip program and command names
+        let mut args = args.skip(2);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => json = true,
+                "--dat" => {
+                    dat = Some(args.next().ok_or_else(|| {
+                        Error::new(ErrorKind::InvalidInput, "Missing value for --dat")
+                    })?)
+                }
+                _ if arg.starts_with("--") => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unexpected argument: {}", arg),
+                    ))
+                }
+                _ => rom_names.push(arg),
+            }
+        }
+
+        if rom_names.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Please provide at least one rom name",
+            ));
+        }
+
+        Ok(InfoConfig {
+            rom_names,
+            json,
+            dat,
+        })
+    }
+}
+
+/// Print the header of every rom. Roms that can't be read are reported and
+/// skipped, failing once all the others have been printed.
+pub fn run(config: &InfoConfig) -> Result<()> {
+    let dat = match config.dat {
+        Some(ref dat) => Some(Dat::load(dat)?),
+        None => None,
+    };
+
+    let mut reports = Vec::new();
+    let mut failed = 0;
+
+    for rom_name in &config.rom_names {
+        let report = match load(rom_name, dat.as_ref()) {
+            Ok(cartridge) if config.json => json_report(rom_name, &cartridge),
+            Ok(cartridge) => text_report(rom_name, &cartridge),
+            Err(e) => {
+                failed += 1;
+                if config.json {
+                    format!(
+                        "{{\"file\": {}, \"error\": {}}}",
+                        json_string(rom_name),
+                        json_string(&e.to_string())
+                    )
+                } else {
+                    eprintln!("{}: {}", rom_name, e);
+                    continue;
+                }
+            }
+        };
+        reports.push(report);
+    }
+
+    if config.json {
+        println!("[{}]", reports.join(", "));
+    } else {
+        println!("{}", reports.join("\n"));
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Failed to read {} of {} roms", n, config.rom_names.len()),
+        )),
+    }
+}
+
+fn load(rom_name: &str, dat: Option<&Dat>) -> Result<Cartridge> {
+    let mut cartridge = Cartridge::new(read_rom(rom_name, None)?)?;
+    if let Some(dat) = dat {
+        cartridge.identify(dat);
+    }
+    Ok(cartridge)
+}
+
+/// Human readable description of the cartridge, one field per line
+pub fn text_report(rom_name: &str, cartridge: &Cartridge) -> String {
+    let header = cartridge.header();
+    let validation = cartridge.validate();
+    let valid = |ok: bool| if ok { "OK" } else { "BAD" };
+
+    let mut lines = vec![format!("{}:", rom_name)];
+    let mut field = |name: &str, value: String| lines.push(format!("  {:<18}{}", name, value));
+
+    field("Title", header.title.clone());
+    field(
+        "Manufacturer code",
+        header
+            .manufacturer_code
+            .clone()
+            .unwrap_or_else(|| "-".to_string()),
+    );
+    field("Licensee", header.licensee().to_string());
+    field("CGB", header.cgb().to_string());
+    field("SGB", header.sgb().to_string());
+    field(
+        "Cartridge type",
+        match header.cartridge_type() {
+            Some(t) if t.is_supported() => format!("{}", t),
+            Some(t) => format!("{} (unsupported)", t),
+            None => format!(
+                "Unknown ({:#04x}) (unsupported)",
+                header.cartridge_type_code
+            ),
+        },
+    );
+    field("ROM size", size(header.rom_size(), header.rom_size_code));
+    field("RAM size", size(header.ram_size(), header.ram_size_code));
+    field(
+        "Dump size",
+        format!(
+            "{} bytes {}",
+            cartridge.dump_size(),
+            valid(validation.rom_size)
+        ),
+    );
+    field(
+        "Destination",
+        match header.destination() {
+            Some(destination) => destination.to_string(),
+            None => format!("Unknown ({:#04x})", header.destination_code),
+        },
+    );
+    field("Version", header.mask_rom_version.to_string());
+    field("Logo", valid(validation.logo).to_string());
+    field(
+        "Header checksum",
+        format!(
+            "{:#04x} {}",
+            header.header_checksum,
+            valid(validation.header_checksum)
+        ),
+    );
+    field(
+        "Global checksum",
+        format!(
+            "{:#06x} {}",
+            header.global_checksum,
+            valid(validation.global_checksum)
+        ),
+    );
+
+    field("CRC32", format!("{:08x}", cartridge.crc32()));
+    field("SHA-1", to_hex(cartridge.sha1()));
+    if let Some(entry) = cartridge.dat_entry() {
+        field("DAT match", format!("{} ({})", entry.game, entry.status));
+    }
+
+    lines.join("\n")
+}
+
+/// Description of the cartridge as a single JSON object. Values that can't
+/// be decoded are `null`, and sizes are in bytes.
+pub fn json_report(rom_name: &str, cartridge: &Cartridge) -> String {
+    let header = cartridge.header();
+    let validation = cartridge.validate();
+    let cartridge_type = header.cartridge_type();
+
+    let fields = vec![
+        ("file", json_string(rom_name)),
+        ("title", json_string(&header.title)),
+        (
+            "manufacturer_code",
+            json_option(header.manufacturer_code.as_ref().map(|c| json_string(c))),
+        ),
+        ("licensee", json_string(&header.licensee().to_string())),
+        ("cgb", json_string(&header.cgb().to_string())),
+        ("sgb", json_string(&header.sgb().to_string())),
+        (
+            "cartridge_type",
+            json_option(cartridge_type.map(|t| json_string(&t.to_string()))),
+        ),
+        (
+            "cartridge_type_code",
+            header.cartridge_type_code.to_string(),
+        ),
+        (
+            "supported",
+            cartridge_type.is_some_and(|t| t.is_supported()).to_string(),
+        ),
+        (
+            "rom_size",
+            json_option(header.rom_size().map(|s| s.to_string())),
+        ),
+        ("dump_size", cartridge.dump_size().to_string()),
+        ("rom_size_valid", validation.rom_size.to_string()),
+        (
+            "ram_size",
+            json_option(header.ram_size().map(|s| s.to_string())),
+        ),
+        (
+            "destination",
+            json_option(header.destination().map(|d| json_string(&d.to_string()))),
+        ),
+        ("version", header.mask_rom_version.to_string()),
+        ("logo_valid", validation.logo.to_string()),
+        ("header_checksum", header.header_checksum.to_string()),
+        (
+            "header_checksum_valid",
+            validation.header_checksum.to_string(),
+        ),
+        ("global_checksum", header.global_checksum.to_string()),
+        (
+            "global_checksum_valid",
+            validation.global_checksum.to_string(),
+        ),
+        ("crc32", json_string(&format!("{:08x}", cartridge.crc32()))),
+        ("sha1", json_string(&to_hex(cartridge.sha1()))),
+        (
+            "dat_name",
+            json_option(cartridge.dat_entry().map(|entry| json_string(&entry.game))),
+        ),
+        (
+            "dat_revision",
+            json_option(
+                cartridge
+                    .dat_entry()
+                    .and_then(|entry| entry.revision())
+                    .map(json_string),
+            ),
+        ),
+        (
+            "dat_status",
+            json_option(
+                cartridge
+                    .dat_entry()
+                    .map(|entry| json_string(&entry.status.to_string())),
+            ),
+        ),
+    ];
+
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|(name, value)| format!("{}: {}", json_string(name), value))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn size(size: Option<usize>, code: u8) -> String {
+    match size {
+        Some(0) => "None".to_string(),
+        Some(size) => format!("{} KiB", size / 1024),
+        None if code == 0 => "None".to_string(),
+        None => format!("Unknown ({:#04x})", code),
+    }
+}
+
+fn json_option(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cartridge::test::TestRom;
+    use test::args;
+
+    fn cartridge() -> Cartridge {
+        let rom = TestRom::new()
+            .set(0x147, 0x03)
+            .set(0x149, 0x02)
+            .set(0x14b, 0x01)
+            .build();
+        Cartridge::new(rom).unwrap()
+    }
+
+    #[test]
+    fn test_info_config() {
+        let config = InfoConfig::new(args(&["rustyboy", "info", "a.gb", "b.gb"])).unwrap();
+        assert_eq!(config.rom_names, vec!["a.gb", "b.gb"]);
+        assert!(!config.json);
+
+        let config = InfoConfig::new(args(&["rustyboy", "info", "--json", "a.gb"])).unwrap();
+        assert!(config.json);
+
+        let config =
+            InfoConfig::new(args(&["rustyboy", "info", "a.gb", "--dat", "gb.dat"])).unwrap();
+        assert_eq!(config.dat, Some("gb.dat".to_string()));
+
+        assert!(InfoConfig::new(args(&["rustyboy", "info"])).is_err());
+        assert!(InfoConfig::new(args(&["rustyboy", "info", "a.gb", "--dat"])).is_err());
+        assert!(InfoConfig::new(args(&["rustyboy", "info", "--xml", "a.gb"])).is_err());
+    }
+
+    #[test]
+    fn test_text_report() {
+        let report = text_report("a.gb", &cartridge());
+        assert!(report.starts_with("a.gb:\n"));
+        assert!(report.contains("  Title             RUSTYB\n"));
+        assert!(report.contains("  Licensee          Nintendo\n"));
+        assert!(report.contains("  Cartridge type    MBC1+RAM+BATTERY (unsupported)\n"));
+        assert!(report.contains("  ROM size          32 KiB\n"));
+        assert!(report.contains("  RAM size          8 KiB\n"));
+        assert!(report.contains("  Logo              OK\n"));
+        assert!(report.contains("  Global checksum   0x0000 BAD\n"));
+        assert!(!report.contains("DAT match"));
+    }
+
+    #[test]
+    fn test_text_report_dat_match() {
+        let mut cartridge = cartridge();
+        let dat = Dat::parse(&format!(
+            r#"<game name="RustyBoy (World)"><rom size="32768" crc="{:08x}"/></game>"#,
+            cartridge.crc32()
+        ))
+        .unwrap();
+        cartridge.identify(&dat);
+
+        let report = text_report("a.gb", &cartridge);
+        assert!(report.contains(&format!("  CRC32             {:08x}\n", cartridge.crc32())));
+        assert!(report.ends_with("  DAT match         RustyBoy (World) (Good)"));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = json_report("a \"b\".gb", &cartridge());
+        assert!(report.starts_with("{\"file\": \"a \\\"b\\\".gb\", \"title\": \"RUSTYB\""));
+        assert!(report.contains("\"manufacturer_code\": null"));
+        assert!(report.contains("\"cartridge_type\": \"MBC1+RAM+BATTERY\""));
+        assert!(report.contains("\"supported\": false"));
+        assert!(report.contains("\"rom_size\": 32768"));
+        assert!(report.contains("\"header_checksum_valid\": true"));
+        assert!(report.contains("\"global_checksum_valid\": false"));
+        assert!(
+            report.ends_with("\"dat_name\": null, \"dat_revision\": null, \"dat_status\": null}")
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("abc"), "\"abc\"");
+        assert_eq!(json_string("a\\b\n\u{1}"), "\"a\\\\b\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,144 @@
+//! The 8 buttons of the Game Boy, read by games through the P1 register.
+
+/// A button of the Game Boy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+// P1 bits, cleared by the game to select which buttons it reads
+const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+const SELECT_ACTIONS: u8 = 0b0010_0000;
+const SELECT_MASK: u8 = SELECT_DIRECTIONS | SELECT_ACTIONS;
+
+impl Button {
+    /// Bit of the button in the joypad state. The directions are in the low
+    /// nibble and the action buttons in the high one, in the order P1
+    /// reports them.
+    #[rustfmt::skip]
+    fn mask(self) -> u8 {
+        match self {
+            Button::Right =>  0b0000_0001,
+            Button::Left =>   0b0000_0010,
+            Button::Up =>     0b0000_0100,
+            Button::Down =>   0b0000_1000,
+            Button::A =>      0b0001_0000,
+            Button::B =>      0b0010_0000,
+            Button::Select => 0b0100_0000,
+            Button::Start =>  0b1000_0000,
+        }
+    }
+}
+
+/// Which buttons are held down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Joypad {
+    pressed: u8,
+    /// Controller read through P1, when the SGB has more than one plugged
+    /// in. Only the first one has buttons that can be pressed.
+    player: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad::default()
+    }
+
+    /// Press or release a button
+    pub fn set(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.pressed |= button.mask();
+        } else {
+            self.pressed &= !button.mask();
+        }
+    }
+
+    pub fn set_player(&mut self, player: u8) {
+        self.player = player;
+    }
+
+    /// P1 as the CPU reads it, given the select bits last written to it.
+    /// The buttons of the selected groups read as 0 when pressed, and the
+    /// unused top bits as 1. With neither group selected, the low bits tell
+    /// which controller is read, 0xf being the first.
+    pub fn read(&self, p1: u8) -> u8 {
+        let pressed = if self.player == 0 { self.pressed } else { 0 };
+        let mut buttons = 0;
+        if p1 & SELECT_MASK == SELECT_MASK {
+            buttons = self.player;
+        }
+        if p1 & SELECT_DIRECTIONS == 0 {
+            buttons |= pressed & 0x0f;
+        }
+        if p1 & SELECT_ACTIONS == 0 {
+            buttons |= pressed >> 4;
+        }
+        0b1100_0000 | (p1 & SELECT_MASK) | (!buttons & 0x0f)
+    }
+
+    /// The part of a P1 write that sticks, since the buttons are read only
+    pub fn write(value: u8) -> u8 {
+        value & SELECT_MASK
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nothing_pressed() {
+        let joypad = Joypad::new();
+        assert_eq!(joypad.read(0x00), 0xcf);
+        assert_eq!(joypad.read(0x30), 0xff);
+    }
+
+    #[test]
+    fn test_select_groups() {
+        let mut joypad = Joypad::new();
+        joypad.set(Button::Up, true);
+        joypad.set(Button::Start, true);
+
+        assert_eq!(joypad.read(SELECT_ACTIONS), 0b1110_1011);
+        assert_eq!(joypad.read(SELECT_DIRECTIONS), 0b1101_0111);
+        // Both groups are mixed together when both are selected
+        assert_eq!(joypad.read(0x00), 0b1100_0011);
+        assert_eq!(joypad.read(SELECT_MASK), 0xff);
+    }
+
+    #[test]
+    fn test_press_and_release() {
+        let mut joypad = Joypad::new();
+        joypad.set(Button::A, true);
+        joypad.set(Button::A, true);
+        assert_eq!(joypad.read(SELECT_DIRECTIONS), 0xde);
+        joypad.set(Button::A, false);
+        assert_eq!(joypad.read(0x00), 0xcf);
+    }
+
+    #[test]
+    fn test_players() {
+        let mut joypad = Joypad::new();
+        joypad.set(Button::A, true);
+        joypad.set_player(1);
+        assert_eq!(joypad.read(SELECT_MASK), 0xfe);
+        // The other controllers have nothing pressed
+        assert_eq!(joypad.read(SELECT_DIRECTIONS), 0xdf);
+
+        joypad.set_player(0);
+        assert_eq!(joypad.read(SELECT_DIRECTIONS), 0xde);
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(Joypad::write(0xff), 0x30);
+        assert_eq!(Joypad::write(0xdf), 0x10);
+    }
+}
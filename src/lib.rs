@@ -1,25 +1,187 @@
 #![allow(clippy::verbose_bit_mask)]
-mod cartridge;
+mod archive;
+pub mod blend;
+mod boot_rom;
+pub mod cartridge;
 mod cpu;
+pub mod dat;
 mod debugger;
 mod errors;
 pub mod game_boy;
+mod hash;
+pub mod image;
+pub mod info;
+pub mod joypad;
+pub mod model;
+pub mod palette;
+mod patch;
+mod ppu;
+mod registers;
+mod sgb;
+pub mod terminal;
+
+pub use ppu::Renderer;
 
 use std::fs::File;
 use std::io::prelude::*;
+use std::str::FromStr;
+use std::time::Duration;
 
+use blend::FrameBlending;
 use errors::{Error, ErrorKind, Result};
+use model::Model;
+use palette::{ColorCorrection, DmgPalette};
+
+#[derive(Debug, PartialEq)]
+pub enum BootMode {
+    /// Start the cartridge at 0x100 with the post boot state already in place
+    Skip,
+    /// Built-in emulation of the boot sequence (logo scroll, chime and validation)
+    Hle,
+    /// Run the given boot rom dump
+    Rom(String),
+}
+
+/// What to do with a cartridge that fails validation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValidationPolicy {
+    /// Refuse to run it
+    Strict,
+    /// Report every failed check and run it anyway
+    Warn,
+    /// Run it without checking
+    Ignore,
+}
+
+impl FromStr for ValidationPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ValidationPolicy> {
+        match s.to_lowercase().as_str() {
+            "strict" => Ok(ValidationPolicy::Strict),
+            "warn" => Ok(ValidationPolicy::Warn),
+            "ignore" => Ok(ValidationPolicy::Ignore),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown validation policy {}, expected one of: strict, warn, ignore",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+/// Frame to save a screenshot of, and where
+#[derive(Debug, PartialEq)]
+pub struct Screenshot {
+    pub frame: u64,
+    pub path: String,
+}
 
 pub struct Config {
     pub rom_name: String,
+    pub boot_mode: BootMode,
+    /// Hardware to emulate, detected from the cartridge header when `None`
+    pub model: Option<Model>,
+    pub validation: ValidationPolicy,
+    /// IPS, UPS or BPS patch to apply to the rom. When `None`, a patch with
+    /// the same name as the rom is used if there is one.
+    pub patch: Option<String>,
+    /// Entry to load when the rom is in a zip archive, instead of the first
+    /// one with a .gb or .gbc extension
+    pub archive_entry: Option<String>,
+    /// No-Intro style DAT file used to identify the rom
+    pub dat: Option<String>,
+    pub renderer: Renderer,
+    /// Colours of the DMG shades
+    pub dmg_palette: DmgPalette,
+    /// How CGB colours are adjusted to look like they do on a real screen
+    pub color_correction: ColorCorrection,
+    /// Blending of every frame with the ones before it, like the LCD does
+    pub frame_blending: FrameBlending,
+    /// Play in the terminal, instead of running without a screen
+    pub terminal: bool,
+    /// How long a button stays down after its key is seen in the terminal
+    pub key_hold: Duration,
+    /// Save a screenshot and stop, instead of running indefinitely
+    pub screenshot: Option<Screenshot>,
+    /// Integer factor screenshots are scaled up by
+    pub scale: usize,
 }
 
 impl Config {
-    pub fn new(mut args: std::env::Args) -> Result<Config> {
+    pub fn new<I>(mut args: I) -> Result<Config>
+    where
+        I: Iterator<Item = String>,
+    {
         // Skip program name
         args.next();
 
-        let rom_name = match args.next() {
+        let mut rom_name = None;
+        let mut boot_mode = BootMode::Skip;
+        let mut model = None;
+        // Homebrew and prototypes often have bad checksums
+        let mut validation = ValidationPolicy::Warn;
+        let mut patch = None;
+        let mut archive_entry = None;
+        let mut dat = None;
+        let mut renderer = Renderer::Scanline;
+        let mut dmg_palette = DmgPalette::Grey;
+        let mut color_correction = ColorCorrection::Raw;
+        let mut frame_blending = FrameBlending::Off;
+        let mut terminal = false;
+        let mut key_hold = terminal::KEY_HOLD;
+        let mut screenshot = None;
+        let mut scale = 1;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--boot-rom" => boot_mode = BootMode::Rom(Config::flag_value(&arg, args.next())?),
+                "--hle-boot" => boot_mode = BootMode::Hle,
+                "--model" => model = Some(Config::flag_value(&arg, args.next())?.parse()?),
+                "--validation" => validation = Config::flag_value(&arg, args.next())?.parse()?,
+                "--patch" => patch = Some(Config::flag_value(&arg, args.next())?),
+                "--entry" => archive_entry = Some(Config::flag_value(&arg, args.next())?),
+                "--dat" => dat = Some(Config::flag_value(&arg, args.next())?),
+                "--renderer" => renderer = Config::flag_value(&arg, args.next())?.parse()?,
+                "--palette" => dmg_palette = Config::flag_value(&arg, args.next())?.parse()?,
+                "--color-correction" => {
+                    color_correction = Config::flag_value(&arg, args.next())?.parse()?
+                }
+                "--frame-blending" => {
+                    frame_blending = Config::flag_value(&arg, args.next())?.parse()?
+                }
+                "--terminal" => terminal = true,
+                "--key-hold" => {
+                    key_hold = Duration::from_millis(Config::number(&arg, args.next())?)
+                }
+                "--screenshot-at-frame" => {
+                    screenshot = Some(Screenshot {
+                        frame: Config::number(&arg, args.next())?,
+                        path: Config::flag_value(&arg, args.next())?,
+                    })
+                }
+                "--scale" => {
+                    scale = Config::number(&arg, args.next())?;
+                    if scale == 0 {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            "The screenshot scale must be at least 1",
+                        ));
+                    }
+                }
+                _ if rom_name.is_none() => rom_name = Some(arg),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unexpected argument: {}", arg),
+                    ))
+                }
+            }
+        }
+
+        let rom_name = match rom_name {
             Some(arg) => arg,
             None => {
                 return Err(Error::new(
@@ -29,7 +191,42 @@ impl Config {
             }
         };
 
-        Ok(Config { rom_name })
+        Ok(Config {
+            rom_name,
+            boot_mode,
+            model,
+            validation,
+            patch,
+            archive_entry,
+            dat,
+            renderer,
+            dmg_palette,
+            color_correction,
+            frame_blending,
+            terminal,
+            key_hold,
+            screenshot,
+            scale,
+        })
+    }
+
+    fn number<T: FromStr>(flag: &str, value: Option<String>) -> Result<T> {
+        let value = Config::flag_value(flag, value)?;
+        value.parse().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid number for {}: {}", flag, value),
+            )
+        })
+    }
+
+    fn flag_value(flag: &str, value: Option<String>) -> Result<String> {
+        value.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Missing value for {}", flag),
+            )
+        })
     }
 }
 
@@ -42,6 +239,11 @@ pub fn read_file(file_name: &str) -> Result<Vec<u8>> {
     Ok(data)
 }
 
+/// Read a rom, decompressing it if it is in a zip or gzip archive
+pub fn read_rom(file_name: &str, archive_entry: Option<&str>) -> Result<Vec<u8>> {
+    archive::extract(read_file(file_name)?, archive_entry)
+}
+
 #[inline]
 pub fn u8_to_u16(b1: u8, b2: u8) -> u16 {
     (u16::from(b1) << 8) | u16::from(b2)
@@ -53,7 +255,7 @@ pub fn u16_to_u8(n: u16) -> (u8, u8) {
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::*;
 
     #[test]
@@ -67,4 +269,185 @@ mod test {
         assert_eq!(u16_to_u8(0xffff), (0xff, 0xff));
         assert_eq!(u16_to_u8(0xf077), (0xf0, 0x77));
     }
+
+    /// Command line arguments, as `Config::new` gets them
+    pub fn args(args: &[&str]) -> std::vec::IntoIter<String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_config_rom_name() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.rom_name, "tetris.gb");
+        assert_eq!(config.boot_mode, BootMode::Skip);
+
+        assert!(Config::new(args(&["rustyboy"])).is_err());
+        assert!(Config::new(args(&["rustyboy", "a.gb", "b.gb"])).is_err());
+    }
+
+    #[test]
+    fn test_config_boot_rom() {
+        let config = Config::new(args(&[
+            "rustyboy",
+            "--boot-rom",
+            "dmg_boot.bin",
+            "tetris.gb",
+        ]))
+        .unwrap();
+        assert_eq!(config.rom_name, "tetris.gb");
+        assert_eq!(config.boot_mode, BootMode::Rom("dmg_boot.bin".to_string()));
+
+        assert!(Config::new(args(&["rustyboy", "tetris.gb", "--boot-rom"])).is_err());
+    }
+
+    #[test]
+    fn test_config_model() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.model, None);
+
+        let config = Config::new(args(&["rustyboy", "--model", "mgb", "tetris.gb"])).unwrap();
+        assert_eq!(config.model, Some(Model::Mgb));
+
+        assert!(Config::new(args(&["rustyboy", "--model", "nes", "tetris.gb"])).is_err());
+    }
+
+    #[test]
+    fn test_config_hle_boot() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb", "--hle-boot"])).unwrap();
+        assert_eq!(config.rom_name, "tetris.gb");
+        assert_eq!(config.boot_mode, BootMode::Hle);
+    }
+
+    #[test]
+    fn test_config_patch() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.patch, None);
+
+        let config = Config::new(args(&["rustyboy", "tetris.gb", "--patch", "dx.ips"])).unwrap();
+        assert_eq!(config.patch, Some("dx.ips".to_string()));
+    }
+
+    #[test]
+    fn test_config_archive_entry() {
+        let config = Config::new(args(&["rustyboy", "roms.zip", "--entry", "dx.gbc"])).unwrap();
+        assert_eq!(config.archive_entry, Some("dx.gbc".to_string()));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.validation, ValidationPolicy::Warn);
+
+        let config =
+            Config::new(args(&["rustyboy", "--validation", "strict", "tetris.gb"])).unwrap();
+        assert_eq!(config.validation, ValidationPolicy::Strict);
+
+        assert!(Config::new(args(&["rustyboy", "--validation", "lax", "tetris.gb"])).is_err());
+    }
+
+    #[test]
+    fn test_config_colors() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.dmg_palette, DmgPalette::Grey);
+        assert_eq!(config.color_correction, ColorCorrection::Raw);
+
+        let config = Config::new(args(&[
+            "rustyboy",
+            "tetris.gb",
+            "--palette",
+            "green",
+            "--color-correction",
+            "lcd",
+        ]))
+        .unwrap();
+        assert_eq!(config.dmg_palette, DmgPalette::Green);
+        assert_eq!(config.color_correction, ColorCorrection::Lcd);
+
+        assert!(Config::new(args(&["rustyboy", "tetris.gb", "--palette", "red"])).is_err());
+        assert!(Config::new(args(&["rustyboy", "tetris.gb", "--color-correction"])).is_err());
+    }
+
+    #[test]
+    fn test_config_frame_blending() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.frame_blending, FrameBlending::Off);
+
+        let config =
+            Config::new(args(&["rustyboy", "tetris.gb", "--frame-blending", "mix"])).unwrap();
+        assert_eq!(config.frame_blending, FrameBlending::Mix);
+
+        assert!(Config::new(args(&["rustyboy", "tetris.gb", "--frame-blending", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_config_terminal() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert!(!config.terminal);
+
+        let config = Config::new(args(&["rustyboy", "--terminal", "tetris.gb"])).unwrap();
+        assert!(config.terminal);
+    }
+
+    #[test]
+    fn test_config_key_hold() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.key_hold, terminal::KEY_HOLD);
+
+        let config = Config::new(args(&["rustyboy", "--key-hold", "300", "tetris.gb"])).unwrap();
+        assert_eq!(config.key_hold, Duration::from_millis(300));
+
+        assert!(Config::new(args(&["rustyboy", "--key-hold", "x", "tetris.gb"])).is_err());
+    }
+
+    #[test]
+    fn test_config_renderer() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.renderer, Renderer::Scanline);
+
+        let config = Config::new(args(&["rustyboy", "tetris.gb", "--renderer", "fifo"])).unwrap();
+        assert_eq!(config.renderer, Renderer::Fifo);
+
+        assert!(Config::new(args(&["rustyboy", "tetris.gb", "--renderer", "gpu"])).is_err());
+    }
+
+    #[test]
+    fn test_config_screenshot() {
+        let config = Config::new(args(&["rustyboy", "tetris.gb"])).unwrap();
+        assert_eq!(config.screenshot, None);
+        assert_eq!(config.scale, 1);
+
+        let config = Config::new(args(&[
+            "rustyboy",
+            "--screenshot-at-frame",
+            "60",
+            "out.png",
+            "--scale",
+            "3",
+            "tetris.gb",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.screenshot,
+            Some(Screenshot {
+                frame: 60,
+                path: "out.png".to_string(),
+            })
+        );
+        assert_eq!(config.scale, 3);
+
+        let config = |a: &[&str]| Config::new(args(a));
+        assert!(config(&[
+            "rustyboy",
+            "tetris.gb",
+            "--screenshot-at-frame",
+            "x",
+            "a.png"
+        ])
+        .is_err());
+        assert!(config(&["rustyboy", "tetris.gb", "--screenshot-at-frame", "60"]).is_err());
+        assert!(config(&["rustyboy", "tetris.gb", "--scale", "0"]).is_err());
+    }
 }
@@ -4,9 +4,15 @@ use std::env;
 use std::process;
 
 use rustyboy::game_boy::GameBoy;
+use rustyboy::info::{self, InfoConfig};
+use rustyboy::terminal;
 use rustyboy::Config;
 
 fn main() {
+    if env::args().nth(1).as_deref() == Some("info") {
+        return run_info();
+    }
+
     let config = Config::new(env::args()).unwrap_or_else(|e| {
         eprintln!("{}", e);
         process::exit(1);
@@ -17,8 +23,27 @@ fn main() {
         process::exit(1);
     });
 
-    game_boy.run().unwrap_or_else(|e| {
+    let result = match config.screenshot {
+        Some(ref screenshot) => {
+            game_boy.save_screenshot_at(screenshot.frame, &screenshot.path, config.scale)
+        }
+        None if config.terminal => terminal::run(&mut game_boy, config.key_hold),
+        None => game_boy.run(),
+    };
+    result.unwrap_or_else(|e| {
         eprintln!("Unexpected error while running: {}", e);
         process::exit(1);
     });
 }
+
+fn run_info() {
+    let config = InfoConfig::new(env::args()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    info::run(&config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+use cartridge::{Cartridge, CgbSupport, SgbSupport};
+use errors::{Error, ErrorKind, Result};
+
+/// Game Boy hardware revisions with observable differences
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    /// Early original Game Boy, only released in Japan
+    Dmg0,
+    /// Original Game Boy
+    Dmg,
+    /// Game Boy Pocket
+    Mgb,
+    /// Super Game Boy
+    Sgb,
+    /// Super Game Boy 2
+    Sgb2,
+    /// Game Boy Color
+    Cgb,
+    /// Game Boy Advance running Game Boy software
+    Agb,
+}
+
+impl Model {
+    /// Pick the model that best fits the cartridge, based on the CGB and SGB
+    /// flags of its header.
+    pub fn detect(cartridge: &Cartridge) -> Model {
+        match (cartridge.header().cgb(), cartridge.header().sgb()) {
+            (CgbSupport::Enhanced, _) | (CgbSupport::CgbOnly, _) => Model::Cgb,
+            (CgbSupport::DmgOnly, SgbSupport::Supported) => Model::Sgb,
+            (CgbSupport::DmgOnly, SgbSupport::Unsupported) => Model::Dmg,
+        }
+    }
+
+    pub fn is_dmg(self) -> bool {
+        matches!(self, Model::Dmg0 | Model::Dmg | Model::Mgb)
+    }
+
+    pub fn is_sgb(self) -> bool {
+        matches!(self, Model::Sgb | Model::Sgb2)
+    }
+
+    pub fn is_cgb(self) -> bool {
+        matches!(self, Model::Cgb | Model::Agb)
+    }
+
+    /// Value of the internal 16 bit divider counter when the boot rom hands
+    /// over control to the cartridge. DIV exposes its upper 8 bits.
+    pub fn div_counter(self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x182c,
+            Model::Dmg | Model::Mgb => 0xabcc,
+            Model::Sgb | Model::Sgb2 => 0xd85c,
+            Model::Cgb | Model::Agb => 0x1ea0,
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Model> {
+        match s.to_lowercase().as_str() {
+            "dmg0" => Ok(Model::Dmg0),
+            "dmg" => Ok(Model::Dmg),
+            "mgb" => Ok(Model::Mgb),
+            "sgb" => Ok(Model::Sgb),
+            "sgb2" => Ok(Model::Sgb2),
+            "cgb" => Ok(Model::Cgb),
+            "agb" => Ok(Model::Agb),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown model {}, expected one of: dmg0, dmg, mgb, sgb, sgb2, cgb, agb",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Model::Dmg0 => "DMG0",
+            Model::Dmg => "DMG",
+            Model::Mgb => "MGB",
+            Model::Sgb => "SGB",
+            Model::Sgb2 => "SGB2",
+            Model::Cgb => "CGB",
+            Model::Agb => "AGB",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rom(cgb_flag: u8, sgb_flag: u8, old_licensee: u8) -> Cartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x143] = cgb_flag;
+        rom[0x146] = sgb_flag;
+        rom[0x14b] = old_licensee;
+        Cartridge::new(rom).unwrap()
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("dmg".parse::<Model>().unwrap(), Model::Dmg);
+        assert_eq!("SGB2".parse::<Model>().unwrap(), Model::Sgb2);
+        assert_eq!("agb".parse::<Model>().unwrap(), Model::Agb);
+        assert!("gba".parse::<Model>().is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Model::detect(&rom(0x00, 0x00, 0x01)), Model::Dmg);
+        assert_eq!(Model::detect(&rom(0x80, 0x00, 0x33)), Model::Cgb);
+        assert_eq!(Model::detect(&rom(0xc0, 0x03, 0x33)), Model::Cgb);
+        assert_eq!(Model::detect(&rom(0x00, 0x03, 0x33)), Model::Sgb);
+        // The SGB flag is ignored unless the old licensee code is 0x33
+        assert_eq!(Model::detect(&rom(0x00, 0x03, 0x01)), Model::Dmg);
+    }
+}
@@ -0,0 +1,205 @@
+//! Colours the frames are shown with, applied to the framebuffer after it is
+//! drawn.
+
+use std::str::FromStr;
+
+use errors::{Error, ErrorKind, Result};
+
+/// Colours of the 4 DMG shades, from lightest to darkest
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DmgPalette {
+    /// Neutral greys from white to black
+    Grey,
+    /// The green tint of the original DMG screen
+    Green,
+    /// The grey screen of the Game Boy Pocket
+    Pocket,
+    /// Any 4 colours, as 0xRRGGBB
+    Custom([u32; 4]),
+}
+
+const GREY: [u32; 4] = [0xffffff, 0xaaaaaa, 0x555555, 0x000000];
+const GREEN: [u32; 4] = [0x9bbc0f, 0x8bac0f, 0x306230, 0x0f380f];
+const POCKET: [u32; 4] = [0xc4cfa1, 0x8b956d, 0x4d533c, 0x1f1f1f];
+
+impl DmgPalette {
+    fn colors(&self) -> [u32; 4] {
+        match *self {
+            DmgPalette::Grey => GREY,
+            DmgPalette::Green => GREEN,
+            DmgPalette::Pocket => POCKET,
+            DmgPalette::Custom(colors) => colors,
+        }
+    }
+
+    /// 8 bit RGBA of a shade, 0 (lightest) to 3 (darkest)
+    pub fn rgba(&self, shade: u8) -> [u8; 4] {
+        let color = self.colors()[usize::from(shade)];
+        [(color >> 16) as u8, (color >> 8) as u8, color as u8, 0xff]
+    }
+}
+
+impl FromStr for DmgPalette {
+    type Err = Error;
+
+    /// One of the built-in palettes by name, or 4 comma separated RRGGBB
+    /// colours from lightest to darkest
+    fn from_str(s: &str) -> Result<DmgPalette> {
+        match s.to_lowercase().as_str() {
+            "grey" => return Ok(DmgPalette::Grey),
+            "green" => return Ok(DmgPalette::Green),
+            "pocket" => return Ok(DmgPalette::Pocket),
+            _ => {}
+        }
+
+        let error = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown palette {}, expected one of: grey, green, pocket, \
+                     or 4 colours as RRGGBB,RRGGBB,RRGGBB,RRGGBB",
+                    s
+                ),
+            )
+        };
+
+        let colors = s
+            .split(',')
+            .map(|color| {
+                if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(error());
+                }
+                u32::from_str_radix(color, 16).map_err(|_| error())
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        if colors.len() != 4 {
+            return Err(error());
+        }
+
+        Ok(DmgPalette::Custom([
+            colors[0], colors[1], colors[2], colors[3],
+        ]))
+    }
+}
+
+/// How the 15 bit CGB colours are turned into the colours on screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorCorrection {
+    /// Scale every channel to 8 bits as is, which looks much more saturated
+    /// than the CGB screen
+    Raw,
+    /// Mix the channels and cap the brightness like the CGB screen does
+    Lcd,
+    /// Darken the mid tones like the GBA screen does when running CGB games
+    Gba,
+}
+
+impl ColorCorrection {
+    /// 8 bit RGBA of a 15 bit CGB colour, with red in the lowest bits
+    pub fn rgba(&self, color: u16) -> [u8; 4] {
+        let r = u32::from(color & 0x1f);
+        let g = u32::from((color >> 5) & 0x1f);
+        let b = u32::from((color >> 10) & 0x1f);
+
+        match *self {
+            ColorCorrection::Raw => [expand(r), expand(g), expand(b), 0xff],
+            ColorCorrection::Lcd => {
+                // Every channel bleeds into the others, and the brightest
+                // colours top out at 960 / 4
+                let mix = |value: u32| (value.min(960) >> 2) as u8;
+                [
+                    mix(r * 26 + g * 4 + b * 2),
+                    mix(g * 24 + b * 8),
+                    mix(r * 6 + g * 4 + b * 22),
+                    0xff,
+                ]
+            }
+            ColorCorrection::Gba => {
+                let darken = |value: u32| (value * value * 0xff / (0x1f * 0x1f)) as u8;
+                [darken(r), darken(g), darken(b), 0xff]
+            }
+        }
+    }
+}
+
+impl FromStr for ColorCorrection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ColorCorrection> {
+        match s.to_lowercase().as_str() {
+            "raw" => Ok(ColorCorrection::Raw),
+            "lcd" => Ok(ColorCorrection::Lcd),
+            "gba" => Ok(ColorCorrection::Gba),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown colour correction {}, expected one of: raw, lcd, gba",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+/// A 5 bit channel scaled to 8 bits, so 0x1f becomes 0xff
+fn expand(value: u32) -> u8 {
+    (value << 3 | value >> 2) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dmg_palette() {
+        assert_eq!(DmgPalette::Grey.rgba(0), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(DmgPalette::Grey.rgba(1), [0xaa, 0xaa, 0xaa, 0xff]);
+        assert_eq!(DmgPalette::Green.rgba(3), [0x0f, 0x38, 0x0f, 0xff]);
+        assert_eq!(DmgPalette::Pocket.rgba(0), [0xc4, 0xcf, 0xa1, 0xff]);
+    }
+
+    #[test]
+    fn test_parse_dmg_palette() {
+        assert_eq!("green".parse::<DmgPalette>().unwrap(), DmgPalette::Green);
+        assert_eq!("Pocket".parse::<DmgPalette>().unwrap(), DmgPalette::Pocket);
+
+        let custom = "e0f8d0,88c070,346856,081820".parse::<DmgPalette>().unwrap();
+        assert_eq!(
+            custom,
+            DmgPalette::Custom([0xe0f8d0, 0x88c070, 0x346856, 0x081820])
+        );
+        assert_eq!(custom.rgba(2), [0x34, 0x68, 0x56, 0xff]);
+
+        assert!("blue".parse::<DmgPalette>().is_err());
+        assert!("e0f8d0,88c070,346856".parse::<DmgPalette>().is_err());
+        assert!("e0f8d0,88c070,346856,0818zz".parse::<DmgPalette>().is_err());
+        assert!("e0f8d0,88c070,346856,+81820".parse::<DmgPalette>().is_err());
+    }
+
+    #[test]
+    fn test_color_correction() {
+        let raw = ColorCorrection::Raw;
+        assert_eq!(raw.rgba(0x7fff), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(raw.rgba(0x001f), [0xff, 0x00, 0x00, 0xff]);
+        assert_eq!(raw.rgba(0x0210), [0x84, 0x84, 0x00, 0xff]);
+
+        // White is capped, and pure red bleeds into blue
+        let lcd = ColorCorrection::Lcd;
+        assert_eq!(lcd.rgba(0x7fff), [0xf0, 0xf0, 0xf0, 0xff]);
+        assert_eq!(lcd.rgba(0x001f), [0xc9, 0x00, 0x2e, 0xff]);
+
+        let gba = ColorCorrection::Gba;
+        assert_eq!(gba.rgba(0x7fff), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(gba.rgba(0x0000), [0x00, 0x00, 0x00, 0xff]);
+        assert_eq!(gba.rgba(0x0010), [0x43, 0x00, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn test_parse_color_correction() {
+        assert_eq!(
+            "lcd".parse::<ColorCorrection>().unwrap(),
+            ColorCorrection::Lcd
+        );
+        assert!("vivid".parse::<ColorCorrection>().is_err());
+    }
+}
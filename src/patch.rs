@@ -0,0 +1,541 @@
+use std::path::{Path, PathBuf};
+
+use errors::{Error, ErrorKind, Result};
+use hash::crc32;
+use u8_to_u16;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+// UPS and BPS patches end with the CRC-32 of the source, the target and the
+// patch itself
+const FOOTER_SIZE: usize = 12;
+
+// The largest Game Boy rom. A patch asking for a bigger one is broken, and
+// would make us allocate whatever size it says.
+const MAX_TARGET_SIZE: usize = 8 * 1024 * 1024;
+
+// BPS actions
+const BPS_SOURCE_READ: usize = 0;
+const BPS_TARGET_READ: usize = 1;
+const BPS_SOURCE_COPY: usize = 2;
+const BPS_TARGET_COPY: usize = 3;
+
+/// Extensions of the patches picked up next to a rom, in order of preference
+const EXTENSIONS: [&str; 3] = ["bps", "ups", "ips"];
+
+/// Patch with the same name as the rom, if there is one
+pub fn find_sibling(rom_name: &str) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|extension| Path::new(rom_name).with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/// Apply an IPS, UPS or BPS patch to the rom, detecting the format from the
+/// patch contents
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Unknown patch format, expected IPS, UPS or BPS",
+        ))
+    }
+}
+
+/// IPS patches are a list of records, each replacing a range of the rom
+/// with either the given bytes or a run of a single byte.
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut target = rom.to_vec();
+    let mut reader = Reader::new(&patch[IPS_MAGIC.len()..]);
+
+    loop {
+        let offset = reader.bytes(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = (offset[0] as usize) << 16 | (offset[1] as usize) << 8 | offset[2] as usize;
+
+        let size = reader.u16()? as usize;
+        let (size, run) = if size == 0 {
+            (reader.u16()? as usize, Some(reader.byte()?))
+        } else {
+            (size, None)
+        };
+
+        if target.len() < offset + size {
+            target.resize(offset + size, 0);
+        }
+        match run {
+            Some(value) => {
+                for byte in &mut target[offset..offset + size] {
+                    *byte = value;
+                }
+            }
+            None => target[offset..offset + size].copy_from_slice(reader.bytes(size)?),
+        }
+    }
+
+    // Some patches append the size the rom should be truncated to
+    if let Ok(size) = reader.bytes(3) {
+        let size = (size[0] as usize) << 16 | (size[1] as usize) << 8 | size[2] as usize;
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+/// UPS patches store the XOR of the source and target, skipping over the
+/// bytes they have in common.
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (body, footer) = split_footer(patch, UPS_MAGIC)?;
+    footer.check_source(rom)?;
+
+    let mut reader = Reader::new(body);
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    check_source_size(rom, source_size)?;
+    check_target_size(target_size)?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+
+    let mut offset: usize = 0;
+    while !reader.is_empty() {
+        offset = offset
+            .checked_add(reader.varint()?)
+            .filter(|&offset| offset <= target_size)
+            .ok_or_else(malformed)?;
+        loop {
+            let xor = reader.byte()?;
+            if xor == 0 {
+                offset += 1;
+                break;
+            }
+            if offset < target_size {
+                target[offset] ^= xor;
+            }
+            offset += 1;
+        }
+    }
+
+    footer.check_target(&target)?;
+    Ok(target)
+}
+
+/// BPS patches build the target from a list of actions that copy data
+/// from the source, the patch, or the part of the target already built.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let (body, footer) = split_footer(patch, BPS_MAGIC)?;
+    footer.check_source(rom)?;
+
+    let mut reader = Reader::new(body);
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    check_source_size(rom, source_size)?;
+    check_target_size(target_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while !reader.is_empty() {
+        let action = reader.varint()?;
+        let length = (action >> 2) + 1;
+        let start = target.len();
+        if start + length > target_size {
+            return Err(malformed());
+        }
+
+        match action & 3 {
+            BPS_SOURCE_READ => target.extend_from_slice(slice(rom, start, length)?),
+            BPS_TARGET_READ => target.extend_from_slice(reader.bytes(length)?),
+            BPS_SOURCE_COPY => {
+                source_offset = relative_offset(source_offset, reader.varint()?)?;
+                target.extend_from_slice(slice(rom, source_offset, length)?);
+                source_offset = source_offset.checked_add(length).ok_or_else(malformed)?;
+            }
+            BPS_TARGET_COPY => {
+                target_offset = relative_offset(target_offset, reader.varint()?)?;
+                // The copy may overlap the bytes it writes, repeating them
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(malformed)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(malformed());
+    }
+
+    footer.check_target(&target)?;
+    Ok(target)
+}
+
+struct Footer {
+    source_crc: u32,
+    target_crc: u32,
+}
+
+impl Footer {
+    fn check_source(&self, rom: &[u8]) -> Result<()> {
+        check_crc("source rom", self.source_crc, rom)
+    }
+
+    fn check_target(&self, rom: &[u8]) -> Result<()> {
+        check_crc("patched rom", self.target_crc, rom)
+    }
+}
+
+/// Split a UPS or BPS patch into the part after the magic and the footer,
+/// after checking the patch isn't corrupted.
+fn split_footer<'a>(patch: &'a [u8], magic: &[u8]) -> Result<(&'a [u8], Footer)> {
+    if patch.len() < magic.len() + FOOTER_SIZE {
+        return Err(malformed());
+    }
+
+    let (body, footer) = patch.split_at(patch.len() - FOOTER_SIZE);
+    check_crc(
+        "patch",
+        read_u32_le(&footer[8..12]),
+        &patch[..patch.len() - 4],
+    )?;
+
+    Ok((
+        &body[magic.len()..],
+        Footer {
+            source_crc: read_u32_le(&footer[0..4]),
+            target_crc: read_u32_le(&footer[4..8]),
+        },
+    ))
+}
+
+fn check_crc(name: &str, expected: u32, data: &[u8]) -> Result<()> {
+    let actual = crc32(data);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::Validation,
+            format!(
+                "Checksum of the {} doesn't match the patch: expected {:08x}, got {:08x}",
+                name, expected, actual
+            ),
+        ))
+    }
+}
+
+fn check_source_size(rom: &[u8], expected: usize) -> Result<()> {
+    if rom.len() == expected {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::Validation,
+            format!(
+                "Size of the source rom doesn't match the patch: expected {}, got {}",
+                expected,
+                rom.len()
+            ),
+        ))
+    }
+}
+
+fn check_target_size(size: usize) -> Result<()> {
+    if size <= MAX_TARGET_SIZE {
+        Ok(())
+    } else {
+        Err(malformed())
+    }
+}
+
+fn read_u32_le(data: &[u8]) -> u32 {
+    data.iter()
+        .rev()
+        .fold(0, |n, &byte| (n << 8) | u32::from(byte))
+}
+
+/// BPS copy offsets are stored as a sign bit followed by the distance
+fn relative_offset(offset: usize, data: usize) -> Result<usize> {
+    let distance = data >> 1;
+    if data & 1 == 1 {
+        offset.checked_sub(distance).ok_or_else(malformed)
+    } else {
+        offset.checked_add(distance).ok_or_else(malformed)
+    }
+}
+
+fn slice(data: &[u8], begin: usize, length: usize) -> Result<&[u8]> {
+    begin
+        .checked_add(length)
+        .and_then(|end| data.get(begin..end))
+        .ok_or_else(malformed)
+}
+
+fn malformed() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Malformed patch")
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.data.len() < n {
+            return Err(malformed());
+        }
+        let (bytes, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Big endian, as used by IPS
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u8_to_u16(bytes[0], bytes[1]))
+    }
+
+    /// Variable length number used by UPS and BPS. Each byte holds 7 bits,
+    /// and the last one has its top bit set.
+    fn varint(&mut self) -> Result<usize> {
+        let mut n: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.byte()?;
+            n = ((byte & 0x7f) as usize)
+                .checked_mul(shift)
+                .and_then(|bits| n.checked_add(bits))
+                .ok_or_else(malformed)?;
+            if byte & 0x80 != 0 {
+                return Ok(n);
+            }
+            shift = shift.checked_shl(7).ok_or_else(malformed)?;
+            n = n.checked_add(shift).ok_or_else(malformed)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn varint(mut n: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        loop {
+            let bits = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                data.push(bits | 0x80);
+                return data;
+            }
+            data.push(bits);
+            n -= 1;
+        }
+    }
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        for crc in &[crc32(source), crc32(target)] {
+            patch.extend_from_slice(&[
+                *crc as u8,
+                (crc >> 8) as u8,
+                (crc >> 16) as u8,
+                (crc >> 24) as u8,
+            ]);
+        }
+        let crc = crc32(&patch);
+        patch.extend_from_slice(&[
+            crc as u8,
+            (crc >> 8) as u8,
+            (crc >> 16) as u8,
+            (crc >> 24) as u8,
+        ]);
+        patch
+    }
+
+    fn ups(source: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        let xor = |i: usize| source.get(i).cloned().unwrap_or(0) ^ target[i];
+        let (mut i, mut last) = (0, 0);
+        while i < target.len() {
+            if xor(i) == 0 {
+                i += 1;
+                continue;
+            }
+            // Each hunk covers a run of changed bytes, and its terminator
+            // skips the unchanged byte after it
+            patch.extend(varint(i - last));
+            while i < target.len() && xor(i) != 0 {
+                patch.push(xor(i));
+                i += 1;
+            }
+            patch.push(0);
+            i += 1;
+            last = i;
+        }
+        with_footer(patch, source, target)
+    }
+
+    #[test]
+    fn test_varint() {
+        for &n in &[0, 1, 0x7f, 0x80, 0x4000, 0x12_3456] {
+            let data = varint(n);
+            assert_eq!(Reader::new(&data).varint().unwrap(), n);
+        }
+        assert!(Reader::new(&[0x00, 0x00]).varint().is_err());
+    }
+
+    #[test]
+    fn test_ips() {
+        let rom = vec![0; 8];
+        let mut patch = IPS_MAGIC.to_vec();
+        // Two bytes at 0x000002
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xaa, 0xbb]);
+        // A run of four 0xcc at 0x000006, growing the rom
+        patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0xcc]);
+        patch.extend_from_slice(IPS_EOF);
+
+        assert_eq!(
+            apply(&rom, &patch).unwrap(),
+            vec![0, 0, 0xaa, 0xbb, 0, 0, 0xcc, 0xcc, 0xcc, 0xcc]
+        );
+
+        // Truncated to 3 bytes
+        patch.extend_from_slice(&[0x00, 0x00, 0x03]);
+        assert_eq!(apply(&rom, &patch).unwrap(), vec![0, 0, 0xaa]);
+
+        // Missing EOF marker
+        let patch = &patch[..patch.len() - 6];
+        assert!(apply(&rom, patch).is_err());
+    }
+
+    #[test]
+    fn test_ups() {
+        let source = b"rustyboy rom".to_vec();
+        let target = b"RustyBoy rom, patched".to_vec();
+        assert_eq!(apply(&source, &ups(&source, &target)).unwrap(), target);
+
+        // Shrinking the rom
+        assert_eq!(apply(&target, &ups(&target, &source)).unwrap(), source);
+    }
+
+    #[test]
+    fn test_ups_wrong_source() {
+        let source = b"rustyboy rom".to_vec();
+        let target = b"RustyBoy rom".to_vec();
+        let patch = ups(&source, &target);
+
+        let error = apply(b"another rom!", &patch).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::Validation));
+    }
+
+    #[test]
+    fn test_corrupted_patch() {
+        let source = b"rustyboy rom".to_vec();
+        let target = b"RustyBoy rom".to_vec();
+        let mut patch = ups(&source, &target);
+        patch[6] ^= 0xff;
+
+        let error = apply(&source, &patch).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::Validation));
+    }
+
+    #[test]
+    fn test_bps() {
+        let source = b"abcdefgh".to_vec();
+        let target = b"abcdXYXYXYefgh".to_vec();
+
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(0));
+        // Source read "abcd"
+        patch.extend(varint((3 << 2) | BPS_SOURCE_READ));
+        // Target read "XY"
+        patch.extend(varint((1 << 2) | BPS_TARGET_READ));
+        patch.extend_from_slice(b"XY");
+        // Target copy "XYXY" from offset 4, overlapping itself
+        patch.extend(varint((3 << 2) | BPS_TARGET_COPY));
+        patch.extend(varint(4 << 1));
+        // Source copy "efgh" from offset 4
+        patch.extend(varint((3 << 2) | BPS_SOURCE_COPY));
+        patch.extend(varint(4 << 1));
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+
+        let error = apply(b"abcdefgX", &patch).unwrap_err();
+        assert!(matches!(*error.kind(), ErrorKind::Validation));
+    }
+
+    #[test]
+    fn test_target_too_big() {
+        let source = b"rustyboy rom".to_vec();
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(usize::MAX));
+        let patch = with_footer(patch, &source, &source);
+        assert!(apply(&source, &patch).is_err());
+
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(MAX_TARGET_SIZE + 1));
+        patch.extend(varint(0));
+        let patch = with_footer(patch, &source, &source);
+        assert!(apply(&source, &patch).is_err());
+    }
+
+    #[test]
+    fn test_offset_overflow() {
+        let source = b"rustyboy rom".to_vec();
+        let mut patch = UPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(source.len()));
+        patch.extend(varint(usize::MAX));
+        patch.extend_from_slice(&[0x01, 0x00]);
+        let patch = with_footer(patch, &source, &source);
+        assert!(apply(&source, &patch).is_err());
+
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(source.len()));
+        patch.extend(varint(0));
+        // Source copy from as far as the offset goes
+        patch.extend(varint((3 << 2) | BPS_SOURCE_COPY));
+        patch.extend(varint(usize::MAX & !1));
+        let patch = with_footer(patch, &source, &source);
+        assert!(apply(&source, &patch).is_err());
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(apply(&[0; 8], b"NOT A PATCH").is_err());
+        assert!(apply(&[0; 8], b"UPS1").is_err());
+    }
+}
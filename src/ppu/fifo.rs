@@ -0,0 +1,418 @@
+//! Renderer modelling the pixel FIFOs and their fetchers dot by dot.
+//!
+//! The background fetcher fills the background FIFO 8 pixels at a time, and
+//! a pixel is shifted out to the LCD every dot the FIFO isn't empty. Objects
+//! stall the shifter while they are fetched into the object FIFO, where they
+//! are mixed with the background as the pixels are shifted out. Since
+//! registers are read as the pixels are fetched and shifted out, changes made
+//! while a line is drawn show up on the screen, and the length of the drawing
+//! mode follows from the fine scroll, the window and the objects.
+
+use std::collections::VecDeque;
+
+use super::oam::{self, Object};
+use super::pixel::{BgPixel, ObjectPixel};
+use super::tiles::*;
+use super::*;
+
+// The first fetch of every line is thrown away
+const FIRST_FETCH_DOTS: u32 = 6;
+// Every fetcher step but pushing takes 2 dots
+const FETCHER_STEP_DOTS: u32 = 2;
+const OBJECT_FETCH_DOTS: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+/// State of the FIFOs while drawing a line
+pub struct Fifo {
+    bg: VecDeque<BgPixel>,
+    objects: VecDeque<ObjectPixel>,
+    /// Objects of the line still to be fetched, by X
+    pending: VecDeque<Object>,
+    /// Dots spent fetching the current object, if the shifter is stalled
+    object_fetch: Option<u32>,
+
+    step: FetcherStep,
+    step_dots: u32,
+    /// Dots left before the fetcher starts
+    delay: u32,
+    /// Tile column of the next fetch
+    fetcher_x: u8,
+    tile: u8,
+    attributes: u8,
+    row: usize,
+    low: u8,
+    high: u8,
+
+    /// Pixels left to throw away, for the fine scroll or a window starting
+    /// left of the screen
+    discard: u8,
+    /// Screen column of the next pixel
+    x: usize,
+    window: bool,
+}
+
+impl Fifo {
+    pub fn new() -> Fifo {
+        Fifo {
+            bg: VecDeque::new(),
+            objects: VecDeque::new(),
+            pending: VecDeque::new(),
+            object_fetch: None,
+            step: FetcherStep::Tile,
+            step_dots: 0,
+            delay: 0,
+            fetcher_x: 0,
+            tile: 0,
+            attributes: 0,
+            row: 0,
+            low: 0,
+            high: 0,
+            discard: 0,
+            x: 0,
+            window: false,
+        }
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.bg.clear();
+        self.step = FetcherStep::Tile;
+        self.step_dots = 0;
+        self.fetcher_x = 0;
+    }
+}
+
+impl Ppu {
+    /// Prepare the FIFOs for drawing the current line
+    pub(super) fn start_fifo(&mut self, cpu: &Cpu) {
+        let mut pending = self.objects.clone();
+        pending.sort_by_key(|object| object.x);
+
+        self.fifo = Fifo::new();
+        self.fifo.pending = pending.into_iter().collect();
+        self.fifo.delay = FIRST_FETCH_DOTS;
+        self.fifo.discard = cpu.get_mem(SCX) % 8;
+    }
+
+    /// Run the FIFOs for a dot, returning whether the line is complete
+    pub(super) fn step_fifo(&mut self, cpu: &Cpu) -> bool {
+        let lcdc = cpu.get_mem(LCDC);
+
+        if !self.fifo.window && self.window_starts(cpu, lcdc) {
+            self.fifo.window = true;
+            self.fifo.restart_fetcher();
+            // The window isn't scrolled by SCX, but the columns it has left
+            // of the screen when WX is below 7 are thrown away the same way
+            let wx = i32::from(cpu.get_mem(WX));
+            self.fifo.discard = (WINDOW_X_OFFSET - wx).max(0) as u8;
+        }
+
+        if self.fifo.object_fetch.is_none()
+            && self.fifo.discard == 0
+            && lcdc & LCDC_OBJECT_ENABLE != 0
+            && self
+                .fifo
+                .pending
+                .front()
+                .is_some_and(|object| object.left() <= self.fifo.x as i32)
+        {
+            self.fifo.object_fetch = Some(0);
+        }
+
+        if let Some(dots) = self.fifo.object_fetch {
+            // The background fetcher finishes its tile before the object
+            // is fetched
+            if self.fifo.step != FetcherStep::Push || self.fifo.bg.is_empty() {
+                self.step_fetcher(cpu, lcdc);
+            } else if dots + 1 == OBJECT_FETCH_DOTS {
+                self.fetch_object(cpu, lcdc);
+                self.fifo.object_fetch = None;
+            } else {
+                self.fifo.object_fetch = Some(dots + 1);
+            }
+            return false;
+        }
+
+        self.step_fetcher(cpu, lcdc);
+        self.shift_pixel(cpu)
+    }
+
+    fn window_starts(&self, cpu: &Cpu, lcdc: u8) -> bool {
+        let wx = cpu.get_mem(WX);
+        (cpu.cgb_mode() || lcdc & LCDC_BG_ENABLE != 0)
+            && lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_triggered
+            && wx <= WINDOW_X_MAX
+            && self.fifo.x as i32 >= i32::from(wx) - WINDOW_X_OFFSET
+    }
+
+    fn step_fetcher(&mut self, cpu: &Cpu, lcdc: u8) {
+        if self.fifo.delay > 0 {
+            self.fifo.delay -= 1;
+            return;
+        }
+
+        if self.fifo.step == FetcherStep::Push {
+            if self.fifo.bg.is_empty() {
+                for bit in (0..8).rev() {
+                    let color = color_index(self.fifo.low, self.fifo.high, bit);
+                    let pixel = BgPixel::new(color, self.fifo.attributes);
+                    self.fifo.bg.push_back(pixel);
+                }
+                self.fifo.fetcher_x = self.fifo.fetcher_x.wrapping_add(1);
+                self.fifo.step = FetcherStep::Tile;
+            }
+            return;
+        }
+
+        self.fifo.step_dots += 1;
+        if self.fifo.step_dots < FETCHER_STEP_DOTS {
+            return;
+        }
+        self.fifo.step_dots = 0;
+
+        self.fifo.step = match self.fifo.step {
+            FetcherStep::Tile => {
+                let (map, x, y) = if self.fifo.window {
+                    (
+                        tilemap(lcdc, LCDC_WINDOW_TILEMAP),
+                        self.fifo.fetcher_x,
+                        self.window_line,
+                    )
+                } else {
+                    (
+                        tilemap(lcdc, LCDC_BG_TILEMAP),
+                        (cpu.get_mem(SCX) / 8).wrapping_add(self.fifo.fetcher_x),
+                        self.ly.wrapping_add(cpu.get_mem(SCY)),
+                    )
+                };
+                let column = usize::from(x) % TILEMAP_WIDTH;
+                let line = usize::from(y) / 8;
+                let addr = map + line * TILEMAP_WIDTH + column;
+                self.fifo.tile = cpu.get_mem(addr);
+                self.fifo.attributes = tile_attributes(cpu, addr);
+                self.fifo.row = usize::from(y) % 8;
+                FetcherStep::DataLow
+            }
+            FetcherStep::DataLow => {
+                self.fifo.low = self.fetch_tile_row(cpu, lcdc).0;
+                FetcherStep::DataHigh
+            }
+            FetcherStep::DataHigh => {
+                self.fifo.high = self.fetch_tile_row(cpu, lcdc).1;
+                FetcherStep::Push
+            }
+            FetcherStep::Push => FetcherStep::Push,
+        };
+    }
+
+    fn fetch_tile_row(&self, cpu: &Cpu, lcdc: u8) -> (u8, u8) {
+        let fifo = &self.fifo;
+        tile_row(cpu, lcdc, fifo.tile, fifo.attributes, fifo.row)
+    }
+
+    /// Mix the next object into the object FIFO. Pixels already in the FIFO
+    /// come from objects with a lower X, or earlier in the OAM, so they are
+    /// only replaced where they are transparent. On the CGB, they are also
+    /// replaced by objects earlier in the OAM.
+    fn fetch_object(&mut self, cpu: &Cpu, lcdc: u8) {
+        let object = match self.fifo.pending.pop_front() {
+            Some(object) => object,
+            None => return,
+        };
+        let (low, high) = object.row(cpu, self.ly, oam::object_height(lcdc));
+        let by_index = oam::priority_by_index(cpu);
+
+        for column in 0..8 {
+            let x = object.left() + column;
+            if x < self.fifo.x as i32 {
+                continue;
+            }
+
+            let pixel = object.pixel(cpu, color_index(low, high, 7 - column as u8));
+
+            let slot = x as usize - self.fifo.x;
+            match self.fifo.objects.get_mut(slot) {
+                Some(existing) => {
+                    if existing.color == 0
+                        || (by_index && pixel.color != 0 && pixel.index < existing.index)
+                    {
+                        *existing = pixel;
+                    }
+                }
+                None => self.fifo.objects.push_back(pixel),
+            }
+        }
+    }
+
+    /// Shift a pixel out to the LCD, returning whether it was the last one of
+    /// the line
+    fn shift_pixel(&mut self, cpu: &Cpu) -> bool {
+        let bg = match self.fifo.bg.pop_front() {
+            Some(pixel) => pixel,
+            None => return false,
+        };
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+        let object = self.fifo.objects.pop_front();
+
+        let x = self.fifo.x;
+        self.draw_pixel(cpu, x, bg, object);
+        self.fifo.x += 1;
+
+        if self.fifo.x < SCREEN_WIDTH {
+            return false;
+        }
+        if self.fifo.window {
+            self.window_line += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::oam::{ATTRIBUTE_BG_PRIORITY, ATTRIBUTE_PALETTE};
+    use super::*;
+    use ppu::test::{render, write_object};
+    use registers::{BCPD, BCPS, OCPD, OCPS};
+
+    fn cpu_with_tiles() -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, 0x93);
+        cpu.set_mem(BGP, 0b1110_0100);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        cpu.set_mem(OBP1, 0b0001_1011);
+
+        // Tile 1 has colours 3, 1, 1, 1, 2, 2, 0, 0 in every row, tile 2
+        // has colour 2 everywhere
+        for i in 0..8 {
+            cpu.set_mem(0x8010 + i * 2, 0b1111_0000);
+            cpu.set_mem(0x8011 + i * 2, 0b1000_1100);
+            cpu.set_mem(0x8021 + i * 2, 0xff);
+        }
+        for i in 0..0x400 {
+            cpu.set_mem(TILEMAP_LOW + i, (i % 3) as u8);
+            cpu.set_mem(TILEMAP_HIGH + i, 0x01);
+        }
+        cpu
+    }
+
+    /// Dots spent drawing line 0
+    fn drawing_dots(cpu: &mut Cpu) -> u32 {
+        let mut ppu = Ppu::new(Renderer::Fifo);
+        ppu.step(cpu, OAM_SCAN_DOTS + 1);
+        let mut dots = 1;
+        while ppu.mode == Mode::Drawing {
+            ppu.step(cpu, 1);
+            dots += 1;
+        }
+        dots - 1
+    }
+
+    fn assert_same_frame(cpu: &mut Cpu) {
+        let scanline = render(cpu, Renderer::Scanline, 10);
+        let fifo = render(cpu, Renderer::Fifo, 10);
+        let pixels = 10 * SCREEN_WIDTH;
+        assert_eq!(&scanline.framebuffer[..pixels], &fifo.framebuffer[..pixels]);
+    }
+
+    #[test]
+    fn test_matches_scanline_renderer() {
+        let mut cpu = cpu_with_tiles();
+        assert_same_frame(&mut cpu);
+
+        cpu.set_mem(SCX, 0x0d);
+        cpu.set_mem(SCY, 0x03);
+        assert_same_frame(&mut cpu);
+
+        cpu.set_mem(LCDC, 0xf3);
+        cpu.set_mem(WY, 2);
+        cpu.set_mem(WX, 7 + 37);
+        assert_same_frame(&mut cpu);
+
+        // A window starting at the left edge isn't scrolled by SCX, and
+        // below 7 its first columns are cut off instead
+        cpu.set_mem(SCX, 0x03);
+        cpu.set_mem(WX, 7);
+        assert_same_frame(&mut cpu);
+
+        cpu.set_mem(SCX, 0);
+        cpu.set_mem(WX, 3);
+        assert_same_frame(&mut cpu);
+    }
+
+    #[test]
+    fn test_objects_match_scanline_renderer() {
+        let mut cpu = cpu_with_tiles();
+        cpu.set_mem(SCX, 0x05);
+        write_object(&mut cpu, 0, 16, 3, 0x01, 0);
+        write_object(&mut cpu, 1, 16, 20, 0x02, ATTRIBUTE_PALETTE);
+        write_object(&mut cpu, 2, 18, 20, 0x01, 0);
+        write_object(&mut cpu, 3, 16, 26, 0x01, 0b0010_0000);
+        write_object(&mut cpu, 4, 17, 40, 0x02, ATTRIBUTE_BG_PRIORITY);
+        write_object(&mut cpu, 5, 16, 164, 0x01, 0);
+        assert_same_frame(&mut cpu);
+    }
+
+    #[test]
+    fn test_cgb_matches_scanline_renderer() {
+        let mut cpu = cpu_with_tiles();
+        cpu.set_cgb_mode(true);
+        for i in 0..0x40u8 {
+            cpu.write_io(BCPS, i);
+            cpu.write_io(BCPD, i.wrapping_mul(3));
+            cpu.write_io(OCPS, i);
+            cpu.write_io(OCPD, i.wrapping_mul(5));
+        }
+        for i in 0..0x400 {
+            cpu.set_vram(1, TILEMAP_LOW + i, (i * 0x25) as u8 & 0b1110_0111);
+        }
+        cpu.set_mem(SCX, 0x05);
+        write_object(&mut cpu, 0, 16, 20, 0x02, 0x03);
+        write_object(&mut cpu, 1, 16, 18, 0x01, ATTRIBUTE_BG_PRIORITY | 0x05);
+        write_object(&mut cpu, 2, 17, 40, 0x01, 0x26);
+        assert_same_frame(&mut cpu);
+
+        let scanline = render(&mut cpu, Renderer::Scanline, 10);
+        let fifo = render(&mut cpu, Renderer::Fifo, 10);
+        let pixels = 10 * SCREEN_WIDTH;
+        assert_eq!(&scanline.colors[..pixels], &fifo.colors[..pixels]);
+    }
+
+    #[test]
+    fn test_drawing_length() {
+        let mut cpu = cpu_with_tiles();
+        assert_eq!(drawing_dots(&mut cpu), DRAWING_DOTS);
+
+        cpu.set_mem(SCX, 0x03);
+        assert_eq!(drawing_dots(&mut cpu), DRAWING_DOTS + 3);
+
+        // Objects stall the shifter
+        cpu.set_mem(SCX, 0x00);
+        write_object(&mut cpu, 0, 16, 0, 0x01, 0);
+        assert!(drawing_dots(&mut cpu) > DRAWING_DOTS + 6);
+    }
+
+    #[test]
+    fn test_palette_change_while_drawing() {
+        let mut cpu = cpu_with_tiles();
+        let mut ppu = Ppu::new(Renderer::Fifo);
+
+        ppu.step(&mut cpu, OAM_SCAN_DOTS + 12 + 80);
+        cpu.set_mem(BGP, 0b0001_1011);
+        ppu.step(&mut cpu, DOTS_PER_LINE);
+
+        // Tile 0 is blank, so the first columns take colour 0 of each palette
+        assert_eq!(ppu.framebuffer[0], 0);
+        assert_eq!(ppu.framebuffer[SCREEN_WIDTH - 2], 3);
+    }
+}
@@ -0,0 +1,568 @@
+mod fifo;
+mod oam;
+mod pixel;
+mod scanline;
+mod tiles;
+pub mod viewer;
+
+use std::str::FromStr;
+
+use self::fifo::Fifo;
+use self::oam::Object;
+use cpu::Cpu;
+use errors::{Error, ErrorKind, Result};
+use registers::{BGP, IF, LCDC, LY, LYC, OBP0, OBP1, OPRI, SCX, SCY, STAT, WX, WY};
+
+const LCDC_ENABLE: u8 = 0b1000_0000;
+const LCDC_WINDOW_TILEMAP: u8 = 0b0100_0000;
+const LCDC_WINDOW_ENABLE: u8 = 0b0010_0000;
+const LCDC_TILE_DATA: u8 = 0b0001_0000;
+const LCDC_BG_TILEMAP: u8 = 0b0000_1000;
+const LCDC_OBJECT_SIZE: u8 = 0b0000_0100;
+const LCDC_OBJECT_ENABLE: u8 = 0b0000_0010;
+const LCDC_BG_ENABLE: u8 = 0b0000_0001;
+
+const STAT_MODE: u8 = 0b0000_0011;
+const STAT_COINCIDENCE: u8 = 0b0000_0100;
+const STAT_HBLANK_INTERRUPT: u8 = 0b0000_1000;
+const STAT_VBLANK_INTERRUPT: u8 = 0b0001_0000;
+const STAT_OAM_SCAN_INTERRUPT: u8 = 0b0010_0000;
+const STAT_COINCIDENCE_INTERRUPT: u8 = 0b0100_0000;
+
+const OPRI_X_PRIORITY: u8 = 0b0000_0001;
+
+const INTERRUPT_VBLANK: u8 = 0b0000_0001;
+const INTERRUPT_STAT: u8 = 0b0000_0010;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// 15 bit colour of the blank CGB screen
+const WHITE: u16 = 0x7fff;
+
+//
+// Timings, in dots
+//
+
+const DOTS_PER_LINE: u32 = 456;
+const LINES: u8 = 154;
+const DOTS_PER_FRAME: u32 = DOTS_PER_LINE * LINES as u32;
+const VISIBLE_LINES: u8 = 144;
+
+const OAM_SCAN_DOTS: u32 = 80;
+const DRAWING_DOTS: u32 = 172;
+// Each OAM row holds two objects, and every object takes 2 dots to scan
+const DOTS_PER_OAM_ROW: u32 = 4;
+
+/// How the PPU draws the lines of a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Renderer {
+    /// Draw every line at once as it starts being drawn. Fast, but changes
+    /// made while a line is drawn only show up on the next one.
+    Scanline,
+    /// Model the pixel FIFOs and their fetchers dot by dot
+    Fifo,
+}
+
+impl FromStr for Renderer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Renderer> {
+        match s {
+            "scanline" => Ok(Renderer::Scanline),
+            "fifo" => Ok(Renderer::Fifo),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown renderer {}, expected one of: scanline, fifo", s),
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// Picture processing unit.
+///
+/// Every line starts with an OAM scan, followed by drawing the line and an
+/// HBlank that fills the rest of its 456 dots. After the 144 visible lines
+/// come 10 lines of VBlank, for a total of 154 lines per frame.
+pub struct Ppu {
+    renderer: Renderer,
+    fifo: Fifo,
+    /// Dot of the current line, from 0 to 455
+    dot: u32,
+    ly: u8,
+    mode: Mode,
+    /// Dots spent drawing the current line, which grow with the fine scroll
+    /// and the objects on the line
+    drawing_dots: u32,
+    /// Objects found on the current line by the OAM scan
+    objects: Vec<Object>,
+    /// The STAT interrupt is requested when any of its enabled sources
+    /// becomes active, but not while another one is keeping the line high
+    stat_line: bool,
+    /// Whether LY matched WY at the start of a line during this frame
+    window_triggered: bool,
+    /// Line of the window to draw next. It only advances on lines where the
+    /// window is visible.
+    window_line: u8,
+    /// Shade of every pixel of the frame, 0 (lightest) to 3 (darkest). In
+    /// CGB mode, the colour index of the pixel in its palette instead.
+    framebuffer: Vec<u8>,
+    /// 15 bit colour of every pixel of the frame, in CGB mode
+    colors: Vec<u16>,
+    /// Whether the frame is drawn in CGB mode
+    cgb: bool,
+    /// Frames completed so far
+    frames: u64,
+    /// Whether the LCD was on during the last dot
+    lcd_on: bool,
+    /// Dots since the LCD was turned off or the last blank frame. The
+    /// frames keep coming at the same rate while the LCD is off.
+    off_dots: u32,
+    /// Whether this is the first frame since the LCD was turned on, which
+    /// the LCD doesn't show
+    first_frame: bool,
+    /// Whether this is the first line since the LCD was turned on, which
+    /// starts without an OAM scan
+    first_line: bool,
+}
+
+impl Ppu {
+    pub fn new(renderer: Renderer) -> Ppu {
+        Ppu {
+            renderer,
+            fifo: Fifo::new(),
+            dot: 0,
+            ly: 0,
+            mode: Mode::OamScan,
+            drawing_dots: DRAWING_DOTS,
+            objects: Vec::new(),
+            stat_line: false,
+            window_triggered: false,
+            window_line: 0,
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            colors: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            cgb: false,
+            frames: 0,
+            lcd_on: true,
+            off_dots: 0,
+            first_frame: false,
+            first_line: false,
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    /// The 15 bit colours of the frame, in CGB mode
+    pub fn colors(&self) -> Option<&[u16]> {
+        if self.cgb {
+            Some(&self.colors)
+        } else {
+            None
+        }
+    }
+
+    /// Advance the PPU by `dots` dots, the time taken by the last CPU
+    /// instruction
+    pub fn step(&mut self, cpu: &mut Cpu, dots: u32) {
+        for _ in 0..dots {
+            self.step_dot(cpu);
+        }
+    }
+
+    fn step_dot(&mut self, cpu: &mut Cpu) {
+        if cpu.get_mem(LCDC) & LCDC_ENABLE == 0 {
+            self.disable(cpu);
+            return;
+        }
+        if !self.lcd_on {
+            self.enable();
+        }
+
+        if self.dot == 0 {
+            self.start_line(cpu);
+        }
+
+        let mode = self.current_mode();
+        if mode != self.mode {
+            match mode {
+                Mode::VBlank => {
+                    if self.first_frame {
+                        self.first_frame = false;
+                        self.blank();
+                    }
+                    self.frames += 1;
+                    request_interrupt(cpu, INTERRUPT_VBLANK);
+                }
+                Mode::Drawing => match self.renderer {
+                    Renderer::Scanline => self.render_line(cpu),
+                    Renderer::Fifo => self.start_fifo(cpu),
+                },
+                _ => {}
+            }
+        }
+        self.mode = mode;
+
+        // The FIFO decides when the line is complete
+        if mode == Mode::Drawing && self.renderer == Renderer::Fifo && self.step_fifo(cpu) {
+            self.drawing_dots = self.dot + 1 - OAM_SCAN_DOTS;
+        }
+
+        if mode == Mode::OamScan {
+            cpu.set_oam_row((self.dot / DOTS_PER_OAM_ROW) as usize);
+        }
+
+        self.update_stat(cpu);
+
+        self.dot += 1;
+        if self.dot == DOTS_PER_LINE {
+            self.first_line = false;
+            self.dot = 0;
+            self.ly = (self.ly + 1) % LINES;
+        }
+    }
+
+    fn start_line(&mut self, cpu: &Cpu) {
+        if self.ly == 0 {
+            self.cgb = cpu.cgb_mode();
+            self.window_triggered = false;
+            self.window_line = 0;
+        }
+        if self.ly == cpu.get_mem(WY) {
+            self.window_triggered = true;
+        }
+        if self.ly < VISIBLE_LINES {
+            // The CPU can't access the OAM while it is scanned, so it can be
+            // scanned all at once
+            let lcdc = cpu.get_mem(LCDC);
+            self.objects = if lcdc & LCDC_OBJECT_ENABLE != 0 {
+                oam::scan(cpu, self.ly, oam::object_height(lcdc))
+            } else {
+                Vec::new()
+            };
+
+            let scx = cpu.get_mem(SCX);
+            self.drawing_dots = match self.renderer {
+                Renderer::Scanline => {
+                    DRAWING_DOTS + u32::from(scx % 8) + oam::penalty(&self.objects, scx)
+                }
+                Renderer::Fifo => DOTS_PER_LINE - OAM_SCAN_DOTS,
+            };
+        }
+    }
+
+    fn current_mode(&self) -> Mode {
+        if self.ly >= VISIBLE_LINES {
+            Mode::VBlank
+        } else if self.dot < OAM_SCAN_DOTS && self.first_line {
+            Mode::HBlank
+        } else if self.dot < OAM_SCAN_DOTS {
+            Mode::OamScan
+        } else if self.dot < OAM_SCAN_DOTS + self.drawing_dots {
+            Mode::Drawing
+        } else {
+            Mode::HBlank
+        }
+    }
+
+    /// Report LY, the mode and the coincidence flag, and request a STAT
+    /// interrupt on a rising edge of the STAT interrupt line
+    fn update_stat(&mut self, cpu: &mut Cpu) {
+        let coincidence = self.ly == cpu.get_mem(LYC);
+
+        let mut stat = (cpu.get_mem(STAT) & !(STAT_MODE | STAT_COINCIDENCE)) | self.mode as u8;
+        if coincidence {
+            stat |= STAT_COINCIDENCE;
+        }
+        cpu.set_mem(STAT, stat);
+        cpu.set_mem(LY, self.ly);
+
+        let source = match self.mode {
+            Mode::HBlank => STAT_HBLANK_INTERRUPT,
+            Mode::VBlank => STAT_VBLANK_INTERRUPT,
+            Mode::OamScan => STAT_OAM_SCAN_INTERRUPT,
+            Mode::Drawing => 0,
+        };
+        let line = stat & source != 0 || (coincidence && stat & STAT_COINCIDENCE_INTERRUPT != 0);
+
+        if line && !self.stat_line {
+            request_interrupt(cpu, INTERRUPT_STAT);
+        }
+        self.stat_line = line;
+    }
+
+    /// While the LCD is off, LY stays at 0, the PPU stays in HBlank and the
+    /// screen is blank. The CPU can access the VRAM and OAM freely.
+    fn disable(&mut self, cpu: &mut Cpu) {
+        if self.lcd_on {
+            self.lcd_on = false;
+            self.off_dots = 0;
+            self.blank();
+        }
+
+        // Blank frames keep coming, without VBlank interrupts
+        self.off_dots += 1;
+        if self.off_dots == DOTS_PER_FRAME {
+            self.off_dots = 0;
+            self.frames += 1;
+        }
+
+        self.dot = 0;
+        self.ly = 0;
+        self.mode = Mode::HBlank;
+        self.stat_line = false;
+
+        cpu.set_mem(STAT, cpu.get_mem(STAT) & !STAT_MODE);
+        cpu.set_mem(LY, 0);
+    }
+
+    /// Turning the LCD on starts a frame from line 0. The first line skips
+    /// the OAM scan, and the first frame isn't shown.
+    fn enable(&mut self) {
+        self.lcd_on = true;
+        self.first_frame = true;
+        self.first_line = true;
+        self.dot = 0;
+        self.ly = 0;
+    }
+
+    fn blank(&mut self) {
+        for shade in self.framebuffer.iter_mut() {
+            *shade = 0;
+        }
+        for color in self.colors.iter_mut() {
+            *color = WHITE;
+        }
+    }
+}
+
+fn request_interrupt(cpu: &mut Cpu, interrupt: u8) {
+    let flags = cpu.get_mem(IF);
+    cpu.set_mem(IF, flags | interrupt);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Put an object in OAM, the way a game would
+    pub fn write_object(cpu: &mut Cpu, index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+        let addr = 0xfe00 + index * 4;
+        cpu.set_mem_range(addr, addr + 3, &[y, x, tile, attributes]);
+    }
+
+    /// Draw the first `lines` lines of a frame with `renderer`
+    pub fn render(cpu: &mut Cpu, renderer: Renderer, lines: u32) -> Ppu {
+        let mut ppu = Ppu::new(renderer);
+        ppu.step(cpu, DOTS_PER_LINE * lines);
+        ppu
+    }
+
+    fn lcd_on() -> (Ppu, Cpu) {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, 0x91);
+        (Ppu::new(Renderer::Scanline), cpu)
+    }
+
+    #[test]
+    fn test_modes() {
+        let (mut ppu, mut cpu) = lcd_on();
+
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.mode, Mode::OamScan);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 2);
+
+        ppu.step(&mut cpu, OAM_SCAN_DOTS);
+        assert_eq!(ppu.mode, Mode::Drawing);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 3);
+
+        ppu.step(&mut cpu, DRAWING_DOTS);
+        assert_eq!(ppu.mode, Mode::HBlank);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 0);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE - OAM_SCAN_DOTS - DRAWING_DOTS);
+        assert_eq!(ppu.mode, Mode::OamScan);
+        assert_eq!(cpu.get_mem(LY), 1);
+    }
+
+    #[test]
+    fn test_fine_scroll_lengthens_drawing() {
+        let (mut ppu, mut cpu) = lcd_on();
+        cpu.set_mem(SCX, 0x13);
+
+        ppu.step(&mut cpu, OAM_SCAN_DOTS + DRAWING_DOTS + 1);
+        assert_eq!(ppu.mode, Mode::Drawing);
+        ppu.step(&mut cpu, 3);
+        assert_eq!(ppu.mode, Mode::HBlank);
+    }
+
+    #[test]
+    fn test_frame() {
+        let (mut ppu, mut cpu) = lcd_on();
+
+        ppu.step(&mut cpu, DOTS_PER_LINE * u32::from(VISIBLE_LINES));
+        assert_eq!(cpu.get_mem(IF) & INTERRUPT_VBLANK, 0);
+
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.ly, VISIBLE_LINES);
+        assert_eq!(ppu.mode, Mode::VBlank);
+        assert_eq!(cpu.get_mem(IF) & INTERRUPT_VBLANK, INTERRUPT_VBLANK);
+        assert_eq!(ppu.frames(), 1);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE * u32::from(LINES - VISIBLE_LINES));
+        assert_eq!(ppu.ly, 0);
+        assert_eq!(ppu.mode, Mode::OamScan);
+    }
+
+    #[test]
+    fn test_coincidence() {
+        let (mut ppu, mut cpu) = lcd_on();
+        cpu.set_mem(LYC, 2);
+        cpu.set_mem(STAT, STAT_COINCIDENCE_INTERRUPT);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE);
+        assert_eq!(cpu.get_mem(STAT) & STAT_COINCIDENCE, 0);
+        assert_eq!(cpu.get_mem(IF), 0);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE + 1);
+        assert_eq!(cpu.get_mem(STAT) & STAT_COINCIDENCE, STAT_COINCIDENCE);
+        assert_eq!(cpu.get_mem(IF), INTERRUPT_STAT);
+    }
+
+    #[test]
+    fn test_stat_blocking() {
+        let (mut ppu, mut cpu) = lcd_on();
+        cpu.set_mem(LYC, 0);
+        cpu.set_mem(STAT, STAT_COINCIDENCE_INTERRUPT | STAT_HBLANK_INTERRUPT);
+
+        ppu.step(&mut cpu, 1);
+        assert_eq!(cpu.get_mem(IF), INTERRUPT_STAT);
+
+        // The coincidence keeps the line high when HBlank starts
+        cpu.set_mem(IF, 0);
+        ppu.step(&mut cpu, DOTS_PER_LINE - 1);
+        assert_eq!(cpu.get_mem(IF), 0);
+
+        // On the next line HBlank raises it again
+        ppu.step(&mut cpu, OAM_SCAN_DOTS + DRAWING_DOTS + 1);
+        assert_eq!(cpu.get_mem(IF), INTERRUPT_STAT);
+    }
+
+    #[test]
+    fn test_oam_row() {
+        let (mut ppu, mut cpu) = lcd_on();
+        for i in 0..0x18 {
+            cpu.set_mem(0xfe00 + i, i as u8);
+        }
+        // ld hl, 0xfe40; inc hl
+        cpu.set_mem_range(0x0000, 0x0003, &[0x21, 0x40, 0xfe, 0x23]);
+        cpu.tick().unwrap();
+
+        // Incrementing HL while the PPU reads row 2 corrupts it with row 1
+        ppu.step(&mut cpu, 2 * DOTS_PER_OAM_ROW + 1);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.get_mem(0xfe12), 0x0a);
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, 0x91);
+        let mut ppu = Ppu::new(Renderer::Scanline);
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.colors(), None);
+
+        // Picked up at the start of the next frame
+        cpu.set_cgb_mode(true);
+        ppu.step(&mut cpu, DOTS_PER_LINE);
+        assert_eq!(ppu.colors(), None);
+        ppu.step(&mut cpu, DOTS_PER_LINE * u32::from(LINES - 1));
+        assert_eq!(
+            ppu.colors().map(|colors| colors.len()),
+            Some(SCREEN_WIDTH * SCREEN_HEIGHT)
+        );
+    }
+
+    #[test]
+    fn test_lcd_off() {
+        let (mut ppu, mut cpu) = lcd_on();
+        ppu.step(&mut cpu, DOTS_PER_LINE * 3 + 100);
+
+        cpu.set_mem(LCDC, 0x11);
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.ly, 0);
+        assert_eq!(cpu.get_mem(LY), 0);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 0);
+    }
+
+    #[test]
+    fn test_lcd_off_blanks_the_screen() {
+        let (mut ppu, mut cpu) = lcd_on();
+        ppu.framebuffer[0] = 3;
+        ppu.colors[0] = 0x001f;
+
+        cpu.set_mem(LCDC, 0x11);
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.framebuffer[0], 0);
+        assert_eq!(ppu.colors[0], WHITE);
+
+        // Blank frames keep coming at the usual rate, without interrupts
+        ppu.step(&mut cpu, DOTS_PER_FRAME - 2);
+        assert_eq!(ppu.frames(), 0);
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.frames(), 1);
+        assert_eq!(cpu.get_mem(IF), 0);
+    }
+
+    #[test]
+    fn test_lcd_on() {
+        let (mut ppu, mut cpu) = lcd_on();
+        cpu.set_mem(LCDC, 0x11);
+        ppu.step(&mut cpu, 1000);
+
+        // The first line starts in HBlank instead of the OAM scan
+        cpu.set_mem(LCDC, 0x91);
+        cpu.set_mem(STAT, STAT_OAM_SCAN_INTERRUPT);
+        ppu.step(&mut cpu, 1);
+        assert_eq!(ppu.ly, 0);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 0);
+        ppu.step(&mut cpu, OAM_SCAN_DOTS);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 3);
+        assert_eq!(cpu.get_mem(IF), 0);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE - OAM_SCAN_DOTS);
+        assert_eq!(ppu.ly, 1);
+        assert_eq!(cpu.get_mem(STAT) & STAT_MODE, 2);
+        assert_eq!(cpu.get_mem(IF), INTERRUPT_STAT);
+    }
+
+    #[test]
+    fn test_first_frame_not_shown() {
+        let (mut ppu, mut cpu) = lcd_on();
+        cpu.set_mem(BGP, 0xff);
+        cpu.set_mem(LCDC, 0x11);
+        ppu.step(&mut cpu, 1);
+        cpu.set_mem(LCDC, 0x91);
+
+        ppu.step(&mut cpu, DOTS_PER_LINE * u32::from(VISIBLE_LINES) + 1);
+        assert_eq!(ppu.frames(), 1);
+        assert!(ppu.framebuffer.iter().all(|&shade| shade == 0));
+
+        ppu.step(&mut cpu, DOTS_PER_FRAME);
+        assert_eq!(ppu.frames(), 2);
+        assert!(ppu.framebuffer.iter().all(|&shade| shade == 3));
+    }
+}
@@ -0,0 +1,263 @@
+//! Objects (sprites) and the OAM scan selecting the ones on a line.
+
+use super::pixel::ObjectPixel;
+use super::*;
+
+const OAM_BEGIN: usize = 0xfe00;
+pub const OAM_OBJECTS: usize = 40;
+const OBJECT_SIZE: usize = 4;
+const OBJECT_TILES: usize = 0x8000;
+
+// At most 10 objects are drawn on every line
+const OBJECTS_PER_LINE: usize = 10;
+
+// OAM coordinates are offset so objects can be partially off-screen at the
+// top and left of the screen
+pub const OBJECT_X_OFFSET: i32 = 8;
+const OBJECT_Y_OFFSET: i32 = 16;
+
+pub const ATTRIBUTE_BG_PRIORITY: u8 = 0b1000_0000;
+pub const ATTRIBUTE_Y_FLIP: u8 = 0b0100_0000;
+pub const ATTRIBUTE_X_FLIP: u8 = 0b0010_0000;
+pub const ATTRIBUTE_PALETTE: u8 = 0b0001_0000;
+pub const ATTRIBUTE_BANK: u8 = 0b0000_1000;
+const ATTRIBUTE_CGB_PALETTE: u8 = 0b0000_0111;
+
+// Dots the fetcher is stalled for to fetch an object
+const OBJECT_FETCH_DOTS: u32 = 6;
+const OBJECT_MAX_WAIT_DOTS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Object {
+    /// Position in the OAM, which breaks ties between objects at the same X
+    pub index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+}
+
+impl Object {
+    pub fn read(cpu: &Cpu, index: usize) -> Object {
+        let addr = OAM_BEGIN + index * OBJECT_SIZE;
+        Object {
+            index,
+            y: cpu.get_mem(addr),
+            x: cpu.get_mem(addr + 1),
+            tile: cpu.get_mem(addr + 2),
+            attributes: cpu.get_mem(addr + 3),
+        }
+    }
+
+    /// Leftmost screen column of the object
+    pub fn left(&self) -> i32 {
+        i32::from(self.x) - OBJECT_X_OFFSET
+    }
+
+    /// Both bytes of the object's row on line `ly`, flipped as needed so bit
+    /// 7 is always the leftmost pixel
+    pub fn row(&self, cpu: &Cpu, ly: u8, height: u8) -> (u8, u8) {
+        let row = (i32::from(ly) - i32::from(self.y) + OBJECT_Y_OFFSET) as u8;
+        self.tile_row(cpu, row, height)
+    }
+
+    /// Both bytes of row `row` of the object, flipped as needed
+    pub fn tile_row(&self, cpu: &Cpu, mut row: u8, height: u8) -> (u8, u8) {
+        if self.attributes & ATTRIBUTE_Y_FLIP != 0 {
+            row = height - 1 - row;
+        }
+
+        // Tall objects ignore the lowest bit of the tile number
+        let tile = if height == 16 {
+            self.tile & 0xfe
+        } else {
+            self.tile
+        };
+        let addr = OBJECT_TILES + usize::from(tile) * 16 + usize::from(row) * 2;
+        let bank = usize::from(cpu.cgb_mode() && self.attributes & ATTRIBUTE_BANK != 0);
+        let (low, high) = (cpu.get_vram(bank, addr), cpu.get_vram(bank, addr + 1));
+
+        if self.attributes & ATTRIBUTE_X_FLIP != 0 {
+            (low.reverse_bits(), high.reverse_bits())
+        } else {
+            (low, high)
+        }
+    }
+
+    /// A pixel of the object with colour index `color`
+    pub fn pixel(&self, cpu: &Cpu, color: u8) -> ObjectPixel {
+        let palette = if cpu.cgb_mode() {
+            self.attributes & ATTRIBUTE_CGB_PALETTE
+        } else {
+            u8::from(self.attributes & ATTRIBUTE_PALETTE != 0)
+        };
+        ObjectPixel {
+            color,
+            palette,
+            bg_priority: self.attributes & ATTRIBUTE_BG_PRIORITY != 0,
+            index: self.index,
+        }
+    }
+}
+
+/// Whether overlapping objects are ordered by their position in the OAM
+/// alone, as on the CGB, instead of by X first, as on the DMG
+pub fn priority_by_index(cpu: &Cpu) -> bool {
+    cpu.cgb_mode() && cpu.get_mem(OPRI) & OPRI_X_PRIORITY == 0
+}
+
+/// Height of objects selected by LCDC
+pub fn object_height(lcdc: u8) -> u8 {
+    if lcdc & LCDC_OBJECT_SIZE != 0 {
+        16
+    } else {
+        8
+    }
+}
+
+/// The first 10 objects in OAM order that overlap line `ly`. Objects that
+/// are off-screen horizontally still count towards the limit.
+pub fn scan(cpu: &Cpu, ly: u8, height: u8) -> Vec<Object> {
+    let line = i32::from(ly) + OBJECT_Y_OFFSET;
+
+    (0..OAM_OBJECTS)
+        .map(|index| Object::read(cpu, index))
+        .filter(|object| {
+            let top = i32::from(object.y);
+            line >= top && line < top + i32::from(height)
+        })
+        .take(OBJECTS_PER_LINE)
+        .collect()
+}
+
+/// Dots the objects of a line add to the drawing mode. Every object stalls
+/// the fetcher, and the first object on every background tile also waits for
+/// the tile to be fetched.
+pub fn penalty(objects: &[Object], scx: u8) -> u32 {
+    let mut tiles = Vec::new();
+    let mut dots = 0;
+
+    for object in objects {
+        if object.left() >= SCREEN_WIDTH as i32 {
+            continue;
+        }
+        dots += OBJECT_FETCH_DOTS;
+
+        let x = i32::from(object.x) + i32::from(scx % 8);
+        let tile = x / 8;
+        if !tiles.contains(&tile) {
+            tiles.push(tile);
+            dots += if object.x == 0 {
+                OBJECT_MAX_WAIT_DOTS
+            } else {
+                OBJECT_MAX_WAIT_DOTS.saturating_sub((x % 8) as u32)
+            };
+        }
+    }
+
+    dots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ppu::test::write_object;
+
+    fn object(y: u8, x: u8) -> Object {
+        Object {
+            index: 0,
+            y,
+            x,
+            tile: 0,
+            attributes: 0,
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut cpu = Cpu::new();
+        write_object(&mut cpu, 3, 16, 8, 0, 0);
+        write_object(&mut cpu, 5, 9, 8, 0, 0);
+        write_object(&mut cpu, 7, 8, 8, 0, 0);
+
+        let objects = scan(&cpu, 0, 8);
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].index, 3);
+        assert_eq!(objects[1].index, 5);
+
+        // Tall objects
+        assert_eq!(scan(&cpu, 0, 16).len(), 3);
+    }
+
+    #[test]
+    fn test_scan_limit() {
+        let mut cpu = Cpu::new();
+        for i in 0..OAM_OBJECTS {
+            // Off-screen objects count as well
+            write_object(&mut cpu, i, 16, 0, 0, 0);
+        }
+
+        let objects = scan(&cpu, 0, 8);
+        assert_eq!(objects.len(), 10);
+        assert_eq!(objects[9].index, 9);
+    }
+
+    #[test]
+    fn test_row() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(0x8020, 0b1100_0000);
+        cpu.set_mem(0x8031, 0b0000_0001);
+        cpu.set_mem(0x803e, 0b1000_0000);
+
+        let mut tall = object(16, 8);
+        tall.tile = 0x03;
+        assert_eq!(tall.row(&cpu, 0, 16), (0b1100_0000, 0));
+        assert_eq!(tall.row(&cpu, 8, 16), (0, 0b0000_0001));
+
+        tall.attributes = ATTRIBUTE_Y_FLIP;
+        assert_eq!(tall.row(&cpu, 0, 16).0, 0b1000_0000);
+
+        tall.attributes = ATTRIBUTE_X_FLIP;
+        assert_eq!(tall.row(&cpu, 0, 16), (0b0000_0011, 0));
+
+        // The tile bank is only used in CGB mode
+        cpu.set_vram(1, 0x8020, 0b0000_1111);
+        tall.attributes = ATTRIBUTE_BANK;
+        assert_eq!(tall.row(&cpu, 0, 16).0, 0b1100_0000);
+        cpu.set_cgb_mode(true);
+        assert_eq!(tall.row(&cpu, 0, 16).0, 0b0000_1111);
+    }
+
+    #[test]
+    fn test_pixel_palette() {
+        let mut cpu = Cpu::new();
+        let mut object = object(16, 8);
+        object.attributes = ATTRIBUTE_PALETTE | 0b0000_0101;
+
+        assert_eq!(object.pixel(&cpu, 1).palette, 1);
+        cpu.set_cgb_mode(true);
+        assert_eq!(object.pixel(&cpu, 1).palette, 5);
+    }
+
+    #[test]
+    fn test_priority_by_index() {
+        let mut cpu = Cpu::new();
+        assert!(!priority_by_index(&cpu));
+        cpu.set_cgb_mode(true);
+        assert!(priority_by_index(&cpu));
+        cpu.set_mem(OPRI, OPRI_X_PRIORITY);
+        assert!(!priority_by_index(&cpu));
+    }
+
+    #[test]
+    fn test_penalty() {
+        assert_eq!(penalty(&[], 0), 0);
+        assert_eq!(penalty(&[object(16, 0)], 0), 11);
+        assert_eq!(penalty(&[object(16, 8)], 0), 11);
+        assert_eq!(penalty(&[object(16, 13)], 0), 6);
+        // Only the first object on a tile waits for it
+        assert_eq!(penalty(&[object(16, 9), object(16, 10)], 0), 16);
+        // Objects past the right of the screen aren't fetched
+        assert_eq!(penalty(&[object(16, 168)], 0), 0);
+    }
+}
@@ -0,0 +1,147 @@
+//! Mixing background and object pixels into the colour shown on the LCD,
+//! shared by both renderers.
+
+use super::tiles::*;
+use super::*;
+
+// Every CGB palette holds 4 colours of 2 bytes
+const CGB_PALETTE_SIZE: usize = 8;
+const CGB_COLOR_SIZE: usize = 2;
+
+/// A pixel of the background or window
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BgPixel {
+    pub color: u8,
+    /// Palette of the tile, on the CGB
+    pub palette: u8,
+    /// Whether the tile is drawn over objects, on the CGB
+    pub priority: bool,
+}
+
+impl BgPixel {
+    pub fn new(color: u8, attributes: u8) -> BgPixel {
+        BgPixel {
+            color,
+            palette: attributes & TILE_ATTRIBUTE_PALETTE,
+            priority: attributes & TILE_ATTRIBUTE_PRIORITY != 0,
+        }
+    }
+}
+
+/// A pixel of an object
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectPixel {
+    pub color: u8,
+    /// OBP0 or OBP1 on the DMG, one of the 8 object palettes on the CGB
+    pub palette: u8,
+    /// Whether the background colours 1-3 are drawn over the object
+    pub bg_priority: bool,
+    /// Position of the object in the OAM
+    pub index: usize,
+}
+
+impl Ppu {
+    /// Draw pixel `x` of the current line, from the background and the
+    /// object pixel over it, if any.
+    ///
+    /// Palettes are read as the pixel is drawn.
+    pub(super) fn draw_pixel(
+        &mut self,
+        cpu: &Cpu,
+        x: usize,
+        bg: BgPixel,
+        object: Option<ObjectPixel>,
+    ) {
+        let lcdc = cpu.get_mem(LCDC);
+        let cgb = cpu.cgb_mode();
+
+        // On the DMG, disabling the background leaves it white
+        let bg = if !cgb && lcdc & LCDC_BG_ENABLE == 0 {
+            BgPixel::default()
+        } else {
+            bg
+        };
+        let object = object.filter(|object| {
+            object.color != 0
+                && lcdc & LCDC_OBJECT_ENABLE != 0
+                && object_visible(cgb, lcdc, bg, object)
+        });
+
+        let i = usize::from(self.ly) * SCREEN_WIDTH + x;
+        if cgb {
+            let (ram, palette, color) = match object {
+                Some(object) => (cpu.obj_palette_ram(), object.palette, object.color),
+                None => (cpu.bg_palette_ram(), bg.palette, bg.color),
+            };
+            self.framebuffer[i] = color;
+            self.colors[i] = cgb_color(ram, palette, color);
+        } else {
+            self.framebuffer[i] = match object {
+                Some(object) => {
+                    let palette = [OBP0, OBP1][usize::from(object.palette)];
+                    shade(cpu.get_mem(palette), object.color)
+                }
+                None => shade(cpu.get_mem(BGP), bg.color),
+            };
+        }
+    }
+}
+
+/// Whether an opaque object pixel is drawn over the background.
+///
+/// Background colour 0 is always behind objects. Otherwise, the object is
+/// hidden if it asks to be, or on the CGB if the tile asks to be drawn over
+/// objects. On the CGB, clearing LCDC bit 0 draws objects over everything.
+fn object_visible(cgb: bool, lcdc: u8, bg: BgPixel, object: &ObjectPixel) -> bool {
+    if cgb && lcdc & LCDC_BG_ENABLE == 0 {
+        return true;
+    }
+    bg.color == 0 || !(object.bg_priority || (cgb && bg.priority))
+}
+
+/// 15 bit colour of a CGB palette, stored little endian in palette RAM as
+/// 5 bits each of red, green and blue, from the lowest bits
+pub fn cgb_color(ram: &[u8], palette: u8, color: u8) -> u16 {
+    let i = usize::from(palette) * CGB_PALETTE_SIZE + usize::from(color) * CGB_COLOR_SIZE;
+    u16::from(ram[i]) | u16::from(ram[i + 1] & 0x7f) << 8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn object(bg_priority: bool) -> ObjectPixel {
+        ObjectPixel {
+            color: 1,
+            palette: 0,
+            bg_priority,
+            index: 0,
+        }
+    }
+
+    #[test]
+    fn test_object_visible() {
+        let white = BgPixel::new(0, TILE_ATTRIBUTE_PRIORITY);
+        let dark = BgPixel::new(3, 0);
+        let dark_priority = BgPixel::new(3, TILE_ATTRIBUTE_PRIORITY);
+
+        assert!(object_visible(false, 0x91, white, &object(true)));
+        assert!(object_visible(false, 0x91, dark, &object(false)));
+        assert!(!object_visible(false, 0x91, dark, &object(true)));
+        // The tile attributes only exist on the CGB
+        assert!(object_visible(false, 0x91, dark_priority, &object(false)));
+        assert!(!object_visible(true, 0x91, dark_priority, &object(false)));
+        assert!(object_visible(true, 0x91, white, &object(true)));
+        // Master priority
+        assert!(object_visible(true, 0x90, dark_priority, &object(true)));
+    }
+
+    #[test]
+    fn test_cgb_color() {
+        let mut ram = [0; 64];
+        ram[10] = 0x1f;
+        ram[11] = 0xfc;
+        assert_eq!(cgb_color(&ram, 1, 1), 0x7c1f);
+        assert_eq!(cgb_color(&ram, 1, 0), 0x0000);
+    }
+}
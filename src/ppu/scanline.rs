@@ -0,0 +1,350 @@
+//! Renderer drawing a whole line at once, when the PPU starts drawing it.
+
+use super::oam;
+use super::pixel::{BgPixel, ObjectPixel};
+use super::tiles::*;
+use super::*;
+
+impl Ppu {
+    /// Draw the current line
+    pub(super) fn render_line(&mut self, cpu: &Cpu) {
+        let bg = self.render_background(cpu);
+        let objects = self.render_objects(cpu);
+        for (x, (&bg, &object)) in bg.iter().zip(objects.iter()).enumerate() {
+            self.draw_pixel(cpu, x, bg, object);
+        }
+    }
+
+    /// The background and window pixels of the current line
+    fn render_background(&mut self, cpu: &Cpu) -> [BgPixel; SCREEN_WIDTH] {
+        let mut pixels = [BgPixel::default(); SCREEN_WIDTH];
+        let lcdc = cpu.get_mem(LCDC);
+        let scx = cpu.get_mem(SCX);
+        let scy = cpu.get_mem(SCY);
+        let wx = cpu.get_mem(WX);
+
+        // On the DMG, the window is disabled together with the background
+        let window_enabled = (cpu.cgb_mode() || lcdc & LCDC_BG_ENABLE != 0)
+            && lcdc & LCDC_WINDOW_ENABLE != 0
+            && self.window_triggered
+            && wx <= WINDOW_X_MAX;
+        let window_x = i32::from(wx) - WINDOW_X_OFFSET;
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = if window_enabled && x as i32 >= window_x {
+                let map = tilemap(lcdc, LCDC_WINDOW_TILEMAP);
+                tile_pixel(
+                    cpu,
+                    lcdc,
+                    map,
+                    (x as i32 - window_x) as u8,
+                    self.window_line,
+                )
+            } else {
+                let map = tilemap(lcdc, LCDC_BG_TILEMAP);
+                tile_pixel(
+                    cpu,
+                    lcdc,
+                    map,
+                    (x as u8).wrapping_add(scx),
+                    self.ly.wrapping_add(scy),
+                )
+            };
+        }
+
+        if window_enabled && window_x < SCREEN_WIDTH as i32 {
+            self.window_line += 1;
+        }
+
+        pixels
+    }
+
+    /// The topmost opaque object pixel of every column of the current line.
+    ///
+    /// Where objects overlap, the one with the lowest X is drawn, or the
+    /// first in the OAM if they share it. On the CGB, only the OAM order
+    /// counts. Transparent pixels let the next object show through.
+    fn render_objects(&self, cpu: &Cpu) -> [Option<ObjectPixel>; SCREEN_WIDTH] {
+        let mut pixels = [None; SCREEN_WIDTH];
+        let height = oam::object_height(cpu.get_mem(LCDC));
+
+        let mut objects = self.objects.clone();
+        if oam::priority_by_index(cpu) {
+            objects.sort_by_key(|object| object.index);
+        } else {
+            objects.sort_by_key(|object| (object.x, object.index));
+        }
+        let rows: Vec<(u8, u8)> = objects
+            .iter()
+            .map(|object| object.row(cpu, self.ly, height))
+            .collect();
+
+        for (x, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = objects
+                .iter()
+                .zip(&rows)
+                .filter_map(|(object, &(low, high))| {
+                    let column = x as i32 - object.left();
+                    if !(0..8).contains(&column) {
+                        return None;
+                    }
+                    match color_index(low, high, 7 - column as u8) {
+                        0 => None,
+                        color => Some(object.pixel(cpu, color)),
+                    }
+                })
+                .next();
+        }
+
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::oam::{ATTRIBUTE_BG_PRIORITY, ATTRIBUTE_PALETTE};
+    use super::*;
+    use ppu::test::{render, write_object};
+    use registers::{BCPD, BCPS, DMA, OCPD, OCPS};
+
+    // Tile 1 has colour 3 in its first column and colour 1 in the rest of
+    // its first row, tile 2 has colour 2 everywhere
+    fn cpu_with_tiles(lcdc: u8) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, lcdc);
+        cpu.set_mem(BGP, 0b1110_0100);
+
+        let tile_1 = if lcdc & LCDC_TILE_DATA != 0 {
+            0x8010
+        } else {
+            0x9010
+        };
+        cpu.set_mem(tile_1, 0xff);
+        cpu.set_mem(tile_1 + 1, 0x80);
+        for i in 0..8 {
+            cpu.set_mem(tile_1 + TILE_SIZE + i * 2 + 1, 0xff);
+        }
+        cpu
+    }
+
+    #[test]
+    fn test_background() {
+        let mut cpu = cpu_with_tiles(0x91);
+        cpu.set_mem(TILEMAP_LOW + 1, 0x01);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 2);
+        assert_eq!(ppu.framebuffer[7], 0);
+        assert_eq!(&ppu.framebuffer[8..11], &[3, 1, 1]);
+        assert_eq!(ppu.framebuffer[SCREEN_WIDTH + 8], 0);
+    }
+
+    #[test]
+    fn test_signed_tile_data_and_high_tilemap() {
+        let mut cpu = cpu_with_tiles(0x89);
+        cpu.set_mem(TILEMAP_HIGH, 0x01);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.framebuffer[0..2], &[3, 1]);
+    }
+
+    #[test]
+    fn test_scroll_wraps_around() {
+        let mut cpu = cpu_with_tiles(0x91);
+        cpu.set_mem(TILEMAP_LOW, 0x01);
+        cpu.set_mem(SCX, 0xfc);
+        cpu.set_mem(SCY, 0xf8);
+
+        // Line 8 shows line 0 of the tilemap, and pixel 4 its column 0
+        let ppu = render(&mut cpu, Renderer::Scanline, 9);
+        let line = 8 * SCREEN_WIDTH;
+        assert_eq!(&ppu.framebuffer[line + 3..line + 6], &[0, 3, 1]);
+    }
+
+    #[test]
+    fn test_bg_disabled() {
+        let mut cpu = cpu_with_tiles(0x90);
+        cpu.set_mem(TILEMAP_LOW, 0x01);
+        cpu.set_mem(BGP, 0b1110_0111);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.framebuffer[0..2], &[3, 3]);
+    }
+
+    #[test]
+    fn test_window() {
+        let mut cpu = cpu_with_tiles(0xf1);
+        cpu.set_mem(TILEMAP_HIGH, 0x02);
+        cpu.set_mem(WY, 2);
+        cpu.set_mem(WX, 7 + 150);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 3);
+        assert_eq!(ppu.framebuffer[SCREEN_WIDTH + 150], 0);
+        assert_eq!(ppu.framebuffer[2 * SCREEN_WIDTH + 149], 0);
+        assert_eq!(ppu.framebuffer[2 * SCREEN_WIDTH + 150], 2);
+        assert_eq!(ppu.window_line, 1);
+    }
+
+    #[test]
+    fn test_objects() {
+        let mut cpu = cpu_with_tiles(0x93);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        cpu.set_mem(OBP1, 0b0001_1011);
+        write_object(&mut cpu, 0, 16, 8, 0x01, 0);
+        write_object(&mut cpu, 1, 16, 20, 0x02, ATTRIBUTE_PALETTE);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.framebuffer[0..3], &[3, 1, 1]);
+        assert_eq!(&ppu.framebuffer[11..13], &[0, 1]);
+    }
+
+    #[test]
+    fn test_objects_through_dma() {
+        let mut cpu = cpu_with_tiles(0x93);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        cpu.set_mem_range(0xc104, 0xc107, &[16, 8, 0x01, 0]);
+        cpu.write_io(DMA, 0xc1);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.framebuffer[0..3], &[3, 1, 1]);
+    }
+
+    #[test]
+    fn test_objects_partially_off_screen() {
+        let mut cpu = cpu_with_tiles(0x93);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        write_object(&mut cpu, 0, 16, 1, 0x01, 0);
+        write_object(&mut cpu, 1, 16, 166, 0x01, 0);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        // Only the last column of the first object is visible
+        assert_eq!(&ppu.framebuffer[0..2], &[1, 0]);
+        assert_eq!(&ppu.framebuffer[157..160], &[0, 3, 1]);
+    }
+
+    #[test]
+    fn test_object_priority() {
+        let mut cpu = cpu_with_tiles(0x93);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        // Colour 2 everywhere, but drawn behind the first object since it
+        // comes later in the OAM
+        write_object(&mut cpu, 1, 16, 8, 0x02, 0);
+        write_object(&mut cpu, 0, 16, 8, 0x01, 0);
+        // The lowest X wins, whatever the OAM order
+        write_object(&mut cpu, 2, 16, 24, 0x02, 0);
+        write_object(&mut cpu, 3, 16, 23, 0x01, 0);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 2);
+        assert_eq!(&ppu.framebuffer[0..2], &[3, 1]);
+        assert_eq!(&ppu.framebuffer[15..18], &[3, 1, 1]);
+        // Transparent pixels show the object behind
+        assert_eq!(ppu.framebuffer[SCREEN_WIDTH], 2);
+    }
+
+    #[test]
+    fn test_object_behind_background() {
+        let mut cpu = cpu_with_tiles(0x93);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        cpu.set_mem(TILEMAP_LOW, 0x01);
+        write_object(&mut cpu, 0, 16, 8, 0x02, ATTRIBUTE_BG_PRIORITY);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 2);
+        // Hidden by colours 1-3 of the background, but not by colour 0
+        assert_eq!(&ppu.framebuffer[0..2], &[3, 1]);
+        assert_eq!(ppu.framebuffer[SCREEN_WIDTH], 2);
+    }
+
+    #[test]
+    fn test_objects_disabled() {
+        let mut cpu = cpu_with_tiles(0x91);
+        write_object(&mut cpu, 0, 16, 8, 0x02, 0);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(ppu.framebuffer[0], 0);
+    }
+
+    /// A CGB with background palette 1 and object palette 2 holding the
+    /// colours 0x0000, 0x0001, 0x0002, 0x0003, and the other palettes white
+    fn cgb_with_tiles(lcdc: u8) -> Cpu {
+        let mut cpu = cpu_with_tiles(lcdc);
+        cpu.set_cgb_mode(true);
+        for i in 0..4 {
+            cpu.write_io(BCPS, 0x88 + i * 2);
+            cpu.write_io(BCPD, i);
+            cpu.write_io(BCPD, 0);
+            cpu.write_io(OCPS, 0x90 + i * 2);
+            cpu.write_io(OCPD, i);
+            cpu.write_io(OCPD, 0);
+        }
+        cpu
+    }
+
+    #[test]
+    fn test_cgb_background_attributes() {
+        let mut cpu = cgb_with_tiles(0x91);
+        cpu.set_mem(TILEMAP_LOW, 0x01);
+        cpu.set_mem(TILEMAP_LOW + 1, 0x01);
+        // Palette 1, flipped horizontally
+        cpu.set_vram(1, TILEMAP_LOW + 1, 0b0010_0001);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.framebuffer[0..2], &[3, 1]);
+        assert_eq!(&ppu.colors[0..2], &[0, 0]);
+        assert_eq!(&ppu.framebuffer[14..16], &[1, 3]);
+        assert_eq!(&ppu.colors[14..16], &[1, 3]);
+    }
+
+    #[test]
+    fn test_cgb_object_palettes_and_priority() {
+        let mut cpu = cgb_with_tiles(0x93);
+        cpu.set_mem(TILEMAP_LOW, 0x01);
+        cpu.set_vram(1, TILEMAP_LOW, 0b1000_0001);
+        // Behind the tile with priority, over the next one
+        write_object(&mut cpu, 0, 16, 12, 0x02, 0x02);
+
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(ppu.colors[4], 1);
+        assert_eq!(ppu.colors[8], 2);
+
+        // Clearing LCDC bit 0 puts objects over everything, and still draws
+        // the background
+        cpu.set_mem(LCDC, 0x92);
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(ppu.colors[4], 2);
+        assert_eq!(ppu.colors[1], 1);
+    }
+
+    #[test]
+    fn test_cgb_object_priority_by_index() {
+        let mut cpu = cgb_with_tiles(0x93);
+        write_object(&mut cpu, 0, 16, 9, 0x02, 0x02);
+        write_object(&mut cpu, 1, 16, 8, 0x01, 0x02);
+
+        // The first object in the OAM wins, whatever its X
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.colors[0..2], &[3, 2]);
+
+        // Unless OPRI asks for the DMG order
+        cpu.set_mem(OPRI, OPRI_X_PRIORITY);
+        let ppu = render(&mut cpu, Renderer::Scanline, 1);
+        assert_eq!(&ppu.colors[0..2], &[3, 1]);
+    }
+
+    #[test]
+    fn test_window_line_only_advances_when_visible() {
+        let mut cpu = cpu_with_tiles(0xf1);
+        cpu.set_mem(WY, 0);
+        cpu.set_mem(WX, 7);
+
+        let mut ppu = render(&mut cpu, Renderer::Scanline, 2);
+        assert_eq!(ppu.window_line, 2);
+
+        // Hidden for a line
+        cpu.set_mem(WX, 200);
+        ppu.step(&mut cpu, DOTS_PER_LINE);
+        assert_eq!(ppu.window_line, 2);
+
+        cpu.set_mem(WX, 7);
+        ppu.step(&mut cpu, DOTS_PER_LINE);
+        assert_eq!(ppu.window_line, 3);
+    }
+}
@@ -0,0 +1,144 @@
+//! Tile data and tilemaps shared by the background and window of both
+//! renderers.
+
+use super::pixel::BgPixel;
+use super::*;
+
+pub const TILE_DATA_UNSIGNED: usize = 0x8000;
+pub const TILE_DATA_SIGNED: usize = 0x9000;
+pub const TILEMAP_LOW: usize = 0x9800;
+pub const TILEMAP_HIGH: usize = 0x9c00;
+
+pub const TILE_SIZE: usize = 16;
+pub const TILEMAP_WIDTH: usize = 32;
+
+// The window is drawn from WX - 7, and not at all past this value
+pub const WINDOW_X_OFFSET: i32 = 7;
+pub const WINDOW_X_MAX: u8 = 166;
+
+// Attributes of every tilemap entry, kept in VRAM bank 1 on the CGB
+pub const TILE_ATTRIBUTE_PRIORITY: u8 = 0b1000_0000;
+const TILE_ATTRIBUTE_Y_FLIP: u8 = 0b0100_0000;
+const TILE_ATTRIBUTE_X_FLIP: u8 = 0b0010_0000;
+const TILE_ATTRIBUTE_BANK: u8 = 0b0000_1000;
+pub const TILE_ATTRIBUTE_PALETTE: u8 = 0b0000_0111;
+
+pub fn tilemap(lcdc: u8, select: u8) -> usize {
+    if lcdc & select != 0 {
+        TILEMAP_HIGH
+    } else {
+        TILEMAP_LOW
+    }
+}
+
+/// The pixel at (x, y) of a 256×256 tilemap
+pub fn tile_pixel(cpu: &Cpu, lcdc: u8, map: usize, x: u8, y: u8) -> BgPixel {
+    let (x, y) = (usize::from(x), usize::from(y));
+    let addr = map + (y / 8) * TILEMAP_WIDTH + x / 8;
+    let attributes = tile_attributes(cpu, addr);
+    let (low, high) = tile_row(cpu, lcdc, cpu.get_mem(addr), attributes, y % 8);
+    BgPixel::new(color_index(low, high, 7 - (x % 8) as u8), attributes)
+}
+
+/// Attributes of the tilemap entry at `addr`, which are all 0 outside of
+/// CGB mode
+pub fn tile_attributes(cpu: &Cpu, addr: usize) -> u8 {
+    if cpu.cgb_mode() {
+        cpu.get_vram(1, addr)
+    } else {
+        0
+    }
+}
+
+/// Both bytes of a row of a background or window tile, flipped as its
+/// attributes ask so bit 7 is always the leftmost pixel. Tiles are numbered
+/// from 0x8000 or, with signed numbers, from 0x9000.
+pub fn tile_row(cpu: &Cpu, lcdc: u8, tile: u8, attributes: u8, row: usize) -> (u8, u8) {
+    let addr = if lcdc & LCDC_TILE_DATA != 0 {
+        TILE_DATA_UNSIGNED + usize::from(tile) * TILE_SIZE
+    } else {
+        (TILE_DATA_SIGNED as isize + isize::from(tile as i8) * TILE_SIZE as isize) as usize
+    };
+    let row = if attributes & TILE_ATTRIBUTE_Y_FLIP != 0 {
+        7 - row
+    } else {
+        row
+    };
+    let bank = usize::from(attributes & TILE_ATTRIBUTE_BANK != 0);
+
+    let (low, high) = (
+        cpu.get_vram(bank, addr + row * 2),
+        cpu.get_vram(bank, addr + row * 2 + 1),
+    );
+    if attributes & TILE_ATTRIBUTE_X_FLIP != 0 {
+        (low.reverse_bits(), high.reverse_bits())
+    } else {
+        (low, high)
+    }
+}
+
+/// Colour index of pixel `bit` of a tile row, where bit 7 is the leftmost
+/// pixel
+pub fn color_index(low: u8, high: u8, bit: u8) -> u8 {
+    ((high >> bit) & 1) << 1 | ((low >> bit) & 1)
+}
+
+/// Shade a palette register maps a colour index to
+pub fn shade(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0b11
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tile_row_addressing() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(0x8ff0, 0x12);
+        cpu.set_mem(0x9000, 0x34);
+        cpu.set_mem(0x8010, 0x56);
+
+        assert_eq!(tile_row(&cpu, 0x00, 0xff, 0, 0).0, 0x12);
+        assert_eq!(tile_row(&cpu, 0x00, 0x00, 0, 0).0, 0x34);
+        assert_eq!(tile_row(&cpu, LCDC_TILE_DATA, 0x01, 0, 0).0, 0x56);
+    }
+
+    #[test]
+    fn test_tile_attributes() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(0x8000, 0b1100_0000);
+        cpu.set_vram(1, 0x800e, 0b0000_0001);
+        cpu.set_vram(1, TILEMAP_LOW, 0xff);
+
+        // Only in CGB mode
+        assert_eq!(tile_attributes(&cpu, TILEMAP_LOW), 0);
+        cpu.set_cgb_mode(true);
+        assert_eq!(tile_attributes(&cpu, TILEMAP_LOW), 0xff);
+
+        assert_eq!(tile_row(&cpu, LCDC_TILE_DATA, 0, 0, 0), (0b1100_0000, 0));
+        assert_eq!(
+            tile_row(&cpu, LCDC_TILE_DATA, 0, TILE_ATTRIBUTE_X_FLIP, 0),
+            (0b0000_0011, 0)
+        );
+        let attributes = TILE_ATTRIBUTE_BANK | TILE_ATTRIBUTE_Y_FLIP;
+        assert_eq!(
+            tile_row(&cpu, LCDC_TILE_DATA, 0, attributes, 0),
+            (0b0000_0001, 0)
+        );
+
+        // Flipped both ways, from bank 1
+        let pixel = tile_pixel(&cpu, LCDC_TILE_DATA, TILEMAP_LOW, 0, 0);
+        assert_eq!(pixel, BgPixel::new(1, 0xff));
+        assert_eq!(pixel.palette, 7);
+        assert!(pixel.priority);
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(shade(0b1110_0100, 0), 0);
+        assert_eq!(shade(0b1110_0100, 3), 3);
+        assert_eq!(shade(0b0001_1011, 0), 3);
+        assert_eq!(shade(0b0001_1011, 2), 1);
+    }
+}
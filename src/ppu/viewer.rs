@@ -0,0 +1,303 @@
+//! Images of the tiles, tilemaps and objects in VRAM and OAM, for debugging
+//! graphics.
+
+use super::oam::{
+    self, Object, ATTRIBUTE_BANK, ATTRIBUTE_BG_PRIORITY, ATTRIBUTE_X_FLIP, ATTRIBUTE_Y_FLIP,
+    OAM_OBJECTS,
+};
+use super::pixel::{cgb_color, BgPixel};
+use super::tiles::*;
+use super::*;
+use image::Image;
+use palette::{ColorCorrection, DmgPalette};
+
+const TILES_PER_BANK: usize = 384;
+const TILES_PER_ROW: usize = 16;
+const TILEMAP_PIXELS: usize = 256;
+const OBJECTS_PER_ROW: usize = 8;
+
+/// Addresses of both tilemaps
+pub const TILEMAPS: [usize; 2] = [TILEMAP_LOW, TILEMAP_HIGH];
+
+const VIEWPORT_OUTLINE: [u8; 4] = [0xff, 0x00, 0x00, 0xff];
+const TRANSPARENT: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+
+/// Draws the VRAM and OAM as the PPU would see them now
+pub struct Viewer<'a> {
+    cpu: &'a Cpu,
+    dmg_palette: DmgPalette,
+    color_correction: ColorCorrection,
+}
+
+impl<'a> Viewer<'a> {
+    pub fn new(
+        cpu: &'a Cpu,
+        dmg_palette: DmgPalette,
+        color_correction: ColorCorrection,
+    ) -> Viewer<'a> {
+        Viewer {
+            cpu,
+            dmg_palette,
+            color_correction,
+        }
+    }
+
+    /// All 384 tiles in a grid 16 tiles wide, with the tiles of the second
+    /// bank to the right of the first on the CGB. Colour indices are drawn
+    /// as the shades of the DMG palette.
+    pub fn tiles(&self) -> Image {
+        let banks = if self.cpu.cgb_mode() { 2 } else { 1 };
+        let bank_width = TILES_PER_ROW * 8;
+        let mut image = blank(bank_width * banks, TILES_PER_BANK / TILES_PER_ROW * 8);
+
+        for bank in 0..banks {
+            for tile in 0..TILES_PER_BANK {
+                for row in 0..8 {
+                    let addr = TILE_DATA_UNSIGNED + tile * TILE_SIZE + row * 2;
+                    let low = self.cpu.get_vram(bank, addr);
+                    let high = self.cpu.get_vram(bank, addr + 1);
+
+                    for column in 0..8 {
+                        let color = color_index(low, high, 7 - column as u8);
+                        let x = bank * bank_width + (tile % TILES_PER_ROW) * 8 + column;
+                        let y = (tile / TILES_PER_ROW) * 8 + row;
+                        image.set_pixel(x, y, self.dmg_palette.rgba(color));
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    /// The whole 256×256 tilemap at `map` as the background would draw it,
+    /// with the 160×144 viewport at SCX and SCY outlined
+    pub fn tilemap(&self, map: usize) -> Image {
+        let lcdc = self.cpu.get_mem(LCDC);
+        let mut image = blank(TILEMAP_PIXELS, TILEMAP_PIXELS);
+
+        for y in 0..TILEMAP_PIXELS {
+            for x in 0..TILEMAP_PIXELS {
+                let pixel = tile_pixel(self.cpu, lcdc, map, x as u8, y as u8);
+                image.set_pixel(x, y, self.bg_rgba(pixel));
+            }
+        }
+
+        // The viewport wraps around the edges of the tilemap
+        let scx = usize::from(self.cpu.get_mem(SCX));
+        let scy = usize::from(self.cpu.get_mem(SCY));
+        let wrap = |value: usize| value % TILEMAP_PIXELS;
+        for i in 0..SCREEN_WIDTH {
+            image.set_pixel(wrap(scx + i), scy, VIEWPORT_OUTLINE);
+            image.set_pixel(
+                wrap(scx + i),
+                wrap(scy + SCREEN_HEIGHT - 1),
+                VIEWPORT_OUTLINE,
+            );
+        }
+        for i in 0..SCREEN_HEIGHT {
+            image.set_pixel(scx, wrap(scy + i), VIEWPORT_OUTLINE);
+            image.set_pixel(
+                wrap(scx + SCREEN_WIDTH - 1),
+                wrap(scy + i),
+                VIEWPORT_OUTLINE,
+            );
+        }
+
+        image
+    }
+
+    /// Every object of the OAM, one per line
+    pub fn oam_table(&self) -> String {
+        let cgb = self.cpu.cgb_mode();
+        let mut table = String::from("  # |   X |   Y | Tile | Attr | Flags\n");
+
+        for index in 0..OAM_OBJECTS {
+            let object = Object::read(self.cpu, index);
+
+            let mut flags = Vec::new();
+            if cgb {
+                flags.push(format!("palette {}", object.pixel(self.cpu, 0).palette));
+                flags.push(format!(
+                    "bank {}",
+                    u8::from(object.attributes & ATTRIBUTE_BANK != 0)
+                ));
+            } else {
+                flags.push(format!("OBP{}", object.pixel(self.cpu, 0).palette));
+            }
+            if object.attributes & ATTRIBUTE_X_FLIP != 0 {
+                flags.push("x flip".to_string());
+            }
+            if object.attributes & ATTRIBUTE_Y_FLIP != 0 {
+                flags.push("y flip".to_string());
+            }
+            if object.attributes & ATTRIBUTE_BG_PRIORITY != 0 {
+                flags.push("behind bg".to_string());
+            }
+
+            table += &format!(
+                "{:3} | {:3} | {:3} |   {:02x} |   {:02x} | {}\n",
+                index,
+                object.x,
+                object.y,
+                object.tile,
+                object.attributes,
+                flags.join(", ")
+            );
+        }
+
+        table
+    }
+
+    /// Every object of the OAM in its palette, in a grid 8 objects wide and
+    /// in OAM order. Colour 0 is left transparent.
+    pub fn sprites(&self) -> Image {
+        let height = usize::from(oam::object_height(self.cpu.get_mem(LCDC)));
+        let rows = OAM_OBJECTS / OBJECTS_PER_ROW;
+        let mut image = blank(OBJECTS_PER_ROW * 8, rows * height);
+
+        for index in 0..OAM_OBJECTS {
+            let object = Object::read(self.cpu, index);
+            let (left, top) = (
+                (index % OBJECTS_PER_ROW) * 8,
+                (index / OBJECTS_PER_ROW) * height,
+            );
+
+            for row in 0..height {
+                let (low, high) = object.tile_row(self.cpu, row as u8, height as u8);
+                for column in 0..8 {
+                    let rgba = match color_index(low, high, 7 - column as u8) {
+                        0 => TRANSPARENT,
+                        color => self.object_rgba(&object, color),
+                    };
+                    image.set_pixel(left + column, top + row, rgba);
+                }
+            }
+        }
+
+        image
+    }
+
+    fn bg_rgba(&self, pixel: BgPixel) -> [u8; 4] {
+        if self.cpu.cgb_mode() {
+            let ram = self.cpu.bg_palette_ram();
+            let color = cgb_color(ram, pixel.palette, pixel.color);
+            self.color_correction.rgba(color)
+        } else {
+            let shade = shade(self.cpu.get_mem(BGP), pixel.color);
+            self.dmg_palette.rgba(shade)
+        }
+    }
+
+    fn object_rgba(&self, object: &Object, color: u8) -> [u8; 4] {
+        let pixel = object.pixel(self.cpu, color);
+        if self.cpu.cgb_mode() {
+            let ram = self.cpu.obj_palette_ram();
+            let color = cgb_color(ram, pixel.palette, pixel.color);
+            self.color_correction.rgba(color)
+        } else {
+            let palette = [OBP0, OBP1][usize::from(pixel.palette)];
+            let shade = shade(self.cpu.get_mem(palette), pixel.color);
+            self.dmg_palette.rgba(shade)
+        }
+    }
+}
+
+fn blank(width: usize, height: usize) -> Image {
+    Image::new(width, height, vec![0; width * height * 4])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WHITE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+    const BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+
+    fn viewer(cpu: &Cpu) -> Viewer<'_> {
+        Viewer::new(cpu, DmgPalette::Grey, ColorCorrection::Raw)
+    }
+
+    #[test]
+    fn test_tiles() {
+        let mut cpu = Cpu::new();
+        // First row of tile 17 and last row of the last tile are colour 3
+        cpu.set_mem_range(0x8110, 0x8111, &[0xff, 0xff]);
+        cpu.set_mem_range(0x97fe, 0x97ff, &[0xff, 0xff]);
+
+        let image = viewer(&cpu).tiles();
+        assert_eq!((image.width(), image.height()), (128, 192));
+        assert_eq!(image.pixel(8, 8), BLACK);
+        assert_eq!(image.pixel(8, 9), WHITE);
+        assert_eq!(image.pixel(127, 191), BLACK);
+
+        // The second bank is drawn to the right on the CGB
+        cpu.set_cgb_mode(true);
+        cpu.set_vram(1, 0x8000, 0x80);
+        let image = viewer(&cpu).tiles();
+        assert_eq!((image.width(), image.height()), (256, 192));
+        assert_eq!(image.pixel(128, 0), [0xaa, 0xaa, 0xaa, 0xff]);
+        assert_eq!(image.pixel(129, 0), WHITE);
+    }
+
+    #[test]
+    fn test_tilemap() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, 0x91);
+        cpu.set_mem(BGP, 0b1110_0100);
+        cpu.set_mem_range(0x8010, 0x8011, &[0xff, 0xff]);
+        cpu.set_mem(TILEMAP_HIGH + 33, 0x01);
+        cpu.set_mem(SCX, 200);
+        cpu.set_mem(SCY, 10);
+
+        let image = viewer(&cpu).tilemap(TILEMAP_HIGH);
+        assert_eq!((image.width(), image.height()), (256, 256));
+        assert_eq!(image.pixel(8, 8), BLACK);
+        assert_eq!(image.pixel(8, 9), WHITE);
+
+        // The viewport wraps around to the left edge
+        assert_eq!(image.pixel(200, 10), VIEWPORT_OUTLINE);
+        assert_eq!(image.pixel(255, 10), VIEWPORT_OUTLINE);
+        assert_eq!(image.pixel(103, 153), VIEWPORT_OUTLINE);
+        assert_eq!(image.pixel(103, 100), VIEWPORT_OUTLINE);
+        assert_eq!(image.pixel(104, 100), WHITE);
+        assert_eq!(image.pixel(200, 154), WHITE);
+    }
+
+    #[test]
+    fn test_oam_table() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem_range(0xfe04, 0xfe07, &[0x10, 0x08, 0x2a, 0xb0]);
+
+        let table = viewer(&cpu).oam_table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 41);
+        assert_eq!(
+            lines[2],
+            "  1 |   8 |  16 |   2a |   b0 | OBP1, x flip, behind bg"
+        );
+
+        cpu.set_cgb_mode(true);
+        let table = viewer(&cpu).oam_table();
+        assert_eq!(
+            table.lines().nth(2),
+            Some("  1 |   8 |  16 |   2a |   b0 | palette 0, bank 0, x flip, behind bg")
+        );
+    }
+
+    #[test]
+    fn test_sprites() {
+        let mut cpu = Cpu::new();
+        cpu.set_mem(LCDC, 0x97);
+        cpu.set_mem(OBP0, 0b1110_0100);
+        cpu.set_mem_range(0x8030, 0x8031, &[0x80, 0x80]);
+        // Object 9 uses tiles 2 and 3, flipped vertically
+        cpu.set_mem_range(0xfe24, 0xfe27, &[0x00, 0x00, 0x03, 0x40]);
+
+        let image = viewer(&cpu).sprites();
+        assert_eq!((image.width(), image.height()), (64, 80));
+        assert_eq!(image.pixel(8, 16 + 15), TRANSPARENT);
+        assert_eq!(image.pixel(8, 16 + 7), BLACK);
+        assert_eq!(image.pixel(9, 16 + 7), TRANSPARENT);
+    }
+}
@@ -0,0 +1,64 @@
+//! Addresses of the IO registers, shared by the CPU, the PPU and the state
+//! the boot rom leaves behind.
+
+// Joypad, serial and timer
+pub const P1: usize = 0xff00;
+pub const SC: usize = 0xff02;
+pub const DIV: usize = 0xff04;
+pub const TIMA: usize = 0xff05;
+pub const TMA: usize = 0xff06;
+pub const TAC: usize = 0xff07;
+
+// Interrupt flags
+pub const IF: usize = 0xff0f;
+
+// Sound
+pub const NR10: usize = 0xff10;
+pub const NR11: usize = 0xff11;
+pub const NR12: usize = 0xff12;
+pub const NR13: usize = 0xff13;
+pub const NR14: usize = 0xff14;
+pub const NR21: usize = 0xff16;
+pub const NR22: usize = 0xff17;
+pub const NR24: usize = 0xff19;
+pub const NR30: usize = 0xff1a;
+pub const NR31: usize = 0xff1b;
+pub const NR32: usize = 0xff1c;
+pub const NR33: usize = 0xff1e;
+pub const NR41: usize = 0xff20;
+pub const NR42: usize = 0xff21;
+pub const NR43: usize = 0xff22;
+pub const NR44: usize = 0xff23;
+pub const NR50: usize = 0xff24;
+pub const NR51: usize = 0xff25;
+pub const NR52: usize = 0xff26;
+
+// LCD
+pub const LCDC: usize = 0xff40;
+pub const STAT: usize = 0xff41;
+pub const SCY: usize = 0xff42;
+pub const SCX: usize = 0xff43;
+pub const LY: usize = 0xff44;
+pub const LYC: usize = 0xff45;
+pub const DMA: usize = 0xff46;
+pub const BGP: usize = 0xff47;
+pub const OBP0: usize = 0xff48;
+pub const OBP1: usize = 0xff49;
+pub const WY: usize = 0xff4a;
+pub const WX: usize = 0xff4b;
+
+// CGB VRAM bank
+pub const VBK: usize = 0xff4f;
+
+// Unmaps the boot rom once written
+pub const BOOT: usize = 0xff50;
+
+// CGB palettes and object priority
+pub const BCPS: usize = 0xff68;
+pub const BCPD: usize = 0xff69;
+pub const OCPS: usize = 0xff6a;
+pub const OCPD: usize = 0xff6b;
+pub const OPRI: usize = 0xff6c;
+
+// Interrupt enable flag
+pub const IE: usize = 0xffff;
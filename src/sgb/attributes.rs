@@ -0,0 +1,284 @@
+//! Which of the 4 SGB palettes colours every 8×8 cell of the screen, set by
+//! the ATTR_* commands.
+
+pub const COLUMNS: usize = 20;
+pub const ROWS: usize = 18;
+pub const CELLS: usize = COLUMNS * ROWS;
+
+/// Attribute files sent with ATTR_TRN hold 4 cells per byte
+pub const FILE_SIZE: usize = CELLS / 4;
+pub const FILES: usize = 45;
+
+const PALETTE_MASK: u8 = 0b11;
+
+// ATTR_BLK control bits, telling which parts of the block are changed
+const BLOCK_INSIDE: u8 = 0b001;
+const BLOCK_BORDER: u8 = 0b010;
+const BLOCK_OUTSIDE: u8 = 0b100;
+const BLOCK_DATA_SIZE: usize = 6;
+
+const LINE_HORIZONTAL: u8 = 0b1000_0000;
+const DIVIDE_HORIZONTAL: u8 = 0b0100_0000;
+
+/// Palette of every cell, row by row
+pub struct Attributes {
+    cells: [u8; CELLS],
+}
+
+impl Attributes {
+    pub fn new() -> Attributes {
+        Attributes { cells: [0; CELLS] }
+    }
+
+    /// Palette of the cell holding pixel (x, y) of the screen
+    pub fn palette(&self, x: usize, y: usize) -> u8 {
+        self.cells[(y / 8) * COLUMNS + x / 8]
+    }
+
+    fn set(&mut self, column: usize, row: usize, palette: u8) {
+        self.cells[row * COLUMNS + column] = palette & PALETTE_MASK;
+    }
+
+    /// ATTR_BLK: colour the inside, border and outside of rectangles.
+    ///
+    /// A rectangle whose inside alone or outside alone is changed has its
+    /// border changed along with it. Changing both leaves the border as it
+    /// is.
+    pub fn block(&mut self, data: &[u8]) {
+        let count = usize::from(data[1]);
+        for block in data[2..].chunks(BLOCK_DATA_SIZE).take(count) {
+            if block.len() < BLOCK_DATA_SIZE {
+                break;
+            }
+            let (control, palettes) = (block[0] & 0b111, block[1]);
+            let (left, top) = (usize::from(block[2]), usize::from(block[3]));
+            let (right, bottom) = (usize::from(block[4]), usize::from(block[5]));
+
+            let inside = palettes & PALETTE_MASK;
+            let outside = (palettes >> 4) & PALETTE_MASK;
+            let border = match control {
+                BLOCK_INSIDE => Some(inside),
+                BLOCK_OUTSIDE => Some(outside),
+                _ if control & BLOCK_BORDER != 0 => Some((palettes >> 2) & PALETTE_MASK),
+                _ => None,
+            };
+
+            for row in 0..ROWS {
+                for column in 0..COLUMNS {
+                    let within = (left..=right).contains(&column) && (top..=bottom).contains(&row);
+                    let on_border = within
+                        && (column == left || column == right || row == top || row == bottom);
+
+                    let palette = if !within {
+                        Some(outside).filter(|_| control & BLOCK_OUTSIDE != 0)
+                    } else if on_border {
+                        border
+                    } else {
+                        Some(inside).filter(|_| control & BLOCK_INSIDE != 0)
+                    };
+                    if let Some(palette) = palette {
+                        self.set(column, row, palette);
+                    }
+                }
+            }
+        }
+    }
+
+    /// ATTR_LIN: colour whole rows or columns
+    pub fn line(&mut self, data: &[u8]) {
+        let count = usize::from(data[1]);
+        for &line in data[2..].iter().take(count) {
+            let (number, palette) = (usize::from(line & 0x1f), line >> 5);
+            if line & LINE_HORIZONTAL != 0 && number < ROWS {
+                for column in 0..COLUMNS {
+                    self.set(column, number, palette);
+                }
+            } else if line & LINE_HORIZONTAL == 0 && number < COLUMNS {
+                for row in 0..ROWS {
+                    self.set(number, row, palette);
+                }
+            }
+        }
+    }
+
+    /// ATTR_DIV: colour both sides of a line and the line itself
+    pub fn divide(&mut self, data: &[u8]) {
+        let control = data[1];
+        let line = usize::from(data[2]);
+        let (after, before, on) = (control, control >> 2, control >> 4);
+
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let position = if control & DIVIDE_HORIZONTAL != 0 {
+                    row
+                } else {
+                    column
+                };
+                let palette = if position < line {
+                    before
+                } else if position == line {
+                    on
+                } else {
+                    after
+                };
+                self.set(column, row, palette);
+            }
+        }
+    }
+
+    /// ATTR_CHR: colour cells one by one from a starting cell, 4 to a byte
+    /// from the highest bits, either row by row or column by column
+    pub fn characters(&mut self, data: &[u8]) {
+        let (mut column, mut row) = (usize::from(data[1]), usize::from(data[2]));
+        let count = (usize::from(data[3]) | usize::from(data[4]) << 8).min(CELLS);
+        let vertical = data[5] & 1 != 0;
+
+        for i in 0..count {
+            let byte = match data.get(6 + i / 4) {
+                Some(&byte) => byte,
+                None => break,
+            };
+            if column >= COLUMNS || row >= ROWS {
+                break;
+            }
+            self.set(column, row, byte >> (6 - (i % 4) * 2));
+
+            if vertical {
+                row += 1;
+                if row == ROWS {
+                    row = 0;
+                    column += 1;
+                }
+            } else {
+                column += 1;
+                if column == COLUMNS {
+                    column = 0;
+                    row += 1;
+                }
+            }
+        }
+    }
+
+    /// Colour the cells with an attribute file sent with ATTR_TRN
+    pub fn load_file(&mut self, file: &[u8]) {
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            *cell = (file[i / 4] >> (6 - (i % 4) * 2)) & PALETTE_MASK;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cell_rows(attributes: &Attributes) -> Vec<String> {
+        attributes
+            .cells
+            .chunks(COLUMNS)
+            .map(|row| row.iter().map(|palette| palette.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_block() {
+        let mut attributes = Attributes::new();
+        #[rustfmt::skip]
+        attributes.block(&[
+            0x21, 2,
+            0b111, 0b11_10_01, 1, 1, 4, 3,
+            // Only the inside, so the border goes with it
+            0b001, 0b00_00_10, 10, 10, 11, 11,
+        ]);
+
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[0], "33333333333333333333");
+        assert_eq!(rows[1], "32222333333333333333");
+        assert_eq!(rows[2], "32112333333333333333");
+        assert_eq!(rows[3], "32222333333333333333");
+        assert_eq!(rows[10], "33333333332233333333");
+        assert_eq!(rows[12], "33333333333333333333");
+    }
+
+    #[test]
+    fn test_block_border_without_control() {
+        let block = |control| {
+            let mut attributes = Attributes::new();
+            attributes.block(&[0x21, 1, control, 0b11_10_01, 1, 1, 3, 3]);
+            cell_rows(&attributes)
+        };
+
+        // The border takes the inside palette
+        let rows = block(0b001);
+        assert_eq!(rows[0], "00000000000000000000");
+        assert_eq!(rows[1], "01110000000000000000");
+        assert_eq!(rows[2], "01110000000000000000");
+
+        // The border takes the outside palette
+        let rows = block(0b100);
+        assert_eq!(rows[1], "33333333333333333333");
+        assert_eq!(rows[2], "33033333333333333333");
+
+        // The border is left alone
+        let rows = block(0b101);
+        assert_eq!(rows[1], "30003333333333333333");
+        assert_eq!(rows[2], "30103333333333333333");
+        assert_eq!(rows[3], "30003333333333333333");
+    }
+
+    #[test]
+    fn test_line() {
+        let mut attributes = Attributes::new();
+        attributes.line(&[0x29, 3, 0x80 | 0x20 | 2, 0x40 | 19, 0x60 | 25]);
+
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[0], "00000000000000000002");
+        assert_eq!(rows[2], "11111111111111111112");
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut attributes = Attributes::new();
+        attributes.divide(&[0x31, 0b0_10_01_11, 2]);
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[0], "11233333333333333333");
+        assert_eq!(rows[17], "11233333333333333333");
+
+        attributes.divide(&[0x31, 0b1_10_01_11, 16]);
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[15], "11111111111111111111");
+        assert_eq!(rows[16], "22222222222222222222");
+        assert_eq!(rows[17], "33333333333333333333");
+    }
+
+    #[test]
+    fn test_characters() {
+        let mut attributes = Attributes::new();
+        attributes.characters(&[0x39, 18, 0, 5, 0, 0, 0b01_10_11_01, 0b11_00_00_00]);
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[0], "00000000000000000012");
+        assert_eq!(rows[1], "31300000000000000000");
+
+        let mut attributes = Attributes::new();
+        attributes.characters(&[0x39, 0, 16, 3, 0, 1, 0b01_10_11_00]);
+        let rows = cell_rows(&attributes);
+        assert_eq!(rows[16], "10000000000000000000");
+        assert_eq!(rows[17], "20000000000000000000");
+        assert_eq!(rows[0], "03000000000000000000");
+    }
+
+    #[test]
+    fn test_load_file() {
+        let mut file = [0; FILE_SIZE];
+        file[0] = 0b00_01_10_11;
+        file[FILE_SIZE - 1] = 0b11;
+
+        let mut attributes = Attributes::new();
+        attributes.load_file(&file);
+        assert_eq!(attributes.palette(0, 0), 0);
+        assert_eq!(attributes.palette(8, 7), 1);
+        assert_eq!(attributes.palette(31, 0), 3);
+        assert_eq!(attributes.palette(159, 143), 3);
+        assert_eq!(attributes.palette(158, 143), 3);
+        assert_eq!(attributes.palette(151, 143), 0);
+    }
+}
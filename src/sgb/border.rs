@@ -0,0 +1,144 @@
+//! The picture the SNES draws around the Game Boy screen, from tiles sent
+//! with CHR_TRN and a tilemap and palettes sent with PCT_TRN.
+
+use super::{read_color, read_word, SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH, TRANSFER_SIZE};
+
+// SNES tiles have 4 bits per pixel, in 2 pairs of bit planes
+const TILE_SIZE: usize = 32;
+const TILES: usize = 256;
+const MAP_WIDTH: usize = 32;
+const MAP_SIZE: usize = MAP_WIDTH * MAP_WIDTH;
+// The palettes follow the tilemap in PCT_TRN data
+const PALETTES_OFFSET: usize = MAP_SIZE * 2;
+const PALETTES: usize = 4;
+const PALETTE_COLORS: usize = 16;
+// Border tiles use SNES palettes 4 to 7
+const FIRST_PALETTE: u16 = 4;
+
+const ENTRY_TILE: u16 = 0x00ff;
+const ENTRY_X_FLIP: u16 = 0x4000;
+const ENTRY_Y_FLIP: u16 = 0x8000;
+
+pub struct Border {
+    tiles: Vec<u8>,
+    map: Vec<u16>,
+    palettes: [[u16; PALETTE_COLORS]; PALETTES],
+}
+
+impl Border {
+    /// An empty border, showing the background colour all around
+    pub fn new() -> Border {
+        Border {
+            tiles: vec![0; TILES * TILE_SIZE],
+            map: vec![0; MAP_SIZE],
+            palettes: [[0; PALETTE_COLORS]; PALETTES],
+        }
+    }
+
+    /// CHR_TRN: half of the tiles, the second half if `high`
+    pub fn load_tiles(&mut self, data: &[u8], high: bool) {
+        let offset = if high { TRANSFER_SIZE } else { 0 };
+        self.tiles[offset..offset + TRANSFER_SIZE].copy_from_slice(&data[..TRANSFER_SIZE]);
+    }
+
+    /// PCT_TRN: the tilemap and its palettes
+    pub fn load_map(&mut self, data: &[u8]) {
+        for (i, entry) in self.map.iter_mut().enumerate() {
+            *entry = read_word(data, i);
+        }
+        for (i, palette) in self.palettes.iter_mut().enumerate() {
+            for (j, color) in palette.iter_mut().enumerate() {
+                *color = read_color(&data[PALETTES_OFFSET..], i * PALETTE_COLORS + j);
+            }
+        }
+    }
+
+    /// Colour of a pixel of the 256×224 picture, or `None` where the border
+    /// is transparent
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u16> {
+        let entry = self.map[(y / 8) * MAP_WIDTH + x / 8];
+        let tile = usize::from(entry & ENTRY_TILE);
+        let palette = usize::from(((entry >> 10) & 0b111).wrapping_sub(FIRST_PALETTE)) % PALETTES;
+
+        let column = if entry & ENTRY_X_FLIP != 0 {
+            x % 8
+        } else {
+            7 - x % 8
+        };
+        let row = if entry & ENTRY_Y_FLIP != 0 {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+
+        let data = &self.tiles[tile * TILE_SIZE..(tile + 1) * TILE_SIZE];
+        let color = [
+            data[row * 2],
+            data[row * 2 + 1],
+            data[16 + row * 2],
+            data[17 + row * 2],
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |color, (plane, byte)| {
+            color | ((byte >> column) & 1) << plane
+        });
+
+        match color {
+            0 => None,
+            color => Some(self.palettes[palette][usize::from(color)]),
+        }
+    }
+
+    /// Every pixel of the 256×224 picture, row by row
+    pub fn pixels(&self) -> Vec<Option<u16>> {
+        (0..SGB_SCREEN_HEIGHT)
+            .flat_map(|y| (0..SGB_SCREEN_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x, y))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let border = Border::new();
+        assert!(border.pixels().iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_tiles() {
+        let mut tiles = vec![0; TRANSFER_SIZE];
+        // First row of tile 0x81 is colours 15, 1, 2, 4, 8, 0...
+        tiles[TILE_SIZE] = 0b1100_0000;
+        tiles[TILE_SIZE + 1] = 0b1010_0000;
+        tiles[TILE_SIZE + 16] = 0b1001_0000;
+        tiles[TILE_SIZE + 17] = 0b1000_1000;
+
+        let mut map = vec![0; TRANSFER_SIZE];
+        // Tile 0x81 in palette 5 at the top left, flipped horizontally at
+        // the next tile
+        map[0..4].copy_from_slice(&[0x81, 5 << 2, 0x81, 5 << 2 | 0x40]);
+        for i in 0..PALETTE_COLORS {
+            let color = 0x100 + i as u16;
+            let offset = PALETTES_OFFSET + (PALETTE_COLORS + i) * 2;
+            map[offset] = color as u8;
+            map[offset + 1] = (color >> 8) as u8;
+        }
+
+        let mut border = Border::new();
+        border.load_tiles(&tiles, true);
+        border.load_map(&map);
+
+        assert_eq!(border.pixel(0, 0), Some(0x10f));
+        assert_eq!(border.pixel(1, 0), Some(0x101));
+        assert_eq!(border.pixel(4, 0), Some(0x108));
+        assert_eq!(border.pixel(5, 0), None);
+        assert_eq!(border.pixel(0, 1), None);
+        assert_eq!(border.pixel(15, 0), Some(0x10f));
+        assert_eq!(border.pixel(14, 0), Some(0x101));
+    }
+}
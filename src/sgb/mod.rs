@@ -0,0 +1,573 @@
+//! The Super Game Boy. Games send it commands through P1 to colour the
+//! screen, draw a border around it and read more controllers.
+//!
+//! The SNES only sees the picture the Game Boy shows, so the screen is
+//! coloured from the shades of every finished frame, and data sent with
+//! the *_TRN commands is read back from the tiles on screen.
+
+mod attributes;
+mod border;
+mod packet;
+
+use std::mem;
+
+use self::attributes::{Attributes, COLUMNS, FILES, FILE_SIZE};
+use self::border::Border;
+use self::packet::{PacketReceiver, P15, PACKET_SIZE};
+use palette::ColorCorrection;
+use ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Size of the SNES picture, with the Game Boy screen in the middle
+pub const SGB_SCREEN_WIDTH: usize = 256;
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+const SCREEN_LEFT: usize = 48;
+const SCREEN_TOP: usize = 40;
+
+// Every VRAM transfer sends the first 256 tiles on screen
+const TRANSFER_SIZE: usize = 4096;
+const TILE_SIZE: usize = 16;
+const SYSTEM_PALETTES: usize = 512;
+
+// The colours the SGB starts with, before the game sends its own
+const DEFAULT_PALETTE: [u16; 4] = [0x67bf, 0x265b, 0x10b5, 0x2866];
+
+//
+// Commands
+//
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0a;
+const PAL_TRN: u8 = 0x0b;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+const MASK_EN: u8 = 0x17;
+
+const PACKET_COUNT_MASK: u8 = 0b111;
+const APPLY_ATTRIBUTE_FILE: u8 = 0b1000_0000;
+const CANCEL_MASK: u8 = 0b0100_0000;
+const ATTRIBUTE_FILE_MASK: u8 = 0b0011_1111;
+
+/// What the screen shows instead of the game, set with MASK_EN to hide
+/// VRAM transfers
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mask {
+    Off,
+    /// Keep showing the last frame
+    Freeze,
+    Black,
+    /// Colour 0 of the first palette
+    Color0,
+}
+
+/// Data a *_TRN command reads from the next frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Transfer {
+    Palettes,
+    /// The first or second half of the border tiles
+    BorderTiles(bool),
+    BorderMap,
+    AttributeFiles,
+}
+
+pub struct Sgb {
+    /// Whether the cartridge asked for SGB functions. Without them, the
+    /// commands are ignored.
+    commands_enabled: bool,
+    receiver: PacketReceiver,
+    /// Packets received of the command in progress
+    command: Vec<u8>,
+    /// 4 colours of every palette. Colour 0 of the first is used by all.
+    palettes: [[u16; 4]; 4],
+    /// Palettes sent with PAL_TRN, picked from with PAL_SET
+    system_palettes: Vec<[u16; 4]>,
+    attributes: Attributes,
+    attribute_files: Vec<u8>,
+    border: Border,
+    mask: Mask,
+    players: u8,
+    player: u8,
+    /// Last value written to P1
+    p1: u8,
+    /// Transfer waiting for a frame, with the frame it was asked during
+    transfer: Option<(Transfer, u64)>,
+    frames: u64,
+    /// Colour of every pixel of the screen, as shown
+    screen: Vec<u16>,
+}
+
+impl Sgb {
+    pub fn new(commands_enabled: bool) -> Sgb {
+        Sgb {
+            commands_enabled,
+            receiver: PacketReceiver::new(),
+            command: Vec::new(),
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: vec![[0; 4]; SYSTEM_PALETTES],
+            attributes: Attributes::new(),
+            attribute_files: vec![0; FILES * FILE_SIZE],
+            border: Border::new(),
+            mask: Mask::Off,
+            players: 1,
+            player: 0,
+            p1: 0xff,
+            transfer: None,
+            frames: 0,
+            screen: vec![DEFAULT_PALETTE[0]; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    /// Controller the game reads through P1
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+
+    /// Follow a write to P1, which sends commands and moves to the next
+    /// controller
+    pub fn write_p1(&mut self, value: u8) {
+        if !self.commands_enabled {
+            return;
+        }
+
+        // P15 going back high moves to the next controller
+        if self.players > 1 && value & P15 != 0 && self.p1 & P15 == 0 {
+            self.player = (self.player + 1) % self.players;
+        }
+        self.p1 = value;
+
+        if let Some(packet) = self.receiver.write(value) {
+            self.receive(&packet);
+        }
+    }
+
+    /// Add a packet to the command in progress, running the command once all
+    /// of its packets are in. The first packet tells how many there are.
+    fn receive(&mut self, packet: &[u8; PACKET_SIZE]) {
+        self.command.extend_from_slice(packet);
+        let packets = usize::from(self.command[0] & PACKET_COUNT_MASK).max(1);
+        if self.command.len() >= packets * PACKET_SIZE {
+            let command = mem::take(&mut self.command);
+            self.run(&command);
+        }
+    }
+
+    fn run(&mut self, data: &[u8]) {
+        if cfg!(feature = "verbose") {
+            println!("SGB command {:02x}: {:02x?}", data[0] >> 3, &data[1..]);
+        }
+
+        match data[0] >> 3 {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attributes.block(data),
+            ATTR_LIN => self.attributes.line(data),
+            ATTR_DIV => self.attributes.divide(data),
+            ATTR_CHR => self.attributes.characters(data),
+            PAL_SET => {
+                for i in 0..4 {
+                    let palette = usize::from(read_word(&data[1..], i)) % SYSTEM_PALETTES;
+                    self.palettes[i] = self.system_palettes[palette];
+                }
+                self.set_attributes(data[9]);
+            }
+            ATTR_SET => self.set_attributes(data[1] | APPLY_ATTRIBUTE_FILE),
+            MASK_EN => {
+                self.mask = match data[1] & 0b11 {
+                    1 => Mask::Freeze,
+                    2 => Mask::Black,
+                    3 => Mask::Color0,
+                    _ => Mask::Off,
+                }
+            }
+            MLT_REQ => {
+                self.players = match data[1] & 0b11 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                };
+                self.player = 0;
+            }
+            PAL_TRN => self.transfer = Some((Transfer::Palettes, self.frames)),
+            CHR_TRN => {
+                let high = data[1] & 1 != 0;
+                self.transfer = Some((Transfer::BorderTiles(high), self.frames));
+            }
+            PCT_TRN => self.transfer = Some((Transfer::BorderMap, self.frames)),
+            ATTR_TRN => self.transfer = Some((Transfer::AttributeFiles, self.frames)),
+            // Sound and the commands for the SNES itself don't change the
+            // picture
+            _ => {}
+        }
+    }
+
+    /// PAL01, PAL23, PAL03 and PAL12: colour 0, shared by all palettes,
+    /// followed by colours 1 to 3 of both palettes
+    fn set_palettes(&mut self, first: usize, second: usize, data: &[u8]) {
+        let colors = &data[1..];
+        self.palettes[0][0] = read_color(colors, 0);
+        for i in 1..4 {
+            self.palettes[first][i] = read_color(colors, i);
+            self.palettes[second][i] = read_color(colors, i + 3);
+        }
+    }
+
+    /// Apply an attribute file if `flags` asks to, and cancel the mask if it
+    /// asks to
+    fn set_attributes(&mut self, flags: u8) {
+        let file = usize::from(flags & ATTRIBUTE_FILE_MASK);
+        if flags & APPLY_ATTRIBUTE_FILE != 0 && file < FILES {
+            let offset = file * FILE_SIZE;
+            self.attributes
+                .load_file(&self.attribute_files[offset..offset + FILE_SIZE]);
+        }
+        if flags & CANCEL_MASK != 0 {
+            self.mask = Mask::Off;
+        }
+    }
+
+    /// Colour a finished frame from its shades, and read the data of a
+    /// transfer from it. Transfers wait for the first frame drawn entirely
+    /// after they were asked for.
+    pub fn frame(&mut self, shades: &[u8]) {
+        self.frames += 1;
+
+        if let Some((transfer, frame)) = self.transfer {
+            if self.frames >= frame + 2 {
+                self.transfer = None;
+                self.load(transfer, &screen_tiles(shades));
+            }
+        }
+
+        match self.mask {
+            Mask::Off => {
+                for (i, (color, &shade)) in self.screen.iter_mut().zip(shades).enumerate() {
+                    let palette = self.attributes.palette(i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+                    *color = match shade {
+                        0 => self.palettes[0][0],
+                        shade => self.palettes[usize::from(palette)][usize::from(shade)],
+                    };
+                }
+            }
+            Mask::Freeze => {}
+            Mask::Black => self.screen.iter_mut().for_each(|color| *color = 0),
+            Mask::Color0 => {
+                let color0 = self.palettes[0][0];
+                self.screen.iter_mut().for_each(|color| *color = color0);
+            }
+        }
+    }
+
+    fn load(&mut self, transfer: Transfer, data: &[u8]) {
+        match transfer {
+            Transfer::Palettes => {
+                for (i, palette) in self.system_palettes.iter_mut().enumerate() {
+                    for (j, color) in palette.iter_mut().enumerate() {
+                        *color = read_color(data, i * 4 + j);
+                    }
+                }
+            }
+            Transfer::BorderTiles(high) => self.border.load_tiles(data, high),
+            Transfer::BorderMap => self.border.load_map(data),
+            Transfer::AttributeFiles => {
+                let size = self.attribute_files.len();
+                self.attribute_files.copy_from_slice(&data[..size]);
+            }
+        }
+    }
+
+    /// 15 bit colour of every pixel of the screen, row by row
+    pub fn screen(&self) -> &[u16] {
+        &self.screen
+    }
+
+    /// The whole 256×224 SNES picture as 8 bit RGBA: the border, drawn over
+    /// the screen, given as 8 bit RGBA, and the background colour
+    pub fn compose(&self, screen: &[u8]) -> Vec<u8> {
+        let backdrop = ColorCorrection::Raw.rgba(self.palettes[0][0]);
+        let mut picture = Vec::with_capacity(SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT * 4);
+
+        for (i, pixel) in self.border.pixels().into_iter().enumerate() {
+            let (x, y) = (i % SGB_SCREEN_WIDTH, i / SGB_SCREEN_WIDTH);
+            let on_screen = (SCREEN_LEFT..SCREEN_LEFT + SCREEN_WIDTH).contains(&x)
+                && (SCREEN_TOP..SCREEN_TOP + SCREEN_HEIGHT).contains(&y);
+
+            match pixel {
+                Some(color) => picture.extend_from_slice(&ColorCorrection::Raw.rgba(color)),
+                None if on_screen => {
+                    let j = ((y - SCREEN_TOP) * SCREEN_WIDTH + x - SCREEN_LEFT) * 4;
+                    picture.extend_from_slice(&screen[j..j + 4]);
+                }
+                None => picture.extend_from_slice(&backdrop),
+            }
+        }
+
+        picture
+    }
+}
+
+/// The 4096 bytes a VRAM transfer sends, read back as 2 bit tiles from the
+/// first 256 tiles on screen, 20 to a row
+fn screen_tiles(shades: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; TRANSFER_SIZE];
+    for (tile, bytes) in data.chunks_mut(TILE_SIZE).enumerate() {
+        let (left, top) = ((tile % COLUMNS) * 8, (tile / COLUMNS) * 8);
+        for row in 0..8 {
+            for column in 0..8 {
+                let shade = shades[(top + row) * SCREEN_WIDTH + left + column];
+                bytes[row * 2] |= (shade & 1) << (7 - column);
+                bytes[row * 2 + 1] |= ((shade >> 1) & 1) << (7 - column);
+            }
+        }
+    }
+    data
+}
+
+/// Little endian word `i` of `data`
+fn read_word(data: &[u8], i: usize) -> u16 {
+    u16::from(data[i * 2]) | u16::from(data[i * 2 + 1]) << 8
+}
+
+/// 15 bit colour `i` of `data`, with red in the lowest bits like on the CGB
+fn read_color(data: &[u8], i: usize) -> u16 {
+    read_word(data, i) & 0x7fff
+}
+
+#[cfg(test)]
+mod test {
+    use super::packet::test::pulses;
+    use super::*;
+
+    /// Send a command, split into as many packets as it needs
+    fn send(sgb: &mut Sgb, command: &[u8]) {
+        for chunk in command.chunks(PACKET_SIZE) {
+            let mut packet = [0; PACKET_SIZE];
+            packet[..chunk.len()].copy_from_slice(chunk);
+            for p1 in pulses(&packet) {
+                sgb.write_p1(p1);
+            }
+        }
+    }
+
+    /// A frame whose first tiles show `data`, as a VRAM transfer sends it
+    fn shades_showing(data: &[u8]) -> Vec<u8> {
+        let mut shades = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        for (tile, bytes) in data.chunks(TILE_SIZE).enumerate() {
+            let (left, top) = ((tile % COLUMNS) * 8, (tile / COLUMNS) * 8);
+            for row in 0..8 {
+                for column in 0..8 {
+                    let low = (bytes[row * 2] >> (7 - column)) & 1;
+                    let high = (bytes[row * 2 + 1] >> (7 - column)) & 1;
+                    shades[(top + row) * SCREEN_WIDTH + left + column] = high << 1 | low;
+                }
+            }
+        }
+        shades
+    }
+
+    /// Run a VRAM transfer the way games do: ask for it, then show the data
+    fn transfer(sgb: &mut Sgb, command: &[u8], data: &[u8]) {
+        send(sgb, command);
+        let shades = shades_showing(data);
+        sgb.frame(&shades);
+        sgb.frame(&shades);
+    }
+
+    fn gradient() -> Vec<u8> {
+        (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+            .map(|i| (i % 4) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_colors() {
+        let mut sgb = Sgb::new(true);
+        sgb.frame(&gradient());
+        assert_eq!(&sgb.screen()[..4], &DEFAULT_PALETTE);
+    }
+
+    #[test]
+    fn test_palettes() {
+        let mut sgb = Sgb::new(true);
+        #[rustfmt::skip]
+        send(&mut sgb, &[
+            PAL12 << 3 | 1,
+            0x00, 0x01,
+            0x11, 0x00, 0x12, 0x00, 0x13, 0x00,
+            0x21, 0x00, 0x22, 0x00, 0xff, 0xff,
+        ]);
+        // Second column of cells in palette 2
+        send(&mut sgb, &[ATTR_LIN << 3 | 1, 1, 0x40 | 1]);
+        sgb.frame(&gradient());
+
+        assert_eq!(&sgb.screen()[..4], &[0x0100, 0x265b, 0x10b5, 0x2866]);
+        assert_eq!(&sgb.screen()[8..12], &[0x0100, 0x0021, 0x0022, 0x7fff]);
+        // Palette 1 is only used after being picked
+        send(&mut sgb, &[ATTR_LIN << 3 | 1, 1, 0x20]);
+        sgb.frame(&gradient());
+        assert_eq!(&sgb.screen()[..4], &[0x0100, 0x0011, 0x0012, 0x0013]);
+    }
+
+    #[test]
+    fn test_commands_disabled() {
+        let mut sgb = Sgb::new(false);
+        send(&mut sgb, &[PAL01 << 3 | 1, 0x00, 0x01]);
+        sgb.frame(&gradient());
+        assert_eq!(sgb.screen()[0], DEFAULT_PALETTE[0]);
+    }
+
+    #[test]
+    fn test_command_over_several_packets() {
+        let mut sgb = Sgb::new(true);
+        let mut command = [0; PACKET_SIZE * 2];
+        command[..6].copy_from_slice(&[ATTR_CHR << 3 | 2, 0, 0, 72, 0, 0]);
+        // The last cell set is in the second packet
+        command[6 + 17] = 0b11;
+
+        send(&mut sgb, &command[..PACKET_SIZE]);
+        assert_eq!(sgb.attributes.palette(11 * 8, 24), 0);
+
+        send(&mut sgb, &command[PACKET_SIZE..]);
+        assert_eq!(sgb.attributes.palette(11 * 8, 24), 3);
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut sgb = Sgb::new(true);
+        sgb.frame(&gradient());
+
+        send(&mut sgb, &[MASK_EN << 3 | 1, 1]);
+        sgb.frame(&[3; SCREEN_WIDTH * SCREEN_HEIGHT]);
+        assert_eq!(sgb.screen()[0], DEFAULT_PALETTE[0]);
+
+        send(&mut sgb, &[MASK_EN << 3 | 1, 2]);
+        sgb.frame(&gradient());
+        assert_eq!(sgb.screen()[3], 0x0000);
+
+        send(&mut sgb, &[MASK_EN << 3 | 1, 3]);
+        sgb.frame(&gradient());
+        assert_eq!(sgb.screen()[3], DEFAULT_PALETTE[0]);
+
+        // PAL_SET can cancel the mask
+        send(
+            &mut sgb,
+            &[PAL_SET << 3 | 1, 0, 0, 0, 0, 0, 0, 0, 0, CANCEL_MASK],
+        );
+        sgb.frame(&gradient());
+        assert_eq!(sgb.screen()[3], 0x0000);
+        assert_eq!(sgb.mask, Mask::Off);
+    }
+
+    #[test]
+    fn test_palette_transfer() {
+        let mut sgb = Sgb::new(true);
+        let mut data = vec![0; TRANSFER_SIZE];
+        // Palette 300
+        data[2400..2408].copy_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00]);
+
+        send(&mut sgb, &[PAL_TRN << 3 | 1]);
+        let shades = shades_showing(&data);
+        sgb.frame(&shades);
+        // Nothing is read from the frame the command was sent during
+        assert_eq!(sgb.system_palettes[300], [0; 4]);
+        sgb.frame(&shades);
+        assert_eq!(sgb.system_palettes[300], [0x0000, 0x0001, 0x0002, 0x0003]);
+
+        send(
+            &mut sgb,
+            &[PAL_SET << 3 | 1, 0x2c, 0x01, 0, 0, 0, 0, 0, 0, 0],
+        );
+        sgb.frame(&gradient());
+        assert_eq!(&sgb.screen()[..4], &[0x0000, 0x0001, 0x0002, 0x0003]);
+    }
+
+    #[test]
+    fn test_attribute_transfer() {
+        let mut sgb = Sgb::new(true);
+        let mut data = vec![0; TRANSFER_SIZE];
+        // First cell of file 2 in palette 1
+        data[FILE_SIZE * 2] = 0b0100_0000;
+        transfer(&mut sgb, &[ATTR_TRN << 3 | 1], &data);
+
+        send(&mut sgb, &[ATTR_SET << 3 | 1, 2]);
+        assert_eq!(sgb.attributes.palette(7, 7), 1);
+        assert_eq!(sgb.attributes.palette(8, 7), 0);
+
+        // Or applied by PAL_SET
+        send(&mut sgb, &[ATTR_SET << 3 | 1, 0]);
+        assert_eq!(sgb.attributes.palette(7, 7), 0);
+        send(&mut sgb, &[PAL_SET << 3 | 1, 0, 0, 0, 0, 0, 0, 0, 0, 0x82]);
+        assert_eq!(sgb.attributes.palette(7, 7), 1);
+    }
+
+    #[test]
+    fn test_multiplayer() {
+        let mut sgb = Sgb::new(true);
+        sgb.write_p1(0x10);
+        sgb.write_p1(0x30);
+        assert_eq!(sgb.player(), 0);
+
+        send(&mut sgb, &[MLT_REQ << 3 | 1, 3]);
+        let players: Vec<u8> = (0..5)
+            .map(|_| {
+                sgb.write_p1(0x10);
+                sgb.write_p1(0x30);
+                sgb.player()
+            })
+            .collect();
+        assert_eq!(players, vec![1, 2, 3, 0, 1]);
+
+        send(&mut sgb, &[MLT_REQ << 3 | 1, 0]);
+        sgb.write_p1(0x10);
+        sgb.write_p1(0x30);
+        assert_eq!(sgb.player(), 0);
+    }
+
+    #[test]
+    fn test_border() {
+        let mut sgb = Sgb::new(true);
+        let mut tiles = vec![0; TRANSFER_SIZE];
+        // Tile 1 is colour 1 all over
+        for byte in tiles[32..48].iter_mut().step_by(2) {
+            *byte = 0xff;
+        }
+        let mut map = vec![0; TRANSFER_SIZE];
+        // Tile 1 in palette 4 at the top left corner and over the screen
+        map[0] = 0x01;
+        map[1] = 0x10;
+        map[(5 * 32 + 6) * 2] = 0x01;
+        map[(5 * 32 + 6) * 2 + 1] = 0x10;
+        map[2048 + 2] = 0x1f;
+
+        transfer(&mut sgb, &[CHR_TRN << 3 | 1, 0], &tiles);
+        transfer(&mut sgb, &[PCT_TRN << 3 | 1], &map);
+
+        let screen = vec![0x42; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+        let picture = sgb.compose(&screen);
+        assert_eq!(picture.len(), SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT * 4);
+
+        let pixel = |x: usize, y: usize| {
+            let i = (y * SGB_SCREEN_WIDTH + x) * 4;
+            &picture[i..i + 4]
+        };
+        let red = [0xff, 0x00, 0x00, 0xff];
+        assert_eq!(pixel(0, 0), red);
+        assert_eq!(pixel(8, 0), ColorCorrection::Raw.rgba(DEFAULT_PALETTE[0]));
+        assert_eq!(pixel(SCREEN_LEFT, SCREEN_TOP), red);
+        assert_eq!(pixel(SCREEN_LEFT + 8, SCREEN_TOP), [0x42; 4]);
+        assert_eq!(
+            pixel(255, 223),
+            ColorCorrection::Raw.rgba(DEFAULT_PALETTE[0])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Packets sent to the SGB one bit at a time through P1.
+//!
+//! A packet starts with both P14 and P15 pulled low, followed by 128 bits
+//! from the lowest bit of the first byte, each a pulse of P14 low for 0 or
+//! P15 low for 1 with both lines back high in between, and a final 0.
+
+pub const PACKET_SIZE: usize = 16;
+
+const PACKET_BITS: usize = PACKET_SIZE * 8;
+
+// P1 select lines, as they are written
+const P14: u8 = 0b0001_0000;
+pub const P15: u8 = 0b0010_0000;
+const LINES: u8 = P14 | P15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Idle,
+    /// Receiving the bit at this position
+    Receiving(usize),
+    /// Waiting for the stop bit after the last bit
+    Stop,
+}
+
+/// Assembles the pulses written to P1 into packets
+pub struct PacketReceiver {
+    state: State,
+    packet: [u8; PACKET_SIZE],
+    /// Whether both lines went back high since the last pulse
+    released: bool,
+}
+
+impl PacketReceiver {
+    pub fn new() -> PacketReceiver {
+        PacketReceiver {
+            state: State::Idle,
+            packet: [0; PACKET_SIZE],
+            released: false,
+        }
+    }
+
+    /// Follow a write to P1, returning the packet it completes, if any
+    pub fn write(&mut self, p1: u8) -> Option<[u8; PACKET_SIZE]> {
+        let lines = p1 & LINES;
+        if lines == LINES {
+            self.released = true;
+            return None;
+        }
+        if lines == 0 {
+            self.state = State::Receiving(0);
+            self.packet = [0; PACKET_SIZE];
+            self.released = false;
+            return None;
+        }
+
+        // Every pulse is a single bit, however many times it is written
+        if !self.released {
+            return None;
+        }
+        self.released = false;
+        let bit = lines == P14;
+
+        match self.state {
+            State::Idle => None,
+            State::Receiving(position) => {
+                if bit {
+                    self.packet[position / 8] |= 1 << (position % 8);
+                }
+                self.state = if position + 1 == PACKET_BITS {
+                    State::Stop
+                } else {
+                    State::Receiving(position + 1)
+                };
+                None
+            }
+            State::Stop => {
+                self.state = State::Idle;
+                // A packet that doesn't end with a 0 is dropped
+                if bit {
+                    None
+                } else {
+                    Some(self.packet)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// The P1 writes sending a packet, the way games do
+    pub fn pulses(packet: &[u8; PACKET_SIZE]) -> Vec<u8> {
+        let mut writes = vec![0x00, LINES];
+        for position in 0..PACKET_BITS {
+            let bit = packet[position / 8] & (1 << (position % 8)) != 0;
+            writes.push(if bit { P14 } else { P15 });
+            writes.push(LINES);
+        }
+        writes.push(P15);
+        writes.push(LINES);
+        writes
+    }
+
+    fn send(receiver: &mut PacketReceiver, writes: &[u8]) -> Vec<[u8; PACKET_SIZE]> {
+        writes
+            .iter()
+            .filter_map(|&p1| receiver.write(p1 | 0xc0))
+            .collect()
+    }
+
+    #[test]
+    fn test_receive() {
+        let mut packet = [0; PACKET_SIZE];
+        packet[0] = 0x89;
+        packet[15] = 0x80;
+
+        let mut receiver = PacketReceiver::new();
+        assert_eq!(send(&mut receiver, &pulses(&packet)), vec![packet]);
+    }
+
+    #[test]
+    fn test_repeated_writes() {
+        let packet = [0xa5; PACKET_SIZE];
+        let writes: Vec<u8> = pulses(&packet)
+            .into_iter()
+            .flat_map(|p1| vec![p1, p1, p1])
+            .collect();
+
+        let mut receiver = PacketReceiver::new();
+        assert_eq!(send(&mut receiver, &writes), vec![packet]);
+    }
+
+    #[test]
+    fn test_ignored_writes() {
+        let mut receiver = PacketReceiver::new();
+        // Reading the joypad without a reset pulse first
+        assert!(send(&mut receiver, &[P15, LINES, P14, LINES]).is_empty());
+
+        // A reset pulse in the middle starts over
+        let packet = [0x11; PACKET_SIZE];
+        let mut writes = pulses(&[0xff; PACKET_SIZE])[..40].to_vec();
+        writes.extend(pulses(&packet));
+        assert_eq!(send(&mut receiver, &writes), vec![packet]);
+
+        // No stop bit
+        let mut writes = pulses(&packet);
+        let last = writes.len() - 2;
+        writes[last] = P14;
+        assert!(send(&mut receiver, &writes).is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+//! A frontend running the game in the terminal. Every character cell shows
+//! two pixels stacked on top of each other, drawn with 24 bit ANSI colours
+//! as the foreground and background of an upper half block, and the
+//! keyboard is read in raw mode for the joypad.
+
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use errors::{Error, ErrorKind, Result};
+use game_boy::{GameBoy, SCREEN_HEIGHT, SCREEN_WIDTH};
+use joypad::Button;
+
+// A frame takes 70224 cycles of the 4.194304 MHz clock
+const FRAME_DURATION: Duration = Duration::from_nanos(16_742_706);
+// Shortest time between two frames drawn, which no terminal redraws faster
+// than
+const MIN_DRAW_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+/// Terminals only report key presses, so a button is released once its key
+/// hasn't been seen for this long by default. Holding a key relies on the
+/// keyboard's auto repeat to keep the button down, so this has to be longer
+/// than the delay before the first repeat, which is 660 ms by default on X11
+/// and usually between 250 and 600 ms elsewhere.
+pub const KEY_HOLD: Duration = Duration::from_millis(660);
+
+const UPPER_HALF_BLOCK: char = '▀';
+const ESCAPE: u8 = 0x1b;
+const CTRL_C: u8 = 0x03;
+
+/// What a key press does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Button(Button),
+    Quit,
+}
+
+/// Run the game in the terminal until Q or Ctrl-C is pressed, keeping
+/// buttons down for `key_hold` after their key is seen
+pub fn run(game_boy: &mut GameBoy, key_hold: Duration) -> Result<()> {
+    let _screen = Screen::enter()?;
+    let input = read_stdin();
+    let mut joypad = HeldButtons::new(key_hold);
+
+    let mut stdout = io::stdout();
+    let mut next_frame = Instant::now();
+    let mut next_draw = next_frame;
+
+    loop {
+        let now = Instant::now();
+        while let Ok(bytes) = input.try_recv() {
+            for key in parse_keys(&bytes) {
+                match key {
+                    Key::Quit => return Ok(()),
+                    Key::Button(button) => {
+                        if joypad.press(button, now) {
+                            game_boy.set_button(button, true);
+                        }
+                    }
+                }
+            }
+        }
+        for button in joypad.release_expired(now) {
+            game_boy.set_button(button, false);
+        }
+
+        game_boy.run_frame()?;
+
+        // Writing blocks while the terminal catches up, so a slow terminal
+        // gets fewer frames instead of falling behind
+        let now = Instant::now();
+        if now >= next_draw {
+            let frame = render(&game_boy.frame_rgba(), SCREEN_WIDTH, SCREEN_HEIGHT);
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+            next_draw = now + now.elapsed().max(MIN_DRAW_INTERVAL);
+        }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Don't rush to catch up after a slow frame
+            next_frame = now;
+        }
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, restored when dropped
+struct Screen {
+    /// Terminal settings to restore, as printed by `stty -g`
+    settings: String,
+}
+
+impl Screen {
+    fn enter() -> Result<Screen> {
+        let output = stty(&["-g"])?;
+        let settings = String::from_utf8_lossy(&output).trim().to_string();
+        stty(&["raw", "-echo"])?;
+
+        // Alternate screen, hidden cursor, cleared
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        io::stdout().flush()?;
+        Ok(Screen { settings })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.settings]);
+    }
+}
+
+/// Run stty on the terminal stdin is attached to
+fn stty(args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorKind::IO,
+            "The terminal frontend needs stdin to be a terminal",
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Read stdin in the background, as it comes
+fn read_stdin() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 64];
+        while let Ok(n) = stdin.read(&mut buffer) {
+            if n == 0 || sender.send(buffer[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// The keys in a chunk of raw terminal input, ignoring the ones that don't
+/// do anything. Arrows and WASD are the directions, X and Z are A and B,
+/// Enter is Start and Space or Backspace is Select.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // Arrows are sent as ESC [ A-D, or ESC O A-D in application mode
+        if bytes[i] == ESCAPE
+            && i + 2 < bytes.len()
+            && (bytes[i + 1] == b'[' || bytes[i + 1] == b'O')
+        {
+            let button = match bytes[i + 2] {
+                b'A' => Some(Button::Up),
+                b'B' => Some(Button::Down),
+                b'C' => Some(Button::Right),
+                b'D' => Some(Button::Left),
+                _ => None,
+            };
+            keys.extend(button.map(Key::Button));
+            i += 3;
+            continue;
+        }
+
+        let key = match bytes[i].to_ascii_lowercase() {
+            b'w' => Some(Key::Button(Button::Up)),
+            b's' => Some(Key::Button(Button::Down)),
+            b'd' => Some(Key::Button(Button::Right)),
+            b'a' => Some(Key::Button(Button::Left)),
+            b'x' => Some(Key::Button(Button::A)),
+            b'z' => Some(Key::Button(Button::B)),
+            b'\r' | b'\n' => Some(Key::Button(Button::Start)),
+            b' ' | 0x7f | 0x08 => Some(Key::Button(Button::Select)),
+            b'q' | CTRL_C => Some(Key::Quit),
+            _ => None,
+        };
+        keys.extend(key);
+        i += 1;
+    }
+
+    keys
+}
+
+/// Buttons held down by recent key presses
+struct HeldButtons {
+    /// Every button held down, with when its key was last seen
+    held: Vec<(Button, Instant)>,
+    /// How long a button stays down after its key is seen
+    hold: Duration,
+}
+
+impl HeldButtons {
+    fn new(hold: Duration) -> HeldButtons {
+        HeldButtons {
+            held: Vec::new(),
+            hold,
+        }
+    }
+
+    /// Hold a button down, returning whether it was up
+    fn press(&mut self, button: Button, now: Instant) -> bool {
+        match self.held.iter_mut().find(|&&mut (held, _)| held == button) {
+            Some(held) => {
+                held.1 = now;
+                false
+            }
+            None => {
+                self.held.push((button, now));
+                true
+            }
+        }
+    }
+
+    /// Let go of the buttons whose keys haven't been seen for a while
+    fn release_expired(&mut self, now: Instant) -> Vec<Button> {
+        let hold = self.hold;
+        let (expired, held): (Vec<_>, Vec<_>) = self
+            .held
+            .drain(..)
+            .partition(|&(_, seen)| now.duration_since(seen) >= hold);
+        self.held = held;
+        expired.into_iter().map(|(button, _)| button).collect()
+    }
+}
+
+/// A frame of 8 bit RGBA pixels drawn as text, two rows of pixels per line
+/// of text, starting at the top left corner of the terminal. Colours are
+/// only sent when they change.
+pub fn render(rgba: &[u8], width: usize, height: usize) -> String {
+    let pixel = |x: usize, y: usize| {
+        let i = (y * width + x) * 4;
+        (rgba[i], rgba[i + 1], rgba[i + 2])
+    };
+
+    let mut text = String::from("\x1b[H");
+    for top in (0..height).step_by(2) {
+        let mut colors = None;
+        for x in 0..width {
+            let upper = pixel(x, top);
+            // An odd last row is drawn over black
+            let lower = if top + 1 < height {
+                pixel(x, top + 1)
+            } else {
+                (0, 0, 0)
+            };
+
+            if colors != Some((upper, lower)) {
+                let _ = write!(
+                    text,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                    upper.0, upper.1, upper.2, lower.0, lower.1, lower.2
+                );
+                colors = Some((upper, lower));
+            }
+            text.push(UPPER_HALF_BLOCK);
+        }
+
+        text.push_str("\x1b[0m");
+        // The terminal is in raw mode, so a new line doesn't go back to the
+        // first column by itself
+        if top + 2 < height {
+            text.push_str("\r\n");
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        #[rustfmt::skip]
+        let rgba = [
+            0xff, 0x00, 0x00, 0xff,   0xff, 0x00, 0x00, 0xff,
+            0x00, 0x00, 0xff, 0xff,   0x00, 0x00, 0xff, 0xff,
+            0x00, 0xff, 0x00, 0xff,   0xff, 0xff, 0xff, 0xff,
+        ];
+
+        assert_eq!(
+            render(&rgba, 2, 3),
+            "\x1b[H\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀▀\x1b[0m\r\n\
+             \x1b[38;2;0;255;0m\x1b[48;2;0;0;0m▀\
+             \x1b[38;2;255;255;255m\x1b[48;2;0;0;0m▀\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1bOCxZ\r "),
+            vec![
+                Key::Button(Button::Up),
+                Key::Button(Button::Right),
+                Key::Button(Button::A),
+                Key::Button(Button::B),
+                Key::Button(Button::Start),
+                Key::Button(Button::Select),
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1b[5~q"), vec![Key::Quit]);
+        assert_eq!(parse_keys(b"\x03"), vec![Key::Quit]);
+        assert_eq!(parse_keys(b"\x1b"), vec![]);
+    }
+
+    #[test]
+    fn test_held_buttons() {
+        let start = Instant::now();
+        let mut joypad = HeldButtons::new(KEY_HOLD);
+
+        assert!(joypad.press(Button::A, start));
+        // Auto repeat keeps the button down
+        assert!(!joypad.press(Button::A, start + KEY_HOLD / 2));
+        assert_eq!(joypad.release_expired(start + KEY_HOLD), vec![]);
+        assert_eq!(
+            joypad.release_expired(start + KEY_HOLD / 2 + KEY_HOLD),
+            vec![Button::A]
+        );
+        assert!(joypad.press(Button::A, start + KEY_HOLD * 2));
+    }
+}